@@ -0,0 +1,71 @@
+//! Throughput of the tokenizer and parser on large generated inputs. Run with
+//! `cargo bench`; files given after `--` are measured as well.
+
+use rustscript::frontend::{parser::Parser, tokenizer::{Located, Tokenizer}};
+use std::{env::args, fs::read_to_string, hint::black_box, time::{Duration, Instant}};
+
+/// How many times each measurement is repeated; the fastest run is reported.
+const RUNS: usize = 10;
+
+fn main() {
+	let generated = [1, 4, 16].into_iter()
+		.map(|megabytes| (format!("generated {} MiB", megabytes), generate(megabytes << 20)));
+	let files = args().skip(1)
+		.filter(|arg| !arg.starts_with("--"))
+		.map(|path| {
+			let source = read_to_string(&path)
+				.unwrap_or_else(|error| panic!("could not read {:?}: {}", path, error));
+			(path, source)
+		});
+
+	generated.chain(files).for_each(|(name, source)| {
+		let tokenize = measure(|| Tokenizer::new(&source).count());
+		let parse = measure(||
+			Parser::new(Located::new(&source)).parse_block().map(|block| block.0.len()));
+
+		println!("{}:", name);
+		report("tokenize", source.len(), tokenize);
+		report("parse", source.len(), parse);
+	});
+}
+
+fn measure<T>(mut run: impl FnMut() -> T) -> Duration {
+	(0..RUNS)
+		.map(|_| {
+			let start = Instant::now();
+			black_box(run());
+			start.elapsed()
+		})
+		.min()
+		.unwrap()
+}
+
+fn report(stage: &str, bytes: usize, time: Duration) {
+	let megabytes = bytes as f64 / (1 << 20) as f64;
+	println!("\t{:<10}{:>10.2?}{:>12.1} MiB/s", stage, time, megabytes / time.as_secs_f64());
+}
+
+/// Generates a crate root of at least `size` bytes, exercising every kind of
+/// token.
+fn generate(size: usize) -> String {
+	let mut source = String::with_capacity(size + 1024);
+	let mut index = 0;
+	while source.len() < size {
+		source.push_str(&format!(concat!(
+			"/// Documentation for item number {index}.\n",
+			"pub data Point{index} {{ x: i32, y: i32 }}\n",
+			"data Wrapper{index}(Point{index}); // a trailing comment\n",
+			"/* a block comment /* which nests */ */\n",
+			"fn function_{index}(a: i32, größe: str) -> str {{\n",
+			"\tlet hex: u32 = 0xDEAD_BEEF;\n",
+			"\tlet float: f64 = 6.022e23;\n",
+			"\tlet text: str = \"line\\n\\u{{1F600}} {index}\";\n",
+			"\tlet raw: str = r#\"raw \"text\"\"#;\n",
+			"\tlet character: char = '\\'';\n",
+			"\tlet result: str = function_{index}(a, größe);\n",
+			"}}\n\n"
+		), index = index));
+		index += 1;
+	}
+	source
+}
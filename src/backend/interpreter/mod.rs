@@ -0,0 +1,609 @@
+//! Runs a crate's main representation directly, without generating any code.
+//! The interpreter is the reference for what a program means: the JavaScript
+//! backend is correct when its output behaves the same way.
+
+use super::{super::frontend::{module::ItemKind, Code, Expression, Foreign, Function,
+	IntegerType, Scope, Statement}, javascript::integer};
+use itertools::Itertools;
+use std::{collections::HashMap, fmt::{Display, Formatter, Result as FMTResult},
+	io::{stderr, stdout, Write}};
+
+/// How deeply functions may call each other before the program is stopped,
+/// standing in for the stack overflow it would otherwise run into.
+const MAX_DEPTH: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	/// What blocks, and functions (which cannot return anything yet), evaluate
+	/// to.
+	Unit,
+	/// An integer, and the integer type it is used as if that is known.
+	Integer(u128, Option<IntegerType>),
+	Float(f64),
+	Boolean(bool),
+	String(Box<str>),
+	Character(char),
+	/// An instance of a user `data` type, or of one of its variants. Unnamed
+	/// fields are named by their index.
+	Data {
+		r#type: Box<str>,
+		variant: Option<Box<str>>,
+		fields: Vec<(Box<str>, Value)>
+	},
+	/// A value from outside of rustscript, which can only be passed around.
+	Foreign(Box<str>)
+}
+
+/// Writes a value as the rustscript expression it would be written as.
+impl Display for Value {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Unit => write!(f, "()"),
+			Self::Integer(integer, _) => write!(f, "{}", integer),
+			Self::Float(float) => write!(f, "{:?}", float),
+			Self::Boolean(boolean) => write!(f, "{}", boolean),
+			Self::String(string) => write!(f, "{:?}", string),
+			Self::Character(character) => write!(f, "{:?}", character),
+			Self::Data {r#type, variant, fields} => {
+				write!(f, "{}", r#type)?;
+				if let Some(variant) = variant {write!(f, "::{}", variant)?}
+
+				match fields.first() {
+					None => Ok(()),
+					Some((name, _)) if name.starts_with(|c: char| c.is_ascii_digit()) =>
+						write!(f, "({})", fields.iter().map(|(_, value)| value).join(", ")),
+					Some(_) => write!(f, " {{{}}}", fields.iter()
+						.map(|(name, value)| format!("{}: {}", name, value))
+						.join(", "))
+				}
+			},
+			Self::Foreign(name) => write!(f, "<{}>", name)
+		}
+	}
+}
+
+/// An error that stops a program while it runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeError {
+	pub message: String,
+	/// The functions being called when the error happened, innermost first.
+	pub stack: Vec<Box<str>>
+}
+
+impl RuntimeError {
+	fn new(message: impl Into<String>) -> Self {
+		Self {message: message.into(), stack: Vec::new()}
+	}
+}
+
+impl Display for RuntimeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "error: {}", self.message)?;
+		// Recursion is written once, rather than once per call.
+		cycles(&self.stack).into_iter().try_for_each(|(cycle, count)| match (cycle, count) {
+			(_, 1) => cycle.iter().try_for_each(|function| write!(f, "\n    in {}", function)),
+			([function], count) => write!(f, "\n    in {} ({} calls)", function, count),
+			(cycle, count) => {
+				cycle.iter().try_for_each(|function| write!(f, "\n    in {}", function))?;
+				write!(f, "\n    (the {} calls above, {} times)", cycle.len(), count)
+			}
+		})
+	}
+}
+
+/// The longest cycle of calls that a stack trace collapses, such as functions
+/// that call one another in turn.
+const MAX_CYCLE: usize = 8;
+
+/// Splits a stack into runs of a cycle of calls repeated some number of times,
+/// taking the cycle that covers the most calls at each point.
+fn cycles(stack: &[Box<str>]) -> Vec<(&[Box<str>], usize)> {
+	let mut cycles = Vec::new();
+	let mut start = 0;
+	while start < stack.len() {
+		let (length, count) = (1..=MAX_CYCLE.min(stack.len() - start))
+			.map(|length| {
+				let rest = &stack[start..];
+				let repeated = (length..rest.len())
+					.take_while(|&index| rest[index] == rest[index - length])
+					.count();
+				(length, 1 + repeated / length)
+			})
+			.max_by_key(|&(length, count)| (count > 1, length * count, usize::MAX - length))
+			.expect("there is at least one call left");
+		cycles.push((&stack[start..start + length], count));
+		start += length * count;
+	}
+	cycles
+}
+
+/// The values of the variables declared so far within a scope. Function
+/// arguments are held in a frame of their own, which has no scope.
+#[derive(Clone, Debug)]
+struct Frame<'c, 's> {
+	scope: Option<&'c Scope<'s>>,
+	values: HashMap<Box<str>, Value>
+}
+
+impl<'c, 's> Frame<'c, 's> {
+	fn new(scope: &'c Scope<'s>) -> Self {
+		Self {scope: Some(scope), values: HashMap::new()}
+	}
+}
+
+/// The frames a piece of code is evaluated within, innermost last. Functions
+/// only see the items around them, so a call starts from the frames of the
+/// scopes it was declared in, emptied of their values.
+type Environment<'c, 's> = Vec<Frame<'c, 's>>;
+
+pub struct Interpreter<'c, 's, 'o> {
+	root: &'c Code<'s>,
+	/// The values of the crate root's variables.
+	values: HashMap<Box<str>, Value>,
+	/// Where `console.log` writes to.
+	output: Box<dyn Write + 'o>,
+	depth: usize
+}
+
+impl<'c, 's, 'o> Interpreter<'c, 's, 'o> {
+	/// Creates an interpreter for the crate whose root is `root`, writing to
+	/// standard output.
+	pub fn new(root: &'c Code<'s>) -> Self {
+		Self {root, values: HashMap::new(), output: Box::new(stdout()), depth: 0}
+	}
+
+	/// Writes what the program prints to `output` instead.
+	pub fn with_output(self, output: impl Write + 'o) -> Self {
+		Self {output: Box::new(output), ..self}
+	}
+
+	/// Runs the top level statements of every module, and then of the crate
+	/// root, as the JavaScript modules do when they are loaded.
+	pub fn load(&mut self) -> Result<(), RuntimeError> {
+		// Modules are loaded before the modules they are nested in.
+		fn modules<'c, 's>(code: &'c Code<'s>, environment: &mut Environment<'c, 's>,
+				order: &mut Vec<(Environment<'c, 's>, &'c Code<'s>)>) {
+			code.scope.modules.iter()
+				.sorted_by(|(a, _), (b, _)| a.cmp(b))
+				.for_each(|(_, module)| {
+					environment.push(Frame::new(&module.code.scope));
+					modules(&module.code, environment, order);
+					order.push((environment.clone(), &module.code));
+					environment.pop();
+				});
+		}
+
+		let mut environment = vec![Frame::new(&self.root.scope)];
+		let mut order = Vec::new();
+		modules(self.root, &mut environment, &mut order);
+		order.push((environment, self.root));
+
+		order.into_iter().try_for_each(|(mut environment, code)| {
+			self.statements(code, &mut environment)?;
+			if std::ptr::eq(code, self.root)
+				{self.values = environment.pop().unwrap().values}
+			Ok(())
+		})
+	}
+
+	/// Calls a function declared at the top level of the crate root.
+	pub fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+		let function = self.root.scope.functions.iter()
+			.find(|(function, _)| **function == name)
+			.map(|(_, function)| function)
+			.ok_or_else(|| RuntimeError::new(format!("unknown function {:?}", name)))?;
+		self.call_function(name, function, vec![Frame::new(&self.root.scope)], arguments)
+	}
+
+	/// Evaluates an expression at the end of the crate root, where its variables
+	/// have the values `load` or `define` gave them.
+	pub fn evaluate(&mut self, expression: &'c Expression<'s>) -> Result<Value, RuntimeError> {
+		let mut environment = vec![Frame {
+			scope: Some(&self.root.scope),
+			values: self.values.clone()
+		}];
+		self.expression(expression, &mut environment)
+	}
+
+	/// Gives a variable of the crate root a value without evaluating it.
+	pub fn define(&mut self, name: &str, value: Value) {
+		self.values.insert(name.into(), value);
+	}
+
+	/// The value of a variable of the crate root, if it has one.
+	pub fn value(&self, name: &str) -> Option<&Value> {
+		self.values.get(name)
+	}
+
+	/// Runs the statements of a block in order, adding each variable to the
+	/// innermost frame.
+	fn statements(&mut self, code: &'c Code<'s>, environment: &mut Environment<'c, 's>)
+			-> Result<(), RuntimeError> {
+		code.statements.iter().try_for_each(|statement| {
+			let value = self.expression(statement.expression(), environment)?;
+			if let Statement::Variable(variable) = statement
+				{environment.last_mut().unwrap().values.insert(Box::from(&*variable.name), value);}
+			Ok(())
+		})
+	}
+
+	fn expression(&mut self, expression: &'c Expression<'s>,
+			environment: &mut Environment<'c, 's>) -> Result<Value, RuntimeError> {
+		Ok(match expression {
+			Expression::Block(code) => {
+				environment.push(Frame::new(&code.scope));
+				let result = self.statements(code, environment);
+				environment.pop();
+				result?;
+
+				Value::Unit
+			},
+			Expression::LiteralInteger {value, integer, ..} => Value::Integer(*value, *integer),
+			// Floats are doubles whatever their type, as they are in JavaScript.
+			Expression::LiteralFloat {value, ..} => Value::Float(value.parse()
+				.expect("float literals are validated by the tokenizer")),
+			Expression::LiteralBoolean(boolean) => Value::Boolean(*boolean),
+			Expression::LiteralString(string) => Value::String(Box::from(&**string)),
+			Expression::LiteralCharacter(character) => Value::Character(*character),
+
+			Expression::Variable {name, ..} => environment.iter().rev()
+				.find_map(|frame| match frame.values.get(&**name) {
+					Some(value) => Some(value.clone()),
+					None => frame.scope
+						.and_then(|scope| scope.values.get(name))
+						.and_then(|value| value.foreign.as_ref())
+						.map(|foreign| Value::Foreign(foreign.name.clone()))
+				})
+				.ok_or_else(|| RuntimeError::new(
+					format!("{:?} was used before it was initialized", name)))?,
+
+			Expression::FunctionCall {name, arguments, ..} => {
+				let arguments = arguments.iter()
+					.map(|argument| self.expression(argument, environment))
+					.collect::<Result<_, _>>()?;
+				let (function, declared) = self.function(name, environment);
+				self.call_function(name, function, declared, arguments)?
+			}
+		})
+	}
+
+	/// Finds the function a name refers to, along with the frames of the scopes
+	/// it was declared within.
+	fn function(&self, name: &str, environment: &Environment<'c, 's>)
+			-> (&'c Function<'s>, Environment<'c, 's>) {
+		let scopes = environment.iter().filter_map(|frame| frame.scope).collect::<Vec<_>>();
+		let (index, scope) = scopes.iter().enumerate().rev()
+			.find(|(_, scope)| scope.functions.keys().any(|function| &**function == name)
+				|| scope.imports.iter().any(|(import, item)|
+					&**import == name && item.kind == ItemKind::Function))
+			.expect("function calls are resolved by the frontend");
+
+		match scope.functions.iter().find(|(function, _)| **function == name) {
+			Some((_, function)) => (function, scopes[..=index].iter()
+				.map(|scope| Frame::new(scope))
+				.collect()),
+
+			// Imports are resolved to the module the function is declared in.
+			None => {
+				let (_, import) = scope.imports.iter()
+					.find(|(import, _)| **import == name)
+					.unwrap();
+				let mut declared = vec![Frame::new(&self.root.scope)];
+				let mut code = self.root;
+				import.path[..import.path.len() - 1].iter().for_each(|module| {
+					code = &code.scope.modules[module].code;
+					declared.push(Frame::new(&code.scope));
+				});
+
+				(&code.scope.functions[import.path.last().unwrap()], declared)
+			}
+		}
+	}
+
+	fn call_function(&mut self, name: &str, function: &'c Function<'s>,
+			mut environment: Environment<'c, 's>, arguments: Vec<Value>)
+				-> Result<Value, RuntimeError> {
+		if let Some(foreign) = &function.foreign {return self.foreign(foreign, arguments)}
+
+		if self.depth == MAX_DEPTH
+			{return Err(RuntimeError::new(format!("maximum call depth of {} exceeded", MAX_DEPTH)))}
+		self.depth += 1;
+
+		environment.push(Frame {
+			scope: None,
+			values: function.arguments.iter()
+				.map(|(name, _)| Box::from(&**name))
+				.zip(arguments)
+				.collect()
+		});
+		environment.push(Frame::new(&function.code.scope));
+		let result = self.statements(&function.code, &mut environment);
+
+		self.depth -= 1;
+		result
+			.map(|_| Value::Unit)
+			.map_err(|mut error| {
+				error.stack.push(name.into());
+				error
+			})
+	}
+
+	/// Calls a function bound through an extern block. Only a few JavaScript
+	/// globals are available to the interpreter.
+	fn foreign(&mut self, foreign: &Foreign, arguments: Vec<Value>)
+			-> Result<Value, RuntimeError> {
+		let line = arguments.iter().map(|argument| console(argument, false)).join(" ");
+		match (&*foreign.abi, &foreign.from, &*foreign.name) {
+			("js", None, "console.log" | "console.info" | "console.debug") =>
+				writeln!(self.output, "{}", line),
+			("js", None, "console.error" | "console.warn") => writeln!(stderr(), "{}", line),
+			(_, Some(from), name) => return Err(RuntimeError::new(format!(
+				"{:?} from {:?} is not available to the interpreter", name, from))),
+			(_, None, name) => return Err(RuntimeError::new(format!(
+				"{:?} is not available to the interpreter", name)))
+		}.map_err(|error| RuntimeError::new(format!("could not write output: {}", error)))?;
+
+		Ok(Value::Unit)
+	}
+}
+
+/// Writes a value the way `console.log` writes the JavaScript it compiles to.
+/// Strings are quoted only within other values.
+fn console(value: &Value, nested: bool) -> String {
+	match value {
+		Value::Unit => "undefined".into(),
+		// Wide integers are `BigInt`s, which are written with an `n`.
+		Value::Integer(integer, Some(r#type)) if integer::is_big(*r#type) =>
+			format!("{}n", integer),
+		Value::Integer(integer, _) => integer.to_string(),
+		Value::Float(float) => number(*float),
+		Value::Boolean(boolean) => boolean.to_string(),
+		Value::String(string) if nested => format!("'{}'", string.replace('\'', "\\'")),
+		Value::String(string) => string.to_string(),
+		Value::Character(character) => console(&Value::String(character.to_string().into()),
+			nested),
+		Value::Data {r#type, fields, ..} => match fields.is_empty() {
+			true => format!("{} {{}}", r#type),
+			false => format!("{} {{ {} }}", r#type, fields.iter()
+				.map(|(name, value)| match name.starts_with(|c: char| c.is_ascii_digit()) {
+					true => format!("_{}: {}", name, console(value, true)),
+					false => format!("{}: {}", name, console(value, true))
+				})
+				.join(", "))
+		},
+		Value::Foreign(name) => format!("[{}]", name)
+	}
+}
+
+/// Writes a float as JavaScript does, in exponential notation when it is very
+/// large or small.
+fn number(float: f64) -> String {
+	match float.abs() {
+		_ if float.is_nan() => "NaN".into(),
+		magnitude if magnitude.is_infinite() => match float > 0.0 {
+			true => "Infinity".into(),
+			false => "-Infinity".into()
+		},
+		magnitude if magnitude >= 1e21 || (magnitude < 1e-6 && magnitude != 0.0) => {
+			let exponential = format!("{:e}", float);
+			match exponential.contains("e-") {
+				true => exponential,
+				false => exponential.replacen('e', "e+", 1)
+			}
+		},
+		_ => float.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{console, number, Interpreter, RuntimeError, Value};
+	use crate::{backend::javascript::ModuleSystem, compiler::{Compiler, Options, Output},
+		frontend::{module::load, IntegerType}};
+	use std::{collections::BTreeSet, env::temp_dir,
+		fs::{create_dir_all, read_dir, remove_dir_all, write}, path::Path,
+		process::{self, Command}, thread};
+
+	/// Loads a compiled crate and calls its `main` function, returning what it
+	/// printed.
+	fn interpret(output: &Output) -> Result<String, RuntimeError> {
+		let mut printed = Vec::new();
+		let mut interpreter = Interpreter::new(&output.code).with_output(&mut printed);
+		interpreter.load().and_then(|_| interpreter.call("main", Vec::new()))?;
+		drop(interpreter);
+		Ok(String::from_utf8(printed).unwrap())
+	}
+
+	fn run(source: &str) -> Result<String, RuntimeError> {
+		interpret(&Compiler::new(Options::default()).compile_str(source, Path::new("")).unwrap())
+	}
+
+	/// Options compiling a crate to be run as `rustscript run --node` runs it.
+	fn node_options() -> Options {
+		Options {
+			target: ModuleSystem::CommonJS,
+			root: Some("main.js".into()),
+			call_main: true,
+			..Options::default()
+		}
+	}
+
+	/// Writes a crate compiled to `target` to `directory` and runs it with
+	/// Node.js, returning what it printed, or `None` if Node.js cannot be run.
+	fn run_with_node(output: &Output, target: ModuleSystem, directory: &Path)
+			-> Option<Result<String, String>> {
+		remove_dir_all(directory).ok();
+		let r#type = match target {
+			ModuleSystem::EcmaScript => "module",
+			ModuleSystem::CommonJS => "commonjs"
+		};
+		create_dir_all(directory).unwrap();
+		write(directory.join("package.json"), format!("{{\"type\": \"{}\"}}", r#type)).unwrap();
+		for file in &output.files {
+			let path = directory.join(&file.path);
+			create_dir_all(path.parent().unwrap()).unwrap();
+			write(path, &file.contents).unwrap();
+		}
+		let node = Command::new("node").arg(directory.join(&output.root)).output().ok()?;
+		remove_dir_all(directory).ok();
+		Some(match node.status.success() {
+			true => Ok(String::from_utf8_lossy(&node.stdout).into()),
+			false => Err(String::from_utf8_lossy(&node.stderr).into())
+		})
+	}
+
+	#[test]
+	fn values_are_written_as_javascript_writes_them() {
+		let source = r#"
+			extern "js" {
+				fn log(value: u64) = "console.log";
+				fn log_f32(value: f32) = "console.log";
+				fn log_text(text: str, character: char) = "console.log";
+			}
+
+			fn main() {
+				log(18446744073709551615);
+				log_f32(1.0);
+				log_text("it's", '\'');
+			}
+		"#;
+		assert_eq!(run(source).unwrap(), "18446744073709551615n\n1\nit's '\n");
+
+		assert_eq!([1e21, 1.5e-7, 0.5, f64::NAN, f64::NEG_INFINITY].map(number),
+			["1e+21", "1.5e-7", "0.5", "NaN", "-Infinity"]);
+		// Values are written as rustscript, rather than as JavaScript, elsewhere.
+		let wide = Value::Integer(5, IntegerType::from_name("i64"));
+		assert_eq!((wide.to_string(), console(&wide, false)), ("5".into(), "5n".into()));
+		assert_eq!(Value::String("a\"b".into()).to_string(), r#""a\"b""#);
+	}
+
+	#[test]
+	fn runaway_recursion_is_stopped() {
+		// The stack of a test's thread is smaller than that of the main thread.
+		let error = thread::Builder::new()
+			.stack_size(64 << 20)
+			.spawn(|| run("fn f() {g();} fn g() {f();} fn main() {f();}").unwrap_err())
+			.unwrap()
+			.join()
+			.unwrap();
+		assert_eq!(error.message, "maximum call depth of 1000 exceeded");
+		assert_eq!(error.stack.len(), 1000);
+		assert_eq!(error.to_string(), "error: maximum call depth of 1000 exceeded\n    \
+			in f\n    in g\n    (the 2 calls above, 499 times)\n    in f\n    in main");
+	}
+
+	#[test]
+	fn recursion_is_written_once() {
+		let error = |stack: &[&str]| RuntimeError {
+			message: "stopped".into(),
+			stack: stack.iter().map(|&function| function.into()).collect()
+		}.to_string();
+		assert_eq!(error(&["f", "f", "f", "main"]),
+			"error: stopped\n    in f (3 calls)\n    in main");
+		assert_eq!(error(&["h", "f", "g", "h", "f", "g", "h", "main"]), "error: stopped\n    \
+			in h\n    in f\n    in g\n    (the 3 calls above, 2 times)\n    in h\n    in main");
+		assert_eq!(error(&["f", "g", "f", "main"]),
+			"error: stopped\n    in f\n    in g\n    in f\n    in main");
+	}
+
+	#[test]
+	fn foreign_modules_are_not_available() {
+		let source = r#"extern "js" from "node:os" {fn hostname() -> str;}
+			fn main() {hostname();}"#;
+		let error = run(source).unwrap_err();
+		assert_eq!(error.message,
+			"\"hostname\" from \"node:os\" is not available to the interpreter");
+		assert_eq!(error.stack, ["main".into()]);
+	}
+
+	/// Runs every crate root in the `test` directory both with the interpreter
+	/// and, as `rustscript run --node` does, with Node.js, which must print the
+	/// same. Crates that use what the interpreter cannot run are left out, as
+	/// is everything if Node.js cannot be run.
+	#[test]
+	fn javascript_behaves_as_the_interpreter() {
+		let directory = temp_dir().join(format!("rustscript-differential-{}", process::id()));
+
+		// Files that other files load as modules are not crate roots.
+		let files: BTreeSet<_> = read_dir("test").unwrap()
+			.map(|entry| entry.unwrap().path())
+			.collect();
+		let mut modules = BTreeSet::new();
+		files.iter().for_each(|path| {
+			let mut sources = BTreeSet::new();
+			load(path, &mut sources).unwrap();
+			modules.extend(sources.into_iter().filter(|source| source != path));
+		});
+
+		let mut compared = 0;
+		for path in files.difference(&modules) {
+			let output = Compiler::new(node_options()).compile_file(path)
+				.unwrap_or_else(|failure| panic!("{}: {}", path.display(), failure));
+			let interpreted = match interpret(&output) {
+				Ok(printed) => printed,
+				Err(error) if error.message.ends_with("not available to the interpreter") =>
+					continue,
+				Err(error) => panic!("{}: {}", path.display(), error)
+			};
+
+			let Some(node) = run_with_node(&output, ModuleSystem::CommonJS, &directory) else {return};
+			let printed = node.unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+			assert_eq!(printed, interpreted, "{}", path.display());
+			compared += 1;
+		}
+		assert!(compared > 0, "no crate in the test directory can be interpreted");
+	}
+
+	#[test]
+	fn shadowed_names_are_bound_afresh() {
+		let sources = [
+			"fn main() {let x: i32 = 1; let x: bool = true; log_bool(x);}",
+			"fn f(a: i32) {let a: bool = true; log_bool(a);} fn main() {f(1);}",
+			"fn main() {fn helper() {} let helper: i32 = 1; helper(); log_i32(helper);}",
+			"data X; fn main() {let X: i32 = 1; log_i32(X);}"
+		];
+		let directory = temp_dir().join(format!("rustscript-shadowing-{}", process::id()));
+
+		for source in sources {
+			let source = format!("extern \"js\" {{fn log_i32(value: i32) = \"console.log\"; \
+				fn log_bool(value: bool) = \"console.log\";}} {}", source);
+			let output = Compiler::new(node_options()).compile_str(&source, Path::new(""))
+				.unwrap_or_else(|failure| panic!("{}: {}", source, failure));
+			let interpreted = interpret(&output).unwrap();
+			let Some(node) = run_with_node(&output, ModuleSystem::CommonJS, &directory) else {return};
+			assert_eq!(node.as_deref(), Ok(&*interpreted), "{}", source);
+		}
+	}
+
+	#[test]
+	fn reserved_words_are_exported_as_themselves() {
+		let source = r#"
+			extern "js" {fn log(value: i32) = "console.log";}
+			mod m {
+				use super::log;
+				pub fn delete() {log(1);}
+				pub mod class {
+					use super::super::log;
+					pub fn new() {log(2);}
+				}
+			}
+			use m::delete;
+			use m::class::new;
+			fn main() {delete(); new();}
+		"#;
+		let directory = temp_dir().join(format!("rustscript-reserved-{}", process::id()));
+
+		for target in [ModuleSystem::EcmaScript, ModuleSystem::CommonJS] {
+			let options = Options {target, ..node_options()};
+			let output = Compiler::new(options).compile_str(source, Path::new("")).unwrap();
+			let m = output.files.iter().find(|file| file.path == "m.js").unwrap();
+			assert!(m.contents.contains(match target {
+				ModuleSystem::EcmaScript => "export{delete$ as delete}",
+				ModuleSystem::CommonJS => "defineProperty(exports,\"delete\""
+			}), "{}", m.contents);
+
+			let Some(node) = run_with_node(&output, target, &directory) else {return};
+			assert_eq!(node.as_deref(), Ok("1\n2\n"), "{:?}", target);
+		}
+	}
+}
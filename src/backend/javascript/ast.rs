@@ -0,0 +1,1419 @@
+//! JavaScript's syntax, after ESTree. Expressions are written with only the
+//! parentheses their precedence needs, and statements with the semicolons and
+//! parentheses that keep them from being read as something else.
+
+use super::printer::{Print, Printer};
+use crate::frontend::tokenizer::Location;
+use itertools::Itertools;
+use serde::Serialize;
+use std::fmt::{Display, Formatter, Result as FMTResult, Write};
+
+#[derive(Debug, Serialize)]
+pub struct Block(pub Vec<Statement>);
+
+impl Print for Block {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		self.0.iter().enumerate().try_for_each(|(index, statement)| {
+			if index > 0 {
+				if separated(&self.0[index - 1], statement) {p.blank_line()?}
+				p.newline()?;
+			}
+			statement.print(p)?;
+			p.terminate(statement.requires_semicolon(), index + 1 == self.0.len())
+		})
+	}
+}
+
+impl Display for Block {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		f.write_str(&self.printed())
+	}
+}
+
+/// Whether a readable layout leaves a blank line between two statements. Items
+/// are set apart from the statements around them, as are imports and exports.
+fn separated(previous: &Statement, next: &Statement) -> bool {
+	use Statement::*;
+	match (previous.unlocated(), next.unlocated()) {
+		(DocComment(_), _) => false,
+		(Class(_) | Function(_), _) | (_, Class(_) | Function(_) | DocComment(_)) => true,
+		(Import(_), Import(_)) | (Export(_), Export(_)) => false,
+		(Import(_) | Export(_), _) | (_, Import(_) | Export(_)) => true,
+		_ => false
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub enum Statement {
+	Expression(Expression),
+	Block(Block),
+	Empty,
+	Return(Option<Expression>),
+	Throw(Expression),
+	If {
+		test: Expression,
+		consequent: Box<Statement>,
+		alternate: Option<Box<Statement>>
+	},
+	While {
+		test: Expression,
+		body: Box<Statement>
+	},
+
+	Variable(VariableDeclaration),
+	/// A function declaration, which always has a name.
+	Function(Function),
+	/// A class declaration, which always has a name.
+	Class(Class),
+	Import(ImportDeclaration),
+	Export(ExportDeclaration),
+
+	DocComment(DocComment),
+	/// A statement compiled from the item at a location in the source.
+	Located(Location, Box<Statement>)
+}
+
+impl Statement {
+	/// Whether or not this statement requires a `;` afterwards (ignoring ASI).
+	pub fn requires_semicolon(&self) -> bool {
+		match self {
+			Self::Block(_) | Self::Empty | Self::Function(_) | Self::Class(_)
+				| Self::DocComment(_) => false,
+			Self::If {consequent, alternate, ..} => alternate.as_deref()
+				.unwrap_or(consequent)
+				.requires_semicolon(),
+			Self::While {body, ..} | Self::Located(_, body) => body.requires_semicolon(),
+			_ => true
+		}
+	}
+
+	/// The statement, without the location it was compiled from.
+	fn unlocated(&self) -> &Self {
+		match self {
+			Self::Located(_, statement) => statement.unlocated(),
+			statement => statement
+		}
+	}
+}
+
+impl Print for Statement {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			// Statements that begin with `{`, `function` or `class` are blocks
+			// and declarations instead.
+			Self::Expression(expression) => match expression.begins_statement() {
+				true => expression.print_parenthesized(p),
+				false => expression.print(p)
+			},
+			Self::Block(block) => p.braced(block.0.is_empty(), |p| block.print(p)),
+			Self::Empty => write!(p, ";"),
+			// The argument has to begin on the same line as `return` and `throw`.
+			Self::Return(None) => write!(p, "return"),
+			Self::Return(Some(argument)) => {
+				write!(p, "return ")?;
+				argument.print(p)
+			},
+			Self::Throw(argument) => {
+				write!(p, "throw ")?;
+				argument.print(p)
+			},
+
+			Self::If {test, consequent, alternate} => {
+				write!(p, "if")?;
+				p.space()?;
+				write!(p, "(")?;
+				test.print(p)?;
+				write!(p, ")")?;
+				p.space()?;
+				match alternate {
+					Some(alternate) => {
+						// An `else` would otherwise belong to an `if` within the
+						// consequent that has none.
+						match consequent.unlocated() {
+							Self::If {alternate: None, ..} => p.braced(false, |p| {
+								consequent.print(p)?;
+								p.terminate(consequent.requires_semicolon(), true)
+							})?,
+							_ => {
+								consequent.print(p)?;
+								p.terminate(consequent.requires_semicolon(), false)?
+							}
+						}
+						p.space()?;
+						write!(p, "else")?;
+						match alternate.unlocated() {
+							Self::Block(_) => p.space()?,
+							_ => write!(p, " ")?
+						}
+						alternate.print(p)
+					},
+					None => consequent.print(p)
+				}
+			},
+			Self::While {test, body} => {
+				write!(p, "while")?;
+				p.space()?;
+				write!(p, "(")?;
+				test.print(p)?;
+				write!(p, ")")?;
+				p.space()?;
+				body.print(p)
+			},
+
+			Self::Variable(declaration) => declaration.print(p),
+			Self::Function(function) => function.print(p),
+			Self::Class(class) => class.print(p),
+			Self::Import(import) => import.print(p),
+			Self::Export(export) => export.print(p),
+
+			Self::DocComment(comment) => comment.print(p),
+			Self::Located(location, statement) =>
+				p.located(*location, |p| statement.print(p))
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum VariableKind {
+	Var,
+	Let,
+	Const
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariableDeclaration {
+	pub kind: VariableKind,
+	/// Each variable declared, along with its initial value, if any.
+	pub declarations: Vec<(Pattern, Option<Expression>)>
+}
+
+impl VariableDeclaration {
+	/// Declares a single variable, bound to `value`.
+	pub fn new(kind: VariableKind, name: Box<str>, value: Expression) -> Self {
+		Self {kind, declarations: vec![(Pattern::Identifier(name), Some(value))]}
+	}
+}
+
+impl Print for VariableDeclaration {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "{} ", match self.kind {
+			VariableKind::Var => "var",
+			VariableKind::Let => "let",
+			VariableKind::Const => "const"
+		})?;
+		p.list(&self.declarations, |p, (pattern, value)| {
+			pattern.print(p)?;
+			match value {
+				Some(value) => assignment(p, None, value),
+				None => Ok(())
+			}
+		})
+	}
+}
+
+/// Something that can be bound or assigned to.
+#[derive(Debug, Serialize)]
+pub enum Pattern {
+	Identifier(Box<str>),
+	Object(Vec<(PropertyKey, Pattern)>),
+	/// The elements of an array, any of which may be skipped.
+	Array(Vec<Option<Pattern>>),
+	/// A pattern with a value to use when there is nothing to bind to it.
+	Default {
+		pattern: Box<Pattern>,
+		value: Box<Expression>
+	},
+	/// The rest of an array or object.
+	Rest(Box<Pattern>),
+	/// A member expression, which may only be assigned to.
+	Member(Box<Expression>)
+}
+
+impl Print for Pattern {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			Self::Identifier(name) => write!(p, "{}", name),
+			Self::Object(properties) => {
+				write!(p, "{{")?;
+				p.list(properties, |p, (key, value)| match (key, value) {
+					(PropertyKey::Identifier(key), Pattern::Identifier(name)) if key == name =>
+						write!(p, "{}", name),
+					(key, value) => {
+						key.print(p)?;
+						write!(p, ":")?;
+						p.space()?;
+						value.print(p)
+					}
+				})?;
+				write!(p, "}}")
+			},
+			Self::Array(elements) => {
+				write!(p, "[")?;
+				p.list(elements, |p, element| match element {
+					Some(element) => element.print(p),
+					None => Ok(())
+				})?;
+				// A trailing hole needs a comma of its own.
+				if matches!(elements.last(), Some(None)) {write!(p, ",")?}
+				write!(p, "]")
+			},
+			Self::Default {pattern, value} => {
+				pattern.print(p)?;
+				assignment(p, None, value)
+			},
+			Self::Rest(pattern) => {
+				write!(p, "...")?;
+				pattern.print(p)
+			},
+			Self::Member(expression) => expression.print_at(p, MEMBER)
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct Function {
+	pub name: Option<Box<str>>,
+	pub parameters: Vec<Pattern>,
+	pub body: Block
+}
+
+impl Print for Function {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "function")?;
+		if let Some(name) = &self.name {write!(p, " {}", name)?}
+		self.print_method(p)
+	}
+}
+
+impl Function {
+	/// Writes the function from its parameters on, as methods are written.
+	fn print_method(&self, p: &mut Printer) -> FMTResult {
+		parameters(p, &self.parameters)?;
+		p.space()?;
+		p.braced(self.body.0.is_empty(), |p| self.body.print(p))
+	}
+}
+
+fn parameters(p: &mut Printer, parameters: &[Pattern]) -> FMTResult {
+	write!(p, "(")?;
+	p.list(parameters, |p, parameter| parameter.print(p))?;
+	write!(p, ")")
+}
+
+#[derive(Debug, Serialize)]
+pub struct Class {
+	pub name: Option<Box<str>>,
+	pub superclass: Option<Box<Expression>>,
+	pub members: Vec<ClassMember>
+}
+
+impl Print for Class {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "class")?;
+		if let Some(name) = &self.name {write!(p, " {}", name)?}
+		if let Some(superclass) = &self.superclass {
+			write!(p, " extends ")?;
+			superclass.print_at(p, MEMBER)?;
+		}
+		p.space()?;
+		p.braced(self.members.is_empty(), |p| self.members.iter().enumerate()
+			.try_for_each(|(index, member)| {
+				if index > 0 {p.newline()?}
+				member.print(p)
+			}))
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub enum ClassMember {
+	Method {
+		r#static: bool,
+		kind: MethodKind,
+		key: PropertyKey,
+		/// The method's function, which has no name of its own.
+		function: Function
+	},
+	Property {
+		r#static: bool,
+		key: PropertyKey,
+		value: Option<Expression>
+	}
+}
+
+impl Print for ClassMember {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			Self::Method {r#static, kind, key, function} => {
+				if *r#static {write!(p, "static ")?}
+				method(p, *kind, key, function)
+			},
+			Self::Property {r#static, key, value} => {
+				if *r#static {write!(p, "static ")?}
+				key.print(p)?;
+				if let Some(value) = value {assignment(p, None, value)?}
+				// Fields are not separated by line breaks alone, which would
+				// join a field to a computed member after it.
+				write!(p, ";")
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum MethodKind {
+	Constructor,
+	Method,
+	Get,
+	Set
+}
+
+fn method(p: &mut Printer, kind: MethodKind, key: &PropertyKey, function: &Function)
+		-> FMTResult {
+	match kind {
+		MethodKind::Constructor => write!(p, "constructor")?,
+		MethodKind::Method => key.print(p)?,
+		MethodKind::Get => {
+			write!(p, "get ")?;
+			key.print(p)?
+		},
+		MethodKind::Set => {
+			write!(p, "set ")?;
+			key.print(p)?
+		}
+	}
+	function.print_method(p)
+}
+
+/// The name of a property, in an object, class or pattern.
+#[derive(Debug, Serialize)]
+pub enum PropertyKey {
+	Identifier(Box<str>),
+	String(Box<str>),
+	Computed(Box<Expression>)
+}
+
+impl Print for PropertyKey {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			Self::Identifier(name) => write!(p, "{}", name),
+			Self::String(string) => write!(p, "{}", StringLiteral(string)),
+			Self::Computed(expression) => {
+				write!(p, "[")?;
+				expression.print_at(p, ASSIGNMENT)?;
+				write!(p, "]")
+			}
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub enum Property {
+	Value(PropertyKey, Expression),
+	Method {
+		kind: MethodKind,
+		key: PropertyKey,
+		function: Function
+	},
+	Spread(Expression)
+}
+
+impl Print for Property {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			Self::Value(key, value) => {
+				key.print(p)?;
+				write!(p, ":")?;
+				p.space()?;
+				value.print_at(p, ASSIGNMENT)
+			},
+			Self::Method {kind, key, function} => method(p, *kind, key, function),
+			Self::Spread(argument) => {
+				write!(p, "...")?;
+				argument.print_at(p, ASSIGNMENT)
+			}
+		}
+	}
+}
+
+// How tightly each kind of expression binds, from loosest to tightest, as in
+// the grammar of ECMAScript. An expression is parenthesized wherever one that
+// binds more tightly is needed.
+const SEQUENCE: u8 = 1;
+const ASSIGNMENT: u8 = 2;
+const CONDITIONAL: u8 = 3;
+const LOGICAL_OR: u8 = 4;
+const LOGICAL_AND: u8 = 5;
+const BITWISE_OR: u8 = 6;
+const BITWISE_XOR: u8 = 7;
+const BITWISE_AND: u8 = 8;
+const EQUALITY: u8 = 9;
+const RELATIONAL: u8 = 10;
+const SHIFT: u8 = 11;
+const ADDITIVE: u8 = 12;
+const MULTIPLICATIVE: u8 = 13;
+const EXPONENT: u8 = 14;
+const PREFIX: u8 = 15;
+const POSTFIX: u8 = 16;
+const MEMBER: u8 = 17;
+const PRIMARY: u8 = 18;
+
+#[derive(Debug, Serialize)]
+pub enum Expression {
+	Identifier(Box<str>),
+	Literal(Literal),
+	This,
+	/// The elements of an array, any of which may be spread.
+	Array(Vec<Expression>),
+	Object(Vec<Property>),
+	Function(Function),
+	Arrow {
+		parameters: Vec<Pattern>,
+		body: ArrowBody
+	},
+	Class(Class),
+
+	Unary {
+		operator: UnaryOperator,
+		argument: Box<Expression>
+	},
+	Update {
+		operator: UpdateOperator,
+		prefix: bool,
+		argument: Box<Expression>
+	},
+	Binary {
+		operator: BinaryOperator,
+		left: Box<Expression>,
+		right: Box<Expression>
+	},
+	Assignment {
+		/// The operator of a compound assignment, such as `+=`, or `None` for
+		/// `=`.
+		operator: Option<BinaryOperator>,
+		target: Pattern,
+		value: Box<Expression>
+	},
+	Conditional {
+		test: Box<Expression>,
+		consequent: Box<Expression>,
+		alternate: Box<Expression>
+	},
+	Sequence(Vec<Expression>),
+
+	Member {
+		object: Box<Expression>,
+		property: MemberProperty
+	},
+	Call {
+		callee: Box<Expression>,
+		arguments: Vec<Expression>
+	},
+	New {
+		callee: Box<Expression>,
+		arguments: Vec<Expression>
+	},
+	/// An array element or argument spread into those around it.
+	Spread(Box<Expression>)
+}
+
+impl Expression {
+	pub fn call(callee: Expression, arguments: Vec<Expression>) -> Self {
+		Self::Call {callee: Box::new(callee), arguments}
+	}
+
+	/// Accesses the property `name` of `object`.
+	pub fn member(object: Expression, name: impl Into<Box<str>>) -> Self {
+		Self::Member {object: Box::new(object), property: MemberProperty::Identifier(name.into())}
+	}
+
+	pub fn string(string: impl Into<Box<str>>) -> Self {
+		Self::Literal(Literal::String(string.into()))
+	}
+
+	fn precedence(&self) -> u8 {
+		match self {
+			Self::Sequence(_) => SEQUENCE,
+			Self::Arrow {..} | Self::Assignment {..} | Self::Spread(_) => ASSIGNMENT,
+			Self::Conditional {..} => CONDITIONAL,
+			Self::Binary {operator, ..} => operator.precedence(),
+			Self::Unary {..} | Self::Update {prefix: true, ..} => PREFIX,
+			Self::Update {prefix: false, ..} => POSTFIX,
+			Self::Member {..} | Self::Call {..} | Self::New {..} => MEMBER,
+			Self::Identifier(_) | Self::Literal(_) | Self::This | Self::Array(_)
+				| Self::Object(_) | Self::Function(_) | Self::Class(_) => PRIMARY
+		}
+	}
+
+	/// Writes the expression where one of at least `precedence` is needed.
+	fn print_at(&self, p: &mut Printer, precedence: u8) -> FMTResult {
+		match self.precedence() < precedence {
+			true => self.print_parenthesized(p),
+			false => self.print(p)
+		}
+	}
+
+	fn print_parenthesized(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "(")?;
+		self.print(p)?;
+		write!(p, ")")
+	}
+
+	/// The operand this expression begins with when it is written, and the
+	/// precedence it is written at, if it begins with one.
+	fn first_operand(&self) -> Option<(&Self, u8)> {
+		match self {
+			Self::Binary {operator, left, ..} => Some((left, operator.operands().0)),
+			Self::Conditional {test, ..} => Some((test, LOGICAL_OR)),
+			Self::Sequence(expressions) => Some((expressions.first()?, ASSIGNMENT)),
+			Self::Assignment {target: Pattern::Member(target), ..}
+				| Self::Update {prefix: false, argument: target, ..} => Some((target, MEMBER)),
+			Self::Member {object: operand, ..} | Self::Call {callee: operand, ..} =>
+				Some((operand, MEMBER)),
+			_ => None
+		}
+	}
+
+	/// The expression that is written first within this one, which is this
+	/// one if it does not begin with an operand written without parentheses.
+	fn leftmost(&self) -> &Self {
+		match self.first_operand() {
+			Some((operand, precedence)) if operand.precedence() >= precedence
+				&& !operand.forces_parentheses(self) => operand.leftmost(),
+			_ => self
+		}
+	}
+
+	/// Whether the expression begins with `{`, which would begin a block where
+	/// a statement or an arrow function's body is expected.
+	fn begins_with_brace(&self) -> bool {
+		matches!(self.leftmost(), Self::Object(_)
+			| Self::Assignment {target: Pattern::Object(_), ..})
+	}
+
+	/// Whether the expression cannot be written as a statement as it is.
+	fn begins_statement(&self) -> bool {
+		self.begins_with_brace() || matches!(self.leftmost(), Self::Function(_) | Self::Class(_))
+	}
+
+	/// The `+` or `-` the expression begins with, if any, which may not follow
+	/// another directly.
+	fn leading_sign(&self) -> Option<char> {
+		match self.leftmost() {
+			Self::Unary {operator: UnaryOperator::Plus, ..}
+				| Self::Update {operator: UpdateOperator::Increment, prefix: true, ..} =>
+				Some('+'),
+			Self::Unary {operator: UnaryOperator::Negate, ..}
+				| Self::Update {operator: UpdateOperator::Decrement, prefix: true, ..} =>
+				Some('-'),
+			_ => None
+		}
+	}
+
+	/// Whether this operand of `parent` needs parentheses even though it binds
+	/// tightly enough.
+	fn forces_parentheses(&self, parent: &Self) -> bool {
+		use BinaryOperator::*;
+		match (parent, self) {
+			// `??` cannot be mixed with `&&` or `||`.
+			(Self::Binary {operator: Coalesce, ..},
+					Self::Binary {operator: LogicalAnd | LogicalOr, ..})
+				| (Self::Binary {operator: LogicalAnd | LogicalOr, ..},
+					Self::Binary {operator: Coalesce, ..}) => true,
+			// The base of an exponent cannot be a unary expression.
+			(Self::Binary {operator: Exponent, left, ..}, Self::Unary {..}) =>
+				std::ptr::eq(&**left, self),
+			// A call within the callee of `new` would take its arguments.
+			(Self::New {..}, _) => self.contains_call(),
+			// A `.` after an integer would be its decimal point.
+			(Self::Member {property: MemberProperty::Identifier(_), ..},
+					Self::Literal(Literal::Number(number))) =>
+				number.bytes().all(|byte| byte.is_ascii_digit()),
+			_ => false
+		}
+	}
+
+	fn contains_call(&self) -> bool {
+		match self {
+			Self::Call {..} => true,
+			Self::Member {object, ..} => object.contains_call(),
+			_ => false
+		}
+	}
+
+	/// Writes an operand of this expression.
+	fn print_operand(&self, p: &mut Printer, operand: &Self, precedence: u8) -> FMTResult {
+		match operand.forces_parentheses(self) {
+			true => operand.print_parenthesized(p),
+			false => operand.print_at(p, precedence)
+		}
+	}
+}
+
+impl Print for Expression {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		match self {
+			Self::Identifier(name) => write!(p, "{}", name),
+			Self::Literal(literal) => write!(p, "{}", literal),
+			Self::This => write!(p, "this"),
+
+			Self::Array(elements) => {
+				write!(p, "[")?;
+				p.list(elements, |p, element| element.print_at(p, ASSIGNMENT))?;
+				write!(p, "]")
+			},
+			Self::Object(properties) => {
+				write!(p, "{{")?;
+				p.list(properties, |p, property| property.print(p))?;
+				write!(p, "}}")
+			},
+			Self::Function(function) => function.print(p),
+			Self::Arrow {parameters: arrow_parameters, body} => {
+				parameters(p, arrow_parameters)?;
+				p.space()?;
+				write!(p, "=>")?;
+				p.space()?;
+				match body {
+					ArrowBody::Expression(body) if body.begins_with_brace() =>
+						body.print_parenthesized(p),
+					ArrowBody::Expression(body) => body.print_at(p, ASSIGNMENT),
+					ArrowBody::Block(body) => p.braced(body.0.is_empty(), |p| body.print(p))
+				}
+			},
+			Self::Class(class) => class.print(p),
+
+			Self::Unary {operator, argument} => {
+				write!(p, "{}", operator.text())?;
+				let sign = match operator {
+					UnaryOperator::Plus => Some('+'),
+					UnaryOperator::Negate => Some('-'),
+					_ => None
+				};
+				if operator.text().ends_with(char::is_alphabetic)
+						|| (sign.is_some() && argument.leading_sign() == sign)
+					{write!(p, " ")?}
+				self.print_operand(p, argument, PREFIX)
+			},
+			Self::Update {operator, prefix: true, argument} => {
+				write!(p, "{}", operator.text())?;
+				self.print_operand(p, argument, MEMBER)
+			},
+			Self::Update {operator, prefix: false, argument} => {
+				self.print_operand(p, argument, MEMBER)?;
+				write!(p, "{}", operator.text())
+			},
+			Self::Binary {operator, left, right} => {
+				let (left_precedence, right_precedence) = operator.operands();
+				self.print_operand(p, left, left_precedence)?;
+				let text = operator.text();
+				match text.ends_with(char::is_alphabetic) {
+					true => write!(p, " {} ", text)?,
+					false => {
+						p.space()?;
+						write!(p, "{}", text)?;
+						// `a + +b` is not `a ++b`.
+						match right.leading_sign() {
+							Some(sign) if text.ends_with(sign) => write!(p, " ")?,
+							_ => p.space()?
+						}
+					}
+				}
+				self.print_operand(p, right, right_precedence)
+			},
+			Self::Assignment {operator, target, value} => {
+				target.print(p)?;
+				assignment(p, *operator, value)
+			},
+			Self::Conditional {test, consequent, alternate} => {
+				self.print_operand(p, test, LOGICAL_OR)?;
+				p.space()?;
+				write!(p, "?")?;
+				p.space()?;
+				consequent.print_at(p, ASSIGNMENT)?;
+				p.space()?;
+				write!(p, ":")?;
+				p.space()?;
+				alternate.print_at(p, ASSIGNMENT)
+			},
+			Self::Sequence(expressions) => {
+				p.list(expressions, |p, expression| expression.print_at(p, ASSIGNMENT))
+			},
+
+			Self::Member {object, property} => {
+				self.print_operand(p, object, MEMBER)?;
+				match property {
+					MemberProperty::Identifier(name) => write!(p, ".{}", name),
+					MemberProperty::Computed(property) => {
+						write!(p, "[")?;
+						property.print(p)?;
+						write!(p, "]")
+					}
+				}
+			},
+			Self::Call {callee, arguments} => {
+				self.print_operand(p, callee, MEMBER)?;
+				self::arguments(p, arguments)
+			},
+			Self::New {callee, arguments} => {
+				write!(p, "new ")?;
+				self.print_operand(p, callee, MEMBER)?;
+				self::arguments(p, arguments)
+			},
+			Self::Spread(argument) => {
+				write!(p, "...")?;
+				argument.print_at(p, ASSIGNMENT)
+			}
+		}
+	}
+}
+
+impl Display for Expression {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		f.write_str(&self.printed())
+	}
+}
+
+fn arguments(p: &mut Printer, arguments: &[Expression]) -> FMTResult {
+	write!(p, "(")?;
+	p.list(arguments, |p, argument| argument.print_at(p, ASSIGNMENT))?;
+	write!(p, ")")
+}
+
+/// Writes the operator of an assignment, declaration or default, followed by
+/// its value.
+fn assignment(p: &mut Printer, operator: Option<BinaryOperator>, value: &Expression)
+		-> FMTResult {
+	p.space()?;
+	write!(p, "{}=", operator.map(BinaryOperator::text).unwrap_or_default())?;
+	p.space()?;
+	value.print_at(p, ASSIGNMENT)
+}
+
+#[derive(Debug, Serialize)]
+pub enum Literal {
+	String(Box<str>),
+	/// A number, as it is written in JavaScript.
+	Number(Box<str>),
+	/// A `BigInt`, as it is written in JavaScript without its `n` suffix.
+	BigInt(Box<str>),
+	Boolean(bool),
+	Null
+}
+
+impl Display for Literal {
+	fn fmt(&self, f: &mut Formatter) -> FMTResult {
+		match self {
+			Self::String(string) => StringLiteral(string).fmt(f),
+			Self::Number(number) => write!(f, "{}", number),
+			Self::BigInt(number) => write!(f, "{}n", number),
+			Self::Boolean(boolean) => write!(f, "{}", boolean),
+			Self::Null => write!(f, "null")
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub enum MemberProperty {
+	Identifier(Box<str>),
+	Computed(Box<Expression>)
+}
+
+#[derive(Debug, Serialize)]
+pub enum ArrowBody {
+	Expression(Box<Expression>),
+	Block(Block)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum UnaryOperator {
+	Negate,
+	Plus,
+	Not,
+	BitwiseNot,
+	TypeOf,
+	Void,
+	Delete
+}
+
+impl UnaryOperator {
+	pub fn text(self) -> &'static str {
+		match self {
+			Self::Negate => "-",
+			Self::Plus => "+",
+			Self::Not => "!",
+			Self::BitwiseNot => "~",
+			Self::TypeOf => "typeof",
+			Self::Void => "void",
+			Self::Delete => "delete"
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum UpdateOperator {
+	Increment,
+	Decrement
+}
+
+impl UpdateOperator {
+	pub fn text(self) -> &'static str {
+		match self {
+			Self::Increment => "++",
+			Self::Decrement => "--"
+		}
+	}
+}
+
+/// The binary and logical operators. Any but the comparisons may also be
+/// compounded with an assignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum BinaryOperator {
+	Add,
+	Subtract,
+	Multiply,
+	Divide,
+	Remainder,
+	Exponent,
+	LeftShift,
+	RightShift,
+	UnsignedRightShift,
+	BitwiseAnd,
+	BitwiseOr,
+	BitwiseXor,
+	Equal,
+	NotEqual,
+	StrictEqual,
+	StrictNotEqual,
+	Less,
+	LessEqual,
+	Greater,
+	GreaterEqual,
+	In,
+	InstanceOf,
+	LogicalAnd,
+	LogicalOr,
+	Coalesce
+}
+
+impl BinaryOperator {
+	pub fn text(self) -> &'static str {
+		match self {
+			Self::Add => "+",
+			Self::Subtract => "-",
+			Self::Multiply => "*",
+			Self::Divide => "/",
+			Self::Remainder => "%",
+			Self::Exponent => "**",
+			Self::LeftShift => "<<",
+			Self::RightShift => ">>",
+			Self::UnsignedRightShift => ">>>",
+			Self::BitwiseAnd => "&",
+			Self::BitwiseOr => "|",
+			Self::BitwiseXor => "^",
+			Self::Equal => "==",
+			Self::NotEqual => "!=",
+			Self::StrictEqual => "===",
+			Self::StrictNotEqual => "!==",
+			Self::Less => "<",
+			Self::LessEqual => "<=",
+			Self::Greater => ">",
+			Self::GreaterEqual => ">=",
+			Self::In => "in",
+			Self::InstanceOf => "instanceof",
+			Self::LogicalAnd => "&&",
+			Self::LogicalOr => "||",
+			Self::Coalesce => "??"
+		}
+	}
+
+	fn precedence(self) -> u8 {
+		match self {
+			Self::LogicalOr | Self::Coalesce => LOGICAL_OR,
+			Self::LogicalAnd => LOGICAL_AND,
+			Self::BitwiseOr => BITWISE_OR,
+			Self::BitwiseXor => BITWISE_XOR,
+			Self::BitwiseAnd => BITWISE_AND,
+			Self::Equal | Self::NotEqual | Self::StrictEqual | Self::StrictNotEqual => EQUALITY,
+			Self::Less | Self::LessEqual | Self::Greater | Self::GreaterEqual | Self::In
+				| Self::InstanceOf => RELATIONAL,
+			Self::LeftShift | Self::RightShift | Self::UnsignedRightShift => SHIFT,
+			Self::Add | Self::Subtract => ADDITIVE,
+			Self::Multiply | Self::Divide | Self::Remainder => MULTIPLICATIVE,
+			Self::Exponent => EXPONENT
+		}
+	}
+
+	/// The precedences the left and right operands are written at. Operators
+	/// group to the left, except `**`, which groups to the right.
+	fn operands(self) -> (u8, u8) {
+		match self {
+			Self::Exponent => (EXPONENT + 1, EXPONENT),
+			operator => (operator.precedence(), operator.precedence() + 1)
+		}
+	}
+}
+
+/// A string, written as a JavaScript string literal. The literal is kept to
+/// ASCII, so that the output reads the same under any encoding: characters
+/// outside of the BMP are written as the surrogate pairs JavaScript strings
+/// hold them as.
+pub struct StringLiteral<'s>(pub &'s str);
+
+impl Display for StringLiteral<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FMTResult {
+		write!(f, "\"")?;
+		self.0.chars().try_for_each(|character| match character {
+			'"' => write!(f, "\\\""),
+			'\\' => write!(f, "\\\\"),
+			'\n' => write!(f, "\\n"),
+			'\r' => write!(f, "\\r"),
+			'\t' => write!(f, "\\t"),
+			// `\0` followed by a digit would be an octal escape, so control
+			// characters are always written in full.
+			'\0'..='\x1F' | '\x7F' => write!(f, "\\x{:02X}", character as u32),
+			' '..='~' => write!(f, "{}", character),
+			_ => character.encode_utf16(&mut [0; 2]).iter()
+				.try_for_each(|unit| write!(f, "\\u{:04X}", unit))
+		})?;
+		write!(f, "\"")
+	}
+}
+
+/// A JSDoc comment, documenting whatever follows it.
+#[derive(Debug, Serialize)]
+pub struct DocComment(pub Box<str>);
+
+impl DocComment {
+	/// Joins every piece of documentation that is present, if any are.
+	pub fn new<'d>(documentation: impl IntoIterator<Item = Option<&'d str>>)
+			-> Option<Self> {
+		let text = documentation.into_iter().flatten().join("\n");
+		(!text.is_empty()).then(|| Self(text.into()))
+	}
+}
+
+impl Print for DocComment {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "/**")?;
+		// A `*/` in the documentation would end the comment early.
+		self.0.lines().try_for_each(|line| {
+			p.line_break()?;
+			write!(p, " *{}", line.replace("*/", "*\\/"))
+		})?;
+		p.line_break()?;
+		write!(p, " */")
+	}
+}
+
+impl Display for DocComment {
+	fn fmt(&self, f: &mut Formatter) -> FMTResult {
+		f.write_str(&self.printed())
+	}
+}
+
+/// An ECMAScript `import` declaration. CommonJS imports are plain `require`
+/// calls, and so are emitted as declarations instead.
+#[derive(Debug, Serialize)]
+pub enum ImportDeclaration {
+	/// Each name imported, along with the name it is bound to.
+	Named {
+		names: Vec<(Box<str>, Box<str>)>,
+		from: Box<str>
+	},
+	Namespace {
+		name: Box<str>,
+		from: Box<str>
+	},
+	/// An import of a module for what it does when it is loaded, which binds
+	/// nothing.
+	Module(Box<str>)
+}
+
+impl Print for ImportDeclaration {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "import")?;
+		p.space()?;
+		let from = match self {
+			Self::Module(from) => return write!(p, "{}", StringLiteral(from)),
+			Self::Named {names, from} => {
+				named(p, names)?;
+				p.space()?;
+				from
+			},
+			Self::Namespace {name, from} => {
+				namespace(p, name)?;
+				from
+			}
+		};
+		write!(p, "from")?;
+		p.space()?;
+		write!(p, "{}", StringLiteral(from))
+	}
+}
+
+/// An ECMAScript `export` declaration. CommonJS exports are assignments to
+/// `exports`, and so are emitted as expressions instead.
+#[derive(Debug, Serialize)]
+pub enum ExportDeclaration {
+	/// Each name exported, along with the name it is exported as.
+	Named(Vec<(Box<str>, Box<str>)>),
+	Namespace {
+		name: Box<str>,
+		from: Box<str>
+	}
+}
+
+impl Print for ExportDeclaration {
+	fn print(&self, p: &mut Printer) -> FMTResult {
+		write!(p, "export")?;
+		p.space()?;
+		match self {
+			Self::Named(names) => named(p, names),
+			Self::Namespace {name, from} => {
+				namespace(p, name)?;
+				write!(p, "from")?;
+				p.space()?;
+				write!(p, "{}", StringLiteral(from))
+			}
+		}
+	}
+}
+
+/// Writes the braced names of an import or export, each renamed with `as` if
+/// it is bound to a name of its own.
+fn named(p: &mut Printer, names: &[(Box<str>, Box<str>)]) -> FMTResult {
+	write!(p, "{{")?;
+	p.list(names, |p, (name, alias)| match name == alias {
+		true => write!(p, "{}", name),
+		false => write!(p, "{} as {}", name, alias)
+	})?;
+	write!(p, "}}")
+}
+
+/// Writes the `* as` binding of an import or export, and the space after it.
+fn namespace(p: &mut Printer, name: &str) -> FMTResult {
+	write!(p, "*")?;
+	p.space()?;
+	write!(p, "as {} ", name)
+}
+
+/// Something that may refer to variables by name.
+pub trait References {
+	/// Calls `each` with every name referred to, including within functions.
+	/// The names patterns bind are not references, and neither are the names of
+	/// properties.
+	fn references(&self, each: &mut dyn FnMut(&str));
+}
+
+impl<T: References> References for [T] {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		self.iter().for_each(|item| item.references(each))
+	}
+}
+
+impl<T: References> References for Option<T> {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		if let Some(item) = self {item.references(each)}
+	}
+}
+
+impl<T: References + ?Sized> References for &T {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		(**self).references(each)
+	}
+}
+
+impl<T: References + ?Sized> References for Box<T> {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		(**self).references(each)
+	}
+}
+
+impl References for Block {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		self.0.references(each)
+	}
+}
+
+impl References for Statement {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		match self {
+			Self::Expression(expression) | Self::Throw(expression)
+				| Self::Return(Some(expression)) => expression.references(each),
+			Self::Block(block) => block.references(each),
+			Self::If {test, consequent, alternate} => {
+				test.references(each);
+				consequent.references(each);
+				alternate.as_deref().references(each)
+			},
+			Self::While {test, body} => {
+				test.references(each);
+				body.references(each)
+			},
+			Self::Variable(declaration) => declaration.declarations.iter()
+				.for_each(|(pattern, value)| {
+					pattern.references(each);
+					value.references(each)
+				}),
+			Self::Function(function) => function.references(each),
+			Self::Class(class) => class.references(each),
+			// Exports refer to what they export.
+			Self::Export(ExportDeclaration::Named(names)) =>
+				names.iter().for_each(|(name, _)| each(name)),
+			Self::Located(_, statement) => statement.references(each),
+			Self::Empty | Self::Return(None) | Self::Import(_)
+				| Self::Export(ExportDeclaration::Namespace {..}) | Self::DocComment(_) => ()
+		}
+	}
+}
+
+impl References for Pattern {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		match self {
+			Self::Identifier(_) => (),
+			Self::Object(properties) => properties.iter().for_each(|(key, value)| {
+				key.references(each);
+				value.references(each)
+			}),
+			Self::Array(elements) => elements.iter()
+				.for_each(|element| element.as_ref().references(each)),
+			Self::Default {pattern, value} => {
+				pattern.references(each);
+				value.references(each)
+			},
+			Self::Rest(pattern) => pattern.references(each),
+			Self::Member(expression) => expression.references(each)
+		}
+	}
+}
+
+impl References for Function {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		self.parameters.references(each);
+		self.body.references(each)
+	}
+}
+
+impl References for Class {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		self.superclass.references(each);
+		self.members.iter().for_each(|member| match member {
+			ClassMember::Method {key, function, ..} => {
+				key.references(each);
+				function.references(each)
+			},
+			ClassMember::Property {key, value, ..} => {
+				key.references(each);
+				value.references(each)
+			}
+		})
+	}
+}
+
+impl References for PropertyKey {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		if let Self::Computed(expression) = self {expression.references(each)}
+	}
+}
+
+impl References for Property {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		match self {
+			Self::Value(key, value) => {
+				key.references(each);
+				value.references(each)
+			},
+			Self::Method {key, function, ..} => {
+				key.references(each);
+				function.references(each)
+			},
+			Self::Spread(argument) => argument.references(each)
+		}
+	}
+}
+
+impl References for Expression {
+	fn references(&self, each: &mut dyn FnMut(&str)) {
+		match self {
+			Self::Identifier(name) => each(name),
+			Self::Literal(_) | Self::This => (),
+			Self::Array(expressions) | Self::Sequence(expressions) => expressions.references(each),
+			Self::Object(properties) => properties.references(each),
+			Self::Function(function) => function.references(each),
+			Self::Arrow {parameters, body} => {
+				parameters.references(each);
+				match body {
+					ArrowBody::Expression(body) => body.references(each),
+					ArrowBody::Block(body) => body.references(each)
+				}
+			},
+			Self::Class(class) => class.references(each),
+			Self::Unary {argument, ..} | Self::Update {argument, ..} | Self::Spread(argument) =>
+				argument.references(each),
+			Self::Binary {left, right, ..} => {
+				left.references(each);
+				right.references(each)
+			},
+			Self::Assignment {target, value, ..} => {
+				target.references(each);
+				value.references(each)
+			},
+			Self::Conditional {test, consequent, alternate} => {
+				test.references(each);
+				consequent.references(each);
+				alternate.references(each)
+			},
+			Self::Member {object, property} => {
+				object.references(each);
+				if let MemberProperty::Computed(property) = property {property.references(each)}
+			},
+			Self::Call {callee, arguments} | Self::New {callee, arguments} => {
+				callee.references(each);
+				arguments.references(each)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::process::Command;
+
+	fn identifier(name: &str) -> Expression {
+		Expression::Identifier(name.into())
+	}
+
+	fn number(number: &str) -> Expression {
+		Expression::Literal(Literal::Number(number.into()))
+	}
+
+	fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+		Expression::Binary {operator, left: Box::new(left), right: Box::new(right)}
+	}
+
+	fn unary(operator: UnaryOperator, argument: Expression) -> Expression {
+		Expression::Unary {operator, argument: Box::new(argument)}
+	}
+
+	#[test]
+	fn doc_comments_are_joined_and_escaped() {
+		let comment = DocComment::new([Some(" First."), None, Some(" Ends */ early.")]).unwrap();
+		assert_eq!(comment.to_string(), "/**\n * First.\n * Ends *\\/ early.\n */");
+		assert!(DocComment::new([None, None]).is_none());
+	}
+
+	#[test]
+	fn strings_are_escaped_to_ascii() {
+		let string = "\"\\\n\r\t\0\x1B\x7F~\u{e9}\u{2028}\u{1F600}";
+		let literal = StringLiteral(string).to_string();
+		assert_eq!(literal, r#""\"\\\n\r\t\x00\x1B\x7F~\u00E9\u2028\uD83D\uDE00""#);
+
+		// JavaScript reads the literal as the string it was written from.
+		let Ok(node) = Command::new("node")
+			.args(["-e", &format!("process.stdout.write({})", literal)])
+			.output() else {return};
+		assert_eq!(String::from_utf8_lossy(&node.stdout), string);
+	}
+
+	#[test]
+	fn operands_are_parenthesized_by_precedence() {
+		use BinaryOperator::*;
+		let cases = [
+			(binary(Multiply, binary(Add, identifier("a"), identifier("b")), identifier("c")),
+				"(a+b)*c"),
+			(binary(Add, identifier("a"), binary(Multiply, identifier("b"), identifier("c"))),
+				"a+b*c"),
+			(binary(Subtract, binary(Subtract, identifier("a"), identifier("b")),
+				identifier("c")), "a-b-c"),
+			(binary(Subtract, identifier("a"), binary(Subtract, identifier("b"),
+				identifier("c"))), "a-(b-c)"),
+			// `**` groups to the right.
+			(binary(Exponent, identifier("a"), binary(Exponent, identifier("b"),
+				identifier("c"))), "a**b**c"),
+			(binary(Exponent, binary(Exponent, identifier("a"), identifier("b")),
+				identifier("c")), "(a**b)**c")
+		];
+		cases.into_iter().for_each(|(expression, printed)|
+			assert_eq!(expression.to_string(), printed));
+	}
+
+	#[test]
+	fn some_operands_are_always_parenthesized() {
+		use BinaryOperator::*;
+		let cases = [
+			(binary(Exponent, unary(UnaryOperator::Negate, identifier("a")), identifier("b")),
+				"(-a)**b"),
+			(binary(Coalesce, identifier("a"), binary(LogicalOr, identifier("b"),
+				identifier("c"))), "a??(b||c)"),
+			(binary(LogicalAnd, binary(Coalesce, identifier("a"), identifier("b")),
+				identifier("c")), "(a??b)&&c"),
+			(Expression::New {
+				callee: Box::new(Expression::call(identifier("f"), Vec::new())),
+				arguments: Vec::new()
+			}, "new (f())()"),
+			(Expression::member(number("1"), "toString"), "(1).toString"),
+			(Expression::member(number("1.5"), "toString"), "1.5.toString")
+		];
+		cases.into_iter().for_each(|(expression, printed)|
+			assert_eq!(expression.to_string(), printed));
+	}
+
+	#[test]
+	fn signs_are_kept_apart() {
+		use UnaryOperator::*;
+		let negated = || unary(Negate, identifier("b"));
+		assert_eq!(binary(BinaryOperator::Subtract, identifier("a"), negated()).to_string(),
+			"a- -b");
+		assert_eq!(binary(BinaryOperator::Add, identifier("a"), unary(Plus, identifier("b")))
+			.to_string(), "a+ +b");
+		assert_eq!(binary(BinaryOperator::Add, identifier("a"), negated()).to_string(), "a+-b");
+		assert_eq!(unary(Negate, negated()).to_string(), "- -b");
+		assert_eq!(unary(TypeOf, identifier("b")).to_string(), "typeof b");
+	}
+
+	#[test]
+	fn statements_are_not_read_as_something_else() {
+		let statement = |expression| Block(vec![Statement::Expression(expression)]).to_string();
+		let function = || Expression::Function(Function {
+			name: None,
+			parameters: Vec::new(),
+			body: Block(Vec::new())
+		});
+
+		assert_eq!(statement(Expression::member(Expression::Object(Vec::new()), "x")), "({}.x)");
+		assert_eq!(statement(Expression::call(function(), Vec::new())), "(function(){}())");
+		assert_eq!(statement(Expression::Assignment {
+			operator: None,
+			target: Pattern::Object(vec![(PropertyKey::Identifier("a".into()),
+				Pattern::Identifier("a".into()))]),
+			value: Box::new(identifier("b"))
+		}), "({a}=b)");
+		assert_eq!(statement(binary(BinaryOperator::Add, function(), number("1"))),
+			"(function(){}+1)");
+		assert_eq!(Expression::Arrow {
+			parameters: Vec::new(),
+			body: ArrowBody::Expression(Box::new(Expression::Object(Vec::new())))
+		}.to_string(), "()=>({})");
+	}
+
+	#[test]
+	fn statements_are_separated_by_semicolons() {
+		let call = |name| Statement::Expression(Expression::call(identifier(name), Vec::new()));
+		// Without a `;`, the array would be read as indexing the call.
+		let block = Block(vec![call("f"), Statement::Expression(Expression::Array(Vec::new())),
+			Statement::Return(Some(identifier("x")))]);
+		assert_eq!(block.to_string(), "f();[];return x");
+
+		// An `else` belongs to the nearest `if` without one.
+		let nested = Statement::If {
+			test: identifier("a"),
+			consequent: Box::new(Statement::If {
+				test: identifier("b"),
+				consequent: Box::new(call("c")),
+				alternate: None
+			}),
+			alternate: Some(Box::new(call("d")))
+		};
+		assert_eq!(Block(vec![nested]).to_string(), "if(a){if(b)c()}else d()");
+	}
+}
@@ -0,0 +1,259 @@
+//! How each integer type is represented in JavaScript. Integers of up to 32
+//! bits are numbers, which the bitwise operators keep within 32 bits. Wider
+//! integers would lose precision as numbers, so they are `BigInt`s instead,
+//! which `BigInt.asIntN` and `BigInt.asUintN` keep within any number of bits.
+
+use super::{ast::*, runtime::Helper};
+use crate::frontend::IntegerType;
+
+/// What happens when an integer leaves the range of its type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+	/// It wraps around to the other end of the range, as in Rust's release
+	/// builds.
+	#[default]
+	Wrap,
+	/// The program panics, as in Rust's debug builds.
+	Check
+}
+
+/// Whether integers of a type are `BigInt`s rather than numbers.
+pub fn is_big(integer: IntegerType) -> bool {
+	integer.bits > 32
+}
+
+/// An integer literal used as `integer`, or as a number if its type is not
+/// known. The frontend has already checked that the value is within range.
+pub fn literal(value: u128, integer: Option<IntegerType>) -> Expression {
+	match integer.is_some_and(is_big) {
+		true => Expression::Literal(Literal::BigInt(value.to_string().into())),
+		false => Expression::Literal(Literal::Number(value.to_string().into()))
+	}
+}
+
+/// Brings an integer from JavaScript, where it may be any number, within the
+/// range of `integer`, and into its representation. Wrapping rounds toward
+/// zero first, as `|0` does, while checking requires a whole number.
+pub fn from_foreign(value: Expression, integer: IntegerType, overflow: Overflow) -> Expression {
+	match (overflow, is_big(integer)) {
+		(Overflow::Wrap, true) => wrap(Helper::ToBigInt.call(vec![value]), integer, integer),
+		(Overflow::Wrap, false) => wrap(value, integer, integer),
+		(Overflow::Check, big) => {
+			let (min, max) = bounds(integer);
+			// Numbers and `BigInt`s compare with each other exactly.
+			let value = Helper::CheckInteger.call(vec![Helper::CheckWhole.call(vec![value]),
+				min, max, Expression::string("convert")]);
+			match big {
+				true => Expression::call(Expression::Identifier("BigInt".into()), vec![value]),
+				false => value
+			}
+		}
+	}
+}
+
+/// Wraps an integer represented as `from` represents it around into the range
+/// of `integer`, from wherever it is, and into its representation.
+pub fn wrap(value: Expression, from: IntegerType, integer: IntegerType) -> Expression {
+	let binary = |operator, left, right: &str| Expression::Binary {
+		operator,
+		left: Box::new(left),
+		right: Box::new(Expression::Literal(Literal::Number(right.into())))
+	};
+	let big = |signed, bits: u32, value| Expression::call(
+		Expression::member(Expression::Identifier("BigInt".into()),
+			if signed {"asIntN"} else {"asUintN"}),
+		vec![Expression::Literal(Literal::Number(bits.to_string().into())), value]);
+	let convert = |to: &str, value| Expression::call(Expression::Identifier(to.into()), vec![value]);
+	// The bitwise operators throw on `BigInt`s, and only read the lowest 32 bits
+	// of numbers, which are all that is kept of a `BigInt` before it is made
+	// one. `BigInt.asIntN` throws on numbers.
+	let value = match (is_big(from), is_big(integer)) {
+		(true, false) => convert("Number", big(true, 32, value)),
+		(false, true) => convert("BigInt", value),
+		_ => value
+	};
+	match (integer.bits, integer.signed) {
+		(bits, signed) if bits > 32 => big(signed, bits, value),
+		(32, true) => binary(BinaryOperator::BitwiseOr, value, "0"),
+		(32, false) => binary(BinaryOperator::UnsignedRightShift, value, "0"),
+		// Narrower integers are shifted to the top of 32 bits and back, which
+		// extends their sign, or else masked.
+		(bits, true) => {
+			let shift = (32 - bits).to_string();
+			binary(BinaryOperator::RightShift,
+				binary(BinaryOperator::LeftShift, value, &shift), &shift)
+		},
+		(bits, false) => binary(BinaryOperator::BitwiseAnd, value,
+			&((1u32 << bits) - 1).to_string())
+	}
+}
+
+/// The least and greatest values of `integer`, as it represents them.
+fn bounds(integer: IntegerType) -> (Expression, Expression) {
+	let min = match integer.signed {
+		true => Expression::Unary {
+			operator: UnaryOperator::Negate,
+			argument: Box::new(literal(integer.max() + 1, Some(integer)))
+		},
+		false => literal(0, Some(integer))
+	};
+	(min, literal(integer.max(), Some(integer)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{literal, wrap, Expression, Overflow};
+	use crate::{backend::javascript::printer::Print, compiler::{Compiler, Options},
+		frontend::IntegerType};
+	use std::{io::Write, path::Path, process::{Command, Stdio}};
+
+	#[test]
+	fn integers_wrap_within_their_bits() {
+		let integer = |name| IntegerType::from_name(name).unwrap();
+		let wrapped = |name| wrap(Expression::Identifier("x".into()), integer(name), integer(name))
+			.printed();
+		assert_eq!(["i8", "u8", "i16", "u16", "i32", "u32", "isize", "usize"].map(wrapped),
+			["x<<24>>24", "x&255", "x<<16>>16", "x&65535", "x|0", "x>>>0", "x|0", "x>>>0"]);
+		assert_eq!(["i64", "u128"].map(wrapped),
+			["BigInt.asIntN(64,x)", "BigInt.asUintN(128,x)"]);
+		let cast = |from, to| wrap(Expression::Identifier("x".into()), integer(from), integer(to))
+			.printed();
+		assert_eq!([cast("i64", "u8"), cast("u32", "i64"), cast("u8", "i8")],
+			["Number(BigInt.asIntN(32,x))&255", "BigInt.asIntN(64,BigInt(x))", "x<<24>>24"]);
+
+		let literal = |name| literal(5, IntegerType::from_name(name)).printed();
+		assert_eq!(["u32", "i64", "unknown"].map(literal), ["5", "5n", "5"]);
+	}
+
+	/// Runs a crate root under Node.js, after `before` has set up the globals it
+	/// uses and followed by `after`, returning what it printed and any error it
+	/// threw. Returns nothing if Node.js cannot be run.
+	fn run(source: &str, before: &str, after: &str, overflow: Overflow) -> Option<String> {
+		let options = Options {overflow, call_main: true, ..Options::default()};
+		let output = Compiler::new(options).compile_str(source, Path::new("")).unwrap();
+		let script = format!("{}\n{}\n{}", before, output.files[0].contents, after);
+
+		let mut node = Command::new("node")
+			.args(["--input-type=module", "-"])
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.ok()?;
+		node.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+		let output = node.wait_with_output().unwrap();
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let error = stderr.lines().find(|line| line.starts_with("Error: "));
+		Some(String::from_utf8_lossy(&output.stdout).into_owned()
+			+ error.unwrap_or_default())
+	}
+
+	#[test]
+	fn integers_wrap_between_representations() {
+		let casts = [("i64", "u8", "-1n"), ("i64", "u8", "2n ** 60n + 300n"),
+			("u128", "i32", "2n ** 100n + 2n ** 31n"), ("i32", "u64", "-1"), ("u8", "i8", "200"),
+			("i64", "i128", "-5n")];
+		let script = casts.iter()
+			.map(|(from, to, value)| format!("console.log({});\n", wrap(
+				Expression::Identifier(format!("({})", value).into()),
+				IntegerType::from_name(from).unwrap(), IntegerType::from_name(to).unwrap()
+			).printed()))
+			.collect::<String>();
+
+		let Ok(mut node) = Command::new("node")
+			.arg("-")
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.spawn()
+			else {return};
+		node.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+		let output = node.wait_with_output().unwrap();
+		assert_eq!(String::from_utf8_lossy(&output.stdout),
+			"255\n44\n-2147483648\n18446744073709551615n\n-56\n-5n\n");
+	}
+
+	const GLOBALS: &str = "
+		globalThis.huge = 2n ** 64n + 5n;
+		globalThis.negative = -1;
+		globalThis.half = -1.5;
+		globalThis.nan = NaN;
+		globalThis.wide = 2 ** 31;
+		globalThis.byte = 257.9;
+		globalThis.identity = value => value;
+	";
+
+	const SOURCE: &str = r#"
+		extern "js" {
+			fn log(value: i64) = "console.log";
+			fn log_u64(value: u64) = "console.log";
+			fn log_i32(value: i32) = "console.log";
+			fn log_u8(value: u8) = "console.log";
+			fn identity(value: u64) -> u64;
+			let huge: i64;
+			let negative: u64;
+			let half: i64;
+			let nan: i64;
+			let wide: i32;
+			let byte: u8;
+		}
+
+		pub fn show(value: i64) {
+			log(value);
+		}
+
+		fn main() {
+			log(huge);
+			log_u64(negative);
+			log(half);
+			log(nan);
+			log_i32(wide);
+			log_u8(byte);
+			log_u64(identity(18446744073709551615));
+			log(9007199254740993);
+		}
+	"#;
+
+	#[test]
+	fn wide_integers_wrap_from_javascript() {
+		let Some(output) = run(SOURCE, GLOBALS, "show(3.7); show(-1); show(2n ** 63n);",
+			Overflow::Wrap) else {return};
+
+		let expected = ["5n", "18446744073709551615n", "-1n", "0n", "-2147483648", "1",
+			"18446744073709551615n", "9007199254740993n", "3n", "-1n", "-9223372036854775808n"];
+		assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn wide_integers_are_checked_from_javascript() {
+		let source = |main: &str| format!(r#"
+			extern "js" {{
+				fn log(value: i64) = "console.log";
+				fn log_u64(value: u64) = "console.log";
+				fn identity(value: u64) -> u64;
+				let huge: i64;
+				let negative: u64;
+				let half: i64;
+			}}
+
+			pub fn show(value: i64) {{
+				log(value);
+			}}
+
+			fn main() {{
+				{}
+			}}
+		"#, main);
+		let run = |main: &str, after: &str|
+			run(&source(main), GLOBALS, after, Overflow::Check);
+
+		let Some(output) = run("show(5);", "show(-2);") else {return};
+		assert_eq!(output, "5n\n-2n\n");
+		assert_eq!(run("", "show(1.5);").unwrap(), "Error: expected an integer, found 1.5");
+		assert_eq!(run("log(huge);", "").unwrap(), "Error: attempt to convert with overflow");
+		assert_eq!(run("identity(negative);", "").unwrap(),
+			"Error: attempt to convert with overflow");
+		assert_eq!(run("log(half);", "").unwrap(), "Error: expected an integer, found -1.5");
+		assert_eq!(run("log_u64(identity(18446744073709551615));", "").unwrap(),
+			"18446744073709551615n\n");
+	}
+}
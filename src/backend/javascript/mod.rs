@@ -1,83 +1,689 @@
-use super::super::frontend::{Code, DataFormat, EnumVariantFormat};
+pub mod ast;
+pub mod integer;
+pub mod names;
+pub mod printer;
+pub mod runtime;
+pub mod source_map;
+
+use super::super::frontend::{module::ItemKind, Code, DataFormat, EnumVariantFormat,
+	Expression as MainExpression, Foreign, Scope, ScopeRef,
+	Statement as MainStatement, Type, TypeName};
+use ast::*;
+use integer::Overflow;
 use itertools::Itertools;
-use std::{fmt::{Display, Formatter, Result as FMTResult}, iter::{empty, once}};
+use names::{binding, field, foreign_binding, fresh_binding, is_identifier, is_identifier_name,
+	module_binding, positional, VARIANT};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-macro_rules! iter {
-	() => {empty()};
-	($first:expr $(, $($rest:expr),*)?) => {
-		once($first).chain(iter![$($($rest),*)?])
+impl Block {
+	/// Appends a call to the function `name` declared in this block, so that
+	/// running the block runs the function.
+	pub fn call(&mut self, name: &str) {
+		self.0.push(Statement::Expression(
+			Expression::call(Expression::Identifier(binding(name)), Vec::new())))
 	}
 }
 
-#[derive(Debug)]
-pub struct Block(Vec<Statement>);
+/// Precedes a statement with its documentation, if it has any.
+fn documented(documentation: Option<DocComment>, statement: Statement)
+		-> impl Iterator<Item = Statement> {
+	documentation.map(Statement::DocComment).into_iter().chain([statement])
+}
 
-impl Display for Block {
-	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
-		(0..self.0.len())
-			.try_for_each(|index| if index == 0
-					|| !self.0[index - 1].requires_semicolon() {
-				write!(f, "{}", self.0[index])
-			} else {
-				write!(f, ";{}", self.0[index])
-			})
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModuleSystem {
+	/// ECMAScript modules, using `import` and `export`.
+	#[default]
+	EcmaScript,
+	/// CommonJS modules, using `require` and `exports`.
+	CommonJS
+}
+
+/// A compiled rustscript module, to be written to its own file.
+#[derive(Debug, Serialize)]
+pub struct Module {
+	/// The path of the module from the crate root.
+	pub path: Vec<Box<str>>,
+	pub block: Block
+}
+
+impl Module {
+	/// The file this module is written to, relative to the directory of the
+	/// crate root's file, `root`.
+	pub fn file(&self, root: &str) -> String {
+		module_file(&self.path, root)
+	}
+}
+
+pub(super) fn module_file(path: &[Box<str>], root: &str) -> String {
+	match path.is_empty() {
+		true => root.into(),
+		false => format!("{}.js", path.join("/"))
+	}
+}
+
+/// The specifier used to import the module at `to` from the module at `from`.
+pub(super) fn module_specifier(from: &[Box<str>], to: &[Box<str>], root: &str)
+		-> Box<str> {
+	let depth = from.len().saturating_sub(1);
+	let prefix = match depth {
+		0 => "./".into(),
+		depth => "../".repeat(depth)
+	};
+
+	format!("{}{}", prefix, module_file(to, root)).into()
+}
+
+#[derive(Clone, Copy)]
+struct Context<'c> {
+	system: ModuleSystem,
+	root: &'c str,
+	path: &'c [Box<str>],
+	/// The foreign modules this module imports, and what each is bound to.
+	foreign: &'c BTreeMap<Box<str>, Box<str>>,
+	/// Every name bound anywhere in the crate, any of which may shadow a global.
+	bound: &'c BTreeSet<Box<str>>,
+	imported: &'c Imported,
+	overflow: Overflow
+}
+
+/// The names bound where code is converted. Items, variables and arguments
+/// share one namespace in JavaScript, so a variable or argument whose name is
+/// already bound is given a fresh one, rather than shadowing what it names.
+#[derive(Clone, Debug, Default)]
+struct Names {
+	/// What each variable and argument visible is bound to, by its name.
+	variables: HashMap<Box<str>, Box<str>>,
+	/// The names of the items visible.
+	items: HashSet<Box<str>>,
+	/// The names of the variables and arguments in the JavaScript functions
+	/// around, including those no longer visible.
+	bound: HashSet<Box<str>>
+}
+
+impl Names {
+	/// The names within a block whose scope is `scope`, which binds its items
+	/// throughout.
+	fn block(&self, scope: &Scope) -> Self {
+		let mut names = self.clone();
+		names.items.extend(scope.types.keys()
+			.chain(scope.functions.keys())
+			.chain(scope.modules.keys())
+			.chain(scope.imports.keys())
+			.map(|name| binding(name)));
+		names
+	}
+
+	/// The names within the body of a function, whose scope is `scope`. Functions
+	/// see none of the variables around them.
+	fn function(&self, scope: &Scope) -> Self {
+		Self {items: self.items.clone(), ..Self::default()}.block(scope)
+	}
+
+	/// Binds a variable or argument, returning the name it is bound to.
+	fn bind(&mut self, name: &str) -> Box<str> {
+		let bound = [binding(name)].into_iter()
+			.chain((1..).map(|index| fresh_binding(name, index)))
+			.find(|bound| !self.items.contains(bound) && !self.bound.contains(bound))
+			.expect("there are always fresh names");
+		self.bound.insert(bound.clone());
+		self.variables.insert(name.into(), bound.clone());
+		bound
+	}
+
+	/// The name a variable or argument is bound to.
+	fn variable(&self, name: &str) -> Box<str> {
+		self.variables.get(name).cloned().unwrap_or_else(|| binding(name))
 	}
 }
 
-#[derive(Debug)]
-pub enum Statement {
-	ClassItem(ClassItem),
-	FunctionItem(),
-	VarDeclaration(),
-	LetDeclaration(),
-	ConstDeclaration()
+/// Converts a crate into one JavaScript module per rustscript module. `root` is
+/// the name of the file the crate root is written to.
+pub fn from_main_representation(code: &Code, root: &str, system: ModuleSystem,
+		overflow: Overflow) -> Vec<Module> {
+	fn from_module(code: &Code, documentation: Option<&str>, context: Context,
+			modules: &mut Vec<Module>) {
+		let foreign = foreign_bindings(code);
+		let context = Context {foreign: &foreign, ..context};
+
+		let prelude = Scope::prelude();
+		let prelude = ScopeRef::new(&prelude);
+		let scope = prelude.r#in(&code.scope);
+		// `@module` keeps the module's documentation from being taken as that
+		// of the statement after it.
+		let mut statements: Vec<_> =
+			DocComment::new([documentation, code.documentation.as_deref()])
+				.map(|DocComment(text)| Statement::DocComment(
+					DocComment(format!("{}\n @module", text).into())))
+				.into_iter()
+				.collect();
+		// CommonJS exports are defined before anything is required, so that
+		// modules that import from each other see each other's items while they
+		// are still being loaded.
+		let exports = export_statements(code, context);
+		let (before, after) = match context.system {
+			ModuleSystem::CommonJS => (exports, Vec::new()),
+			ModuleSystem::EcmaScript => (Vec::new(), exports)
+		};
+		statements.extend(before);
+		statements.extend(import_statements(code, context));
+		// Helpers are declared only once it is known which the module uses.
+		let mut body = from_code(code, scope, context, &mut Names::default().block(&code.scope));
+		body.extend(after);
+		statements.extend(runtime::declarations(&body));
+		statements.extend(body);
+		modules.push(Module {path: context.path.to_vec(), block: Block(statements)});
+
+		code.scope.modules.iter()
+			.sorted_by(|(a, _), (b, _)| a.cmp(b))
+			.for_each(|(name, module)| {
+				let path: Vec<_> = context.path.iter().cloned()
+					.chain([Box::from(&**name)])
+					.collect();
+				from_module(&module.code, module.documentation.as_deref(),
+					Context {path: &path, ..context}, modules)
+			});
+	}
+
+	let mut bound = BTreeSet::new();
+	bound_names(code, &mut bound);
+	let mut imported = BTreeMap::new();
+	imported_names(code, &mut imported);
+	let context = Context {
+		system, root, path: &[], foreign: &BTreeMap::new(), bound: &bound,
+		imported: &imported, overflow
+	};
+	let mut modules = Vec::new();
+	from_module(code, None, context, &mut modules);
+	modules
+}
+
+/// Converts an expression as it would be written at the end of the crate root,
+/// whose main representation is `code`.
+pub fn from_root_expression(expression: &MainExpression, code: &Code, root: &str,
+		system: ModuleSystem, overflow: Overflow) -> Expression {
+	let foreign = foreign_bindings(code);
+	let mut bound = BTreeSet::new();
+	bound_names(code, &mut bound);
+	let mut imported = BTreeMap::new();
+	imported_names(code, &mut imported);
+	let prelude = Scope::prelude();
+	let prelude = ScopeRef::new(&prelude);
+	from_expression(expression, prelude.r#in(&code.scope), Context {
+		system, root, path: &[], foreign: &foreign, bound: &bound, imported: &imported, overflow
+	}, &Names::default().block(&code.scope))
+}
+
+/// Collects the JavaScript name of everything declared within `code`, in any
+/// scope, including modules and the arguments of functions.
+fn bound_names(code: &Code, names: &mut BTreeSet<Box<str>>) {
+	fn from_expression(expression: &MainExpression, names: &mut BTreeSet<Box<str>>) {
+		match expression {
+			MainExpression::Block(code) => bound_names(code, names),
+			MainExpression::FunctionCall {arguments, ..} => arguments.iter()
+				.for_each(|argument| from_expression(argument, names)),
+			_ => ()
+		}
+	}
+
+	let scope = &code.scope;
+	let declared = scope.types.keys()
+		.chain(scope.functions.keys())
+		.chain(scope.modules.keys())
+		.chain(scope.imports.keys())
+		.chain(scope.values.keys())
+		.chain(scope.functions.values().flat_map(|function| function.arguments.iter()
+			.map(|(name, _)| name)));
+	names.extend(declared.map(|name| binding(name)));
+
+	scope.functions.values().for_each(|function| bound_names(&function.code, names));
+	scope.modules.values().for_each(|module| bound_names(&module.code, names));
+	code.statements.iter().for_each(|statement| {
+		if let MainStatement::Variable(variable) = statement
+			{names.insert(binding(&variable.name));}
+		from_expression(statement.expression(), names)
+	});
+}
+
+/// The names each module declares that other modules import, by the path of
+/// the module, which it exports even if they are private.
+pub(super) type Imported = BTreeMap<Vec<Box<str>>, BTreeSet<Box<str>>>;
+
+/// Collects the name of every item imported by a module within `code`, under
+/// the path of the module that declares it.
+pub(super) fn imported_names(code: &Code, names: &mut Imported) {
+	code.scope.imports.values().for_each(|import| {
+		let (name, module) = import.path.split_last().expect("imports name an item");
+		names.entry(module.iter().map(|name| Box::from(&**name)).collect())
+			.or_default()
+			.insert(Box::from(&**name));
+	});
+	code.scope.modules.values().for_each(|module| imported_names(&module.code, names));
+}
+
+/// Whether the module at `path` exports the item it declares named `name`,
+/// because it is public or because another module imports it.
+pub(super) fn is_exported(code: &Code, name: &str, path: &[Box<str>], imported: &Imported)
+		-> bool {
+	code.scope.exports.contains(name) || imported.get(path)
+		.is_some_and(|imported| imported.contains(name))
+}
+
+/// The names of everything the module at `path` exports, in order.
+pub(super) fn exported_names<'c>(code: &'c Code, path: &[Box<str>], imported: &'c Imported)
+		-> Vec<&'c str> {
+	let imported = imported.get(path).into_iter().flatten();
+	code.scope.exports.iter()
+		.map(|name| &**name)
+		.chain(imported.map(|name| &**name))
+		.sorted()
+		.dedup()
+		.collect()
+}
+
+/// Binds each foreign module the items within `code` are imported from to a
+/// name of its own.
+fn foreign_bindings(code: &Code) -> BTreeMap<Box<str>, Box<str>> {
+	let mut foreign = BTreeSet::new();
+	foreign_modules(code, &mut foreign);
+	foreign.into_iter()
+		.enumerate()
+		.map(|(index, from)| (from, foreign_binding(index)))
+		.collect()
+}
+
+/// Collects every foreign module that items within `code` are imported from,
+/// excluding those within nested modules.
+fn foreign_modules(code: &Code, modules: &mut BTreeSet<Box<str>>) {
+	fn from_expression(expression: &MainExpression, modules: &mut BTreeSet<Box<str>>) {
+		match expression {
+			MainExpression::Block(code) => foreign_modules(code, modules),
+			MainExpression::FunctionCall {arguments, ..} => arguments.iter()
+				.for_each(|argument| from_expression(argument, modules)),
+			_ => ()
+		}
+	}
+
+	let types = code.scope.types.values()
+		.filter_map(|r#type| match r#type {
+			Type::Foreign {binding, ..} => Some(binding),
+			_ => None
+		});
+	let functions = code.scope.functions.values()
+		.filter_map(|function| function.foreign.as_ref());
+	let values = code.scope.values.values()
+		.filter_map(|value| value.foreign.as_ref());
+	types.chain(functions).chain(values)
+		.filter_map(|foreign| foreign.from.clone())
+		.for_each(|from| {modules.insert(from);});
+
+	code.scope.functions.values()
+		.for_each(|function| foreign_modules(&function.code, modules));
+	code.statements.iter()
+		.for_each(|statement| from_expression(statement.expression(), modules));
 }
 
-impl Statement {
-	/// Whether or not this statement requires a `;` afterwards (ignoring ASI).
-	pub fn requires_semicolon(&self) -> bool {
-		match self {
-			_ => false
+/// The expression referring to a foreign item. Its name is only a path, so
+/// each part that is not a valid name is accessed by string instead, and a
+/// global is accessed through `globalThis` when it could be shadowed.
+fn foreign_expression(foreign: &Foreign, context: Context) -> Expression {
+	let mut path = foreign.name.split('.').peekable();
+	let base = match (&foreign.from, path.peek()) {
+		(Some(from), _) => Expression::Identifier(context.foreign[from].clone()),
+		(None, Some(&name)) if is_identifier(name) && !context.bound.contains(name) => {
+			path.next();
+			Expression::Identifier(name.into())
+		},
+		(None, _) => Expression::Identifier("globalThis".into())
+	};
+
+	path.fold(base, |object, name| match is_identifier_name(name) {
+		true => Expression::member(object, name),
+		false => Expression::Member {
+			object: Box::new(object),
+			property: MemberProperty::Computed(Box::new(Expression::string(name)))
 		}
+	})
+}
+
+fn import_statements(code: &Code, context: Context) -> Vec<Statement> {
+	let (modules, items): (Vec<_>, Vec<_>) = code.scope.imports.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.partition(|(_, import)| import.kind == ItemKind::Module);
+
+	let modules = modules.into_iter()
+		.map(|(name, import)| {
+			let path: Vec<Box<str>> = import.path.iter().map(|name| Box::from(&**name)).collect();
+			(binding(name), module_specifier(context.path, &path, context.root))
+		})
+		.map(|(name, from)| match context.system {
+			ModuleSystem::EcmaScript =>
+				Statement::Import(ImportDeclaration::Namespace {name, from}),
+			ModuleSystem::CommonJS => constant(name, require(from))
+		});
+
+	// Items are grouped by the module they are imported from.
+	let items = items.into_iter()
+		.fold(BTreeMap::<_, Vec<_>>::new(), |mut items, (name, import)| {
+			let path: Vec<Box<str>> = import.path[..import.path.len() - 1].iter()
+				.map(|name| Box::from(&**name))
+				.collect();
+			// Items are exported under their own names, even if they are bound to
+			// others.
+			items.entry(path).or_default().push((Box::from(&**name), binding(name)));
+			items
+		})
+		.into_iter()
+		.map(|(path, names)| {
+			let from = module_specifier(context.path, &path, context.root);
+			match context.system {
+				ModuleSystem::EcmaScript =>
+					Statement::Import(ImportDeclaration::Named {names, from}),
+				// Imported items are accessed through the module, so that modules
+				// that import from each other see each other's items.
+				ModuleSystem::CommonJS => constant(module_binding(&path), require(from))
+			}
+		});
+
+	// Every module declared is loaded before the module it is declared in, as
+	// the interpreter loads them, whether or not anything is imported from it.
+	let declared = code.scope.modules.keys()
+		.sorted()
+		.map(|name| {
+			let path: Vec<_> = context.path.iter().cloned().chain([Box::from(&**name)]).collect();
+			let from = module_specifier(context.path, &path, context.root);
+			match context.system {
+				ModuleSystem::EcmaScript => Statement::Import(ImportDeclaration::Module(from)),
+				ModuleSystem::CommonJS => Statement::Expression(require(from))
+			}
+		});
+
+	let foreign = context.foreign.iter()
+		.map(|(from, binding)| match context.system {
+			ModuleSystem::EcmaScript => Statement::Import(ImportDeclaration::Namespace {
+				name: binding.clone(),
+				from: from.clone()
+			}),
+			ModuleSystem::CommonJS => constant(binding.clone(), require(from.clone()))
+		});
+
+	foreign.chain(declared).chain(modules).chain(items).collect()
+}
+
+fn export_statements(code: &Code, context: Context) -> Vec<Statement> {
+	let exports = exported_names(code, context.path, context.imported).into_iter();
+
+	match context.system {
+		ModuleSystem::EcmaScript => {
+			let (modules, names): (Vec<_>, Vec<_>) = exports
+				.partition(|name| code.scope.modules.contains_key(*name));
+
+			let modules = modules.into_iter()
+				.map(|name| {
+					let path: Vec<_> = context.path.iter().cloned()
+						.chain([Box::from(name)])
+						.collect();
+					Statement::Export(ExportDeclaration::Namespace {
+						name: name.into(),
+						from: module_specifier(context.path, &path, context.root)
+					})
+				});
+			let names = (!names.is_empty())
+				.then(|| Statement::Export(ExportDeclaration::Named(names.into_iter()
+					.map(|name| (binding(name), name.into()))
+					.collect())));
+
+			modules.chain(names).collect()
+		},
+
+		// Exports are getters, so that they are always up to date even while
+		// modules that import from each other are still being loaded.
+		ModuleSystem::CommonJS => exports
+			.map(|name| {
+				let value = match (
+					code.scope.modules.contains_key(name),
+					code.scope.imports.get(name)
+				) {
+					(true, _) => {
+						let path: Vec<_> = context.path.iter().cloned()
+							.chain([Box::from(name)])
+							.collect();
+						require(module_specifier(context.path, &path, context.root))
+					},
+					(false, Some(import)) if import.kind != ItemKind::Module => {
+						let path: Vec<Box<str>> = import.path[..import.path.len() - 1].iter()
+							.map(|name| Box::from(&**name))
+							.collect();
+						Expression::member(Expression::Identifier(module_binding(&path)), name)
+					},
+					_ => Expression::Identifier(binding(name))
+				};
+
+				Statement::Expression(Expression::call(
+					Expression::member(Expression::Identifier("Object".into()), "defineProperty"),
+					vec![
+						Expression::Identifier("exports".into()),
+						Expression::string(name),
+						Expression::Object(vec![
+							Property::Value(PropertyKey::Identifier("enumerable".into()),
+								Expression::Literal(Literal::Boolean(true))),
+							Property::Value(PropertyKey::Identifier("get".into()),
+								Expression::Arrow {
+									parameters: Vec::new(),
+									body: ArrowBody::Expression(Box::new(value))
+								})
+						])
+					]
+				))
+			})
+			.collect()
 	}
 }
 
-impl Display for Statement {
-	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
-		match self {
-			Self::ClassItem(class) => class.fmt(f),
-			_ => todo!()
+fn require(from: Box<str>) -> Expression {
+	Expression::call(Expression::Identifier("require".into()), vec![Expression::string(from)])
+}
+
+fn constant(name: Box<str>, value: Expression) -> Statement {
+	Statement::Variable(VariableDeclaration::new(VariableKind::Const, name, value))
+}
+
+/// Converts the items and statements of a block, whose scope is `scope`, binding
+/// its variables in `names`.
+fn from_code(code: &Code, scope: ScopeRef, context: Context, names: &mut Names)
+		-> Vec<Statement> {
+	let classes = class_items(code).into_iter();
+
+	// Foreign items are referred to directly, so they only need a declaration
+	// of their own when they are exported.
+	let foreign_types = code.scope.types.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.filter(|(name, _)| is_exported(code, name, context.path, context.imported))
+		.filter_map(|(name, r#type)| match r#type {
+			Type::Foreign {binding: foreign, ..} =>
+				Some(constant(binding(name), foreign_expression(foreign, context))),
+			_ => None
+		});
+
+	let functions = code.scope.functions.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.filter(|(name, function)| function.foreign.is_none()
+			|| is_exported(code, name, context.path, context.imported))
+		.flat_map(|(name, function)| {
+			let mut names = names.function(&function.code.scope);
+			let arguments: Vec<_> = function.arguments.iter()
+				.map(|(argument, r#type)| (names.bind(argument), r#type))
+				.collect();
+			let parameters = arguments.iter()
+				.map(|(argument, _)| Pattern::Identifier(argument.clone()))
+				.collect();
+			let body = match &function.foreign {
+				Some(foreign) => vec![Statement::Return(Some(from_foreign(Expression::call(
+					foreign_expression(foreign, context),
+					arguments.iter()
+						.map(|(argument, _)| Expression::Identifier(argument.clone()))
+						.collect()
+				), function.r#return.as_ref(), context)))],
+				// JavaScript may call exported functions with any value, so their
+				// integer arguments are brought within range first.
+				None => arguments.iter()
+					.filter(|_| code.scope.exports.contains(name))
+					.filter(|(_, r#type)| r#type.integer().is_some())
+					.map(|(argument, r#type)| {
+						let value = from_foreign(Expression::Identifier(argument.clone()),
+							Some(r#type), context);
+						Statement::Expression(Expression::Assignment {
+							operator: None,
+							target: Pattern::Identifier(argument.clone()),
+							value: Box::new(value)
+						})
+					})
+					.collect::<Vec<_>>().into_iter()
+					.chain(from_code(&function.code, scope.r#in(&function.code.scope), context,
+						&mut names))
+					.collect()
+			};
+
+			let documentation = DocComment::new([function.documentation.as_deref(),
+				function.code.documentation.as_deref()]);
+			documented(documentation, Statement::Located(function.location,
+				Box::new(Statement::Function(Function {
+					name: Some(binding(name)),
+					parameters,
+					body: Block(body)
+				}))))
+		});
+
+	let mut converted = classes.chain(foreign_types).chain(functions).collect::<Vec<_>>();
+	// Each variable is bound once its value is, which may refer to what it
+	// shadows.
+	code.statements.iter().for_each(|statement| converted.extend(match statement {
+		MainStatement::Variable(variable) => {
+			let value = from_expression(&variable.expression, scope, context, names);
+			documented(
+				DocComment::new([variable.documentation.as_deref()]),
+				Statement::Located(variable.location, Box::new(Statement::Variable(
+					VariableDeclaration::new(VariableKind::Let, names.bind(&variable.name), value)
+				)))
+			)
+		},
+		MainStatement::Expression {expression, location} => documented(None,
+			Statement::Located(*location, Box::new(Statement::Expression(
+				from_expression(expression, scope, context, names))))
+		)
+	}));
+
+	converted
+}
+
+fn from_expression(expression: &MainExpression, scope: ScopeRef, context: Context,
+		names: &Names) -> Expression {
+	match expression {
+		MainExpression::Block(code) => Expression::call(Expression::Arrow {
+			parameters: Vec::new(),
+			body: ArrowBody::Block(Block(from_code(code, scope.r#in(&code.scope), context,
+				&mut names.block(&code.scope))))
+		}, Vec::new()),
+		MainExpression::LiteralInteger {value, integer, ..} => integer::literal(*value, *integer),
+		MainExpression::LiteralFloat {value, ..} =>
+			Expression::Literal(Literal::Number(Box::from(&**value))),
+		MainExpression::LiteralBoolean(boolean) =>
+			Expression::Literal(Literal::Boolean(*boolean)),
+		MainExpression::LiteralString(string) => Expression::string(string.clone()),
+		// JavaScript has no characters, so they are strings of one character.
+		MainExpression::LiteralCharacter(character) =>
+			Expression::string(character.to_string()),
+
+		MainExpression::Variable {name, r#type, foreign} => match foreign {
+			Some(foreign) => from_foreign(foreign_expression(foreign, context),
+				Some(r#type), context),
+			None => Expression::Identifier(names.variable(name))
+		},
+
+		MainExpression::FunctionCall {name, arguments, r#return, foreign} => {
+			let import = scope.import_ref(name, ItemKind::Function);
+			let callee = match (foreign, import, context.system) {
+				(Some(foreign), _, _) => foreign_expression(foreign, context),
+				(None, Some(import), ModuleSystem::CommonJS) => {
+					let path: Vec<Box<str>> = import.path[..import.path.len() - 1].iter()
+						.map(|name| Box::from(&**name))
+						.collect();
+					Expression::member(Expression::Identifier(module_binding(&path)), &**name)
+				},
+				_ => Expression::Identifier(binding(name))
+			};
+
+			let call = Expression::call(callee, arguments.iter()
+				.map(|argument| from_expression(argument, scope, context, names))
+				.collect());
+			match foreign {
+				Some(_) => from_foreign(call, r#return.as_ref(), context),
+				None => call
+			}
 		}
 	}
 }
 
-#[derive(Debug)]
-pub struct ClassItem {
-	name: Box<str>,
-	fields: Vec<Box<str>>
+/// Brings a value from JavaScript into the representation of its type, `r#type`.
+/// Only integers may be represented differently, or be out of range.
+fn from_foreign(value: Expression, r#type: Option<&TypeName>, context: Context)
+		-> Expression {
+	match r#type.and_then(TypeName::integer) {
+		Some(integer) => integer::from_foreign(value, integer, context.overflow),
+		None => value
+	}
 }
 
-impl Display for ClassItem {
-	fn fmt(&self, f: &mut Formatter) -> FMTResult {
-		write!(f, "class {}{{constructor(", self.name)?;
-		(0..self.fields.len())
-			.try_for_each(|index| if index == 0 {
-				write!(f, "_{}", index)
-			} else {
-				write!(f, ",_{}", index)
-			})?;
-		write!(f, "){{")?;
-		self.fields.iter().enumerate()
-			.try_for_each(|(index, field)| if index == 0 {
-				write!(f, "this[{:?}]=_{}", field, index)
-			} else {
-				write!(f, ";this[{:?}]=_{}", field, index)
-			})?;
-		write!(f, "}}}}")
+fn class_items(code: &Code) -> Vec<Statement> {
+	code.scope.types.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.filter_map(|(name, r#type)| match r#type {
+			Type::User {format, documentation, location} =>
+				Some((name, format, documentation, location)),
+			_ => None
+		})
+		.flat_map(|(name, format, documentation, location)| documented(
+			DocComment::new([documentation.as_deref()]),
+			Statement::Located(*location, Box::new(Statement::Class(Class {
+				name: Some(binding(name)),
+				superclass: None,
+				members: vec![constructor(&class_fields(format))]
+			})))
+		))
+		.collect()
+}
+
+/// The constructor of the class a data type is represented by, which takes the
+/// value of each field in turn.
+fn constructor(fields: &[Box<str>]) -> ClassMember {
+	let parameters = (0..fields.len()).map(positional);
+	let body = fields.iter().zip(parameters.clone())
+		.map(|(field, parameter)| Statement::Expression(Expression::Assignment {
+			operator: None,
+			target: Pattern::Member(Box::new(Expression::Member {
+				object: Box::new(Expression::This),
+				property: MemberProperty::Computed(Box::new(Expression::string(field.clone())))
+			})),
+			value: Box::new(Expression::Identifier(parameter))
+		}))
+		.collect();
+
+	ClassMember::Method {
+		r#static: false,
+		kind: MethodKind::Constructor,
+		key: PropertyKey::Identifier("constructor".into()),
+		function: Function {
+			name: None,
+			parameters: parameters.map(Pattern::Identifier).collect(),
+			body: Block(body)
+		}
 	}
 }
 
-pub fn from_main_representation(code: &Code) -> Block {
+/// The fields of the class a data type is represented by, in the order the
+/// class's constructor takes them.
+pub fn class_fields(format: &DataFormat) -> Vec<Box<str>> {
 	enum FormatFieldIterator<U, N, T>
 			where U: Iterator<Item = T>, N: Iterator<Item = T> {
 		Empty,
@@ -98,51 +704,35 @@ pub fn from_main_representation(code: &Code) -> Block {
 		}
 	}
 
-	let classes = code.scope.types.iter()
-		.filter_map(|(name, r#type)|
-			r#type.format_ref().map(|r#type| (name, r#type)))
-		.map(|(name, format)| match format {
-			DataFormat::Marker => ClassItem {
-				name: name.1.clone(),
-				fields: Vec::new()
-			},
+	match format {
+		DataFormat::Marker => Vec::new(),
 
-			DataFormat::Unnamed {fields} => ClassItem {
-				name: name.1.clone(),
-				fields: (0..fields.len())
-					.map(|index| format!("_{}", index).into_boxed_str())
-					.collect()
-			},
-
-			DataFormat::Named {fields, variants} => ClassItem {
-				name: name.1.clone(),
-				fields: (!variants.is_empty())
-					.then(|| "_variant".into())
-					.into_iter()
-					.chain(
-						fields.keys()
-							.map(|name| name.1.clone())
-					)
-					.chain(
-						variants.values()
-							.map(|variant| match variant {
-								EnumVariantFormat::Marker => FormatFieldIterator::Empty,
-
-								EnumVariantFormat::Unnamed {fields} =>
-									FormatFieldIterator::Unnamed((0..fields.len())
-										.map(|index| format!("_{}", index).into_boxed_str())),
-
-								EnumVariantFormat::Named {fields, variants} =>
-									FormatFieldIterator::Named(fields.keys()
-										.map(|name| name.1.clone()))
-							})
-							.flatten()
-							.dedup()
-					)
-					.collect()
-			}
-		})
-		.map(|class| Statement::ClassItem(class));
+		DataFormat::Unnamed {fields} => (0..fields.len()).map(positional).collect(),
 
-	Block(classes.collect())
+		DataFormat::Named {fields, variants} => (!variants.is_empty())
+			.then(|| VARIANT.into())
+			.into_iter()
+			.chain(
+				fields.keys()
+					.map(|name| field(name))
+					.sorted()
+			)
+			.chain(
+				variants.iter()
+					.sorted_by(|(a, _), (b, _)| a.cmp(b))
+					.flat_map(|(_, variant)| match variant {
+						EnumVariantFormat::Marker => FormatFieldIterator::Empty,
+
+						EnumVariantFormat::Unnamed {fields} =>
+							FormatFieldIterator::Unnamed((0..fields.len()).map(positional)),
+
+						EnumVariantFormat::Named {fields, ..} =>
+							FormatFieldIterator::Named(fields.keys()
+								.map(|name| field(name))
+								.sorted())
+					})
+					.unique()
+			)
+			.collect()
+	}
 }
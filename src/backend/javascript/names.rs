@@ -0,0 +1,119 @@
+//! The names rustscript items, variables and fields are given in JavaScript.
+//! Every rustscript identifier is a valid JavaScript identifier, but not every
+//! one may be used as a name, and the generated code needs names of its own.
+//! Names are changed by adding `$`, which rustscript identifiers cannot contain,
+//! so a changed name can never collide with one written in the source.
+
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+/// Words JavaScript does not allow as binding names in modules, which are
+/// always strict.
+const RESERVED: [&str; 48] = ["arguments", "await", "break", "case", "catch",
+	"class", "const", "continue", "debugger", "default", "delete", "do", "else",
+	"enum", "eval", "export", "extends", "false", "finally", "for", "function",
+	"if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+	"null", "package", "private", "protected", "public", "return", "static",
+	"super", "switch", "this", "throw", "true", "try", "typeof", "var", "void",
+	"while", "with", "yield"];
+
+/// The globals the generated code refers to, which user names must not shadow.
+const GENERATED: [&str; 6] = ["BigInt", "Error", "Object", "exports", "globalThis",
+	"require"];
+
+/// The name a rustscript item or variable is bound to in JavaScript. Reserved
+/// words, and the names of globals the generated code relies on, are suffixed
+/// with `$`.
+pub fn binding(name: &str) -> Box<str> {
+	match RESERVED.contains(&name) || GENERATED.contains(&name) {
+		true => format!("{}$", name).into(),
+		false => name.into()
+	}
+}
+
+/// The name a variable or argument is bound to when its own is already bound
+/// where it is declared, which it would otherwise collide with or shadow: its
+/// name suffixed with `$` and a number, which no other name is.
+pub fn fresh_binding(name: &str, index: usize) -> Box<str> {
+	format!("{}${}", name, index).into()
+}
+
+/// The name of the member a field declared in the source is stored in. The
+/// generated members, `_variant` and the positional `_0`, `_1`, and so on, are
+/// shared with no field, so fields named like them are suffixed with `$`.
+pub fn field(name: &str) -> Box<str> {
+	let positional = name.strip_prefix('_')
+		.is_some_and(|index| !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()));
+	match positional || name == VARIANT {
+		true => format!("{}$", name).into(),
+		false => name.into()
+	}
+}
+
+/// The member the variant of a data type with variants is stored in.
+pub const VARIANT: &str = "_variant";
+
+/// The member a data type's field at `index` is stored in, if it is unnamed.
+pub fn positional(index: usize) -> Box<str> {
+	format!("_{}", index).into()
+}
+
+/// The name a CommonJS module binds another module to.
+pub fn module_binding(path: &[Box<str>]) -> Box<str> {
+	format!("${}", path.join("$")).into()
+}
+
+/// The name a module binds a foreign module to, which collides with neither
+/// user names nor module bindings.
+pub fn foreign_binding(index: usize) -> Box<str> {
+	format!("$${}", index).into()
+}
+
+/// The name a runtime helper is bound to. Helpers' names begin with a letter,
+/// so they collide with neither foreign nor module bindings.
+pub fn helper_binding(name: &str) -> Box<str> {
+	format!("$${}", name).into()
+}
+
+/// Whether `name` may be written as a property name after a `.`, which unlike
+/// a binding name may be a reserved word.
+pub fn is_identifier_name(name: &str) -> bool {
+	let mut characters = name.chars();
+	characters.next()
+		.is_some_and(|first| first == '$' || first == '_' || is_xid_start(first))
+		&& characters.all(|character| character == '$' || is_xid_continue(character))
+}
+
+/// Whether `name` may be referred to as a variable, if it is not shadowed.
+pub fn is_identifier(name: &str) -> bool {
+	is_identifier_name(name) && !RESERVED.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{binding, field, foreign_binding, fresh_binding, helper_binding, is_identifier,
+		is_identifier_name, module_binding, positional};
+
+	#[test]
+	fn reserved_and_generated_names_are_changed() {
+		assert_eq!(["await", "eval", "require", "Object", "yield_", "object"].map(binding),
+			["await$", "eval$", "require$", "Object$", "yield_", "object"].map(Box::from));
+		assert_eq!(["_0", "_12", "_variant", "_", "_x", "_0x", "variant"].map(field),
+			["_0$", "_12$", "_variant$", "_", "_x", "_0x", "variant"].map(Box::from));
+		assert_eq!(field(&positional(3)), "_3$".into());
+	}
+
+	#[test]
+	fn generated_names_are_identifiers() {
+		assert_eq!(module_binding(&["a".into(), "b".into()]), "$a$b".into());
+		assert_eq!(foreign_binding(0), "$$0".into());
+		assert_eq!(helper_binding("wrap"), "$$wrap".into());
+		assert_eq!(fresh_binding("delete", 2), "delete$2".into());
+		assert!(["$a$b", "$$0", "$$wrap", "delete$2", "café"].into_iter().all(is_identifier));
+	}
+
+	#[test]
+	fn reserved_words_may_only_be_property_names() {
+		assert!(is_identifier_name("class") && !is_identifier("class"));
+		assert!(!is_identifier_name("") && !is_identifier_name("0a") && !is_identifier_name("a-b"));
+	}
+}
@@ -0,0 +1,240 @@
+//! Writes JavaScript while keeping track of where each statement is written,
+//! so that the output can be mapped back to the source it was compiled from.
+//! The same printing code writes every layout; it asks the printer for the
+//! whitespace that only a readable layout has.
+
+use crate::frontend::tokenizer::Location;
+use std::fmt::{Result as FMTResult, Write};
+
+/// Marks the output from `generated` onwards as compiled from `original`, or
+/// from nothing written in the source if it is `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mapping {
+	pub generated: Location,
+	pub original: Option<Location>
+}
+
+/// How JavaScript is laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+	/// Without any whitespace that is not needed, and on as few lines as
+	/// possible.
+	#[default]
+	Compact,
+	/// A statement per line, indented with tabs, with spaces around operators
+	/// and blank lines between items.
+	Readable
+}
+
+#[derive(Debug, Default)]
+pub struct Printer {
+	layout: Layout,
+	/// The number of levels the current line is indented by.
+	indent: usize,
+	output: String,
+	location: Location,
+	mappings: Vec<Mapping>,
+	/// The original locations of the statements being written, innermost last.
+	open: Vec<Location>
+}
+
+impl Printer {
+	pub fn new(layout: Layout) -> Self {
+		Self {layout, ..Self::default()}
+	}
+
+	pub fn readable(&self) -> bool {
+		self.layout == Layout::Readable
+	}
+
+	/// Writes a space that is only needed to be readable.
+	pub fn space(&mut self) -> FMTResult {
+		match self.readable() {
+			true => self.write_str(" "),
+			false => Ok(())
+		}
+	}
+
+	/// Ends a line that is only ended to be readable.
+	pub fn newline(&mut self) -> FMTResult {
+		match self.readable() {
+			true => self.line_break(),
+			false => Ok(())
+		}
+	}
+
+	/// Ends a line, as comments need to be, indenting the next if readable.
+	pub fn line_break(&mut self) -> FMTResult {
+		self.write_str("\n")?;
+		match self.readable() {
+			true => self.write_str(&"\t".repeat(self.indent)),
+			false => Ok(())
+		}
+	}
+
+	/// Leaves a blank line before the next one, if readable.
+	pub fn blank_line(&mut self) -> FMTResult {
+		match self.readable() {
+			true => self.write_str("\n"),
+			false => Ok(())
+		}
+	}
+
+	/// Writes a body in braces, on lines of its own unless it is empty.
+	pub fn braced(&mut self, empty: bool, body: impl FnOnce(&mut Self) -> FMTResult)
+			-> FMTResult {
+		self.write_str("{")?;
+		if !empty {
+			self.indent += 1;
+			self.newline()?;
+			body(self)?;
+			self.indent -= 1;
+			self.newline()?;
+		}
+		self.write_str("}")
+	}
+
+	/// Writes each item, separated by commas.
+	pub fn list<T>(&mut self, items: impl IntoIterator<Item = T>,
+			mut each: impl FnMut(&mut Self, T) -> FMTResult) -> FMTResult {
+		items.into_iter().enumerate().try_for_each(|(index, item)| {
+			if index > 0 {
+				self.write_str(",")?;
+				self.space()?;
+			}
+			each(self, item)
+		})
+	}
+
+	/// Ends a statement, which may need a `;`. A compact layout only has them
+	/// between statements, and so leaves it off the last.
+	pub fn terminate(&mut self, semicolon: bool, last: bool) -> FMTResult {
+		match semicolon && (self.readable() || !last) {
+			true => self.write_str(";"),
+			false => Ok(())
+		}
+	}
+
+	/// Writes a statement compiled from the item at `original`. Once it ends,
+	/// the output is mapped back to the statement around it, if any.
+	pub fn located(&mut self, original: Location, print: impl FnOnce(&mut Self) -> FMTResult)
+			-> FMTResult {
+		self.open.push(original);
+		self.map(Some(original));
+		print(self)?;
+		self.open.pop();
+		self.map(self.open.last().copied());
+		Ok(())
+	}
+
+	fn map(&mut self, original: Option<Location>) {
+		// Only the last mapping at any one place in the output has any effect.
+		if self.mappings.last().is_some_and(|last| last.generated == self.location)
+			{self.mappings.pop();}
+		self.mappings.push(Mapping {generated: self.location, original});
+	}
+
+	/// The output, along with where each part of it was compiled from.
+	pub fn finish(self) -> (String, Vec<Mapping>) {
+		(self.output, self.mappings)
+	}
+}
+
+impl Write for Printer {
+	fn write_str(&mut self, text: &str) -> FMTResult {
+		self.output += text;
+		self.location = self.location.advance(text);
+		Ok(())
+	}
+}
+
+/// Something written as JavaScript that may contain located statements.
+pub trait Print {
+	fn print(&self, printer: &mut Printer) -> FMTResult;
+
+	/// Writes this on its own in the compact layout, discarding where it was
+	/// compiled from.
+	fn printed(&self) -> String {
+		let mut printer = Printer::default();
+		self.print(&mut printer).expect("printing to a string cannot fail");
+		printer.finish().0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Layout, Print, Printer};
+	use crate::backend::javascript::ast::*;
+
+	fn identifier(name: &str) -> Expression {
+		Expression::Identifier(name.into())
+	}
+
+	fn printed(block: &Block, layout: Layout) -> String {
+		let mut printer = Printer::new(layout);
+		block.print(&mut printer).unwrap();
+		printer.finish().0
+	}
+
+	/// A module with an item of each kind, and statements around them.
+	fn module() -> Block {
+		let function = Function {
+			name: Some("f".into()),
+			parameters: vec![Pattern::Identifier("x".into())],
+			body: Block(vec![Statement::Return(Some(Expression::Binary {
+				operator: BinaryOperator::Add,
+				left: Box::new(identifier("x")),
+				right: Box::new(Expression::Literal(Literal::Number("1".into())))
+			}))])
+		};
+		let call = |name| Statement::Expression(Expression::call(identifier(name), Vec::new()));
+
+		Block(vec![
+			Statement::Import(ImportDeclaration::Named {
+				names: vec![("a".into(), "a".into())],
+				from: "./a.js".into()
+			}),
+			Statement::Import(ImportDeclaration::Module("./b.js".into())),
+			Statement::DocComment(DocComment(" Adds one.".into())),
+			Statement::Function(function),
+			Statement::Class(Class {name: Some("C".into()), superclass: None, members: Vec::new()}),
+			Statement::Variable(VariableDeclaration::new(VariableKind::Let, "y".into(),
+				Expression::call(identifier("f"), vec![identifier("a")]))),
+			Statement::If {
+				test: identifier("y"),
+				consequent: Box::new(Statement::Block(Block(vec![call("g"), call("h")]))),
+				alternate: None
+			},
+			Statement::Export(ExportDeclaration::Named(vec![("f".into(), "f".into())]))
+		])
+	}
+
+	#[test]
+	fn readable_layout_sets_items_apart() {
+		assert_eq!(printed(&module(), Layout::Readable), "\
+			import {a} from \"./a.js\";\n\
+			import \"./b.js\";\n\
+			\n\
+			/**\n * Adds one.\n */\n\
+			function f(x) {\n\
+			\treturn x + 1;\n\
+			}\n\
+			\n\
+			class C {}\n\
+			\n\
+			let y = f(a);\n\
+			if (y) {\n\
+			\tg();\n\
+			\th();\n\
+			}\n\
+			\n\
+			export {f};");
+	}
+
+	#[test]
+	fn compact_layout_has_only_needed_whitespace() {
+		assert_eq!(printed(&module(), Layout::Compact), "import{a}from\"./a.js\";\
+			import\"./b.js\";/**\n * Adds one.\n */function f(x){return x+1}class C{}\
+			let y=f(a);if(y){g();h()}export{f}");
+	}
+}
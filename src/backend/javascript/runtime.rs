@@ -0,0 +1,219 @@
+//! The helpers the generated code relies on. Each module declares the helpers
+//! it refers to itself, along with any those rely on in turn, so a program that
+//! needs none of them is written without any.
+
+use super::{ast::*, names::helper_binding};
+use std::collections::BTreeSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Helper {
+	/// Throws an error with a message, which ends the program unless caught.
+	Panic,
+	/// Checks that the result of an operation on integers is within the range
+	/// of their type, and returns it.
+	CheckInteger,
+	/// Checks that a value from JavaScript is a whole number or a `BigInt`,
+	/// and returns it.
+	CheckWhole,
+	/// Converts a value from JavaScript to a `BigInt` as `|0` converts it to a
+	/// number: rounding toward zero, with values that are not finite becoming
+	/// zero. `BigInt`s are returned as they are.
+	ToBigInt
+}
+
+impl Helper {
+	const ALL: [Self; 4] = [Self::Panic, Self::CheckInteger, Self::CheckWhole, Self::ToBigInt];
+
+	pub fn name(self) -> Box<str> {
+		helper_binding(match self {
+			Self::Panic => "panic",
+			Self::CheckInteger => "checkInteger",
+			Self::CheckWhole => "checkWhole",
+			Self::ToBigInt => "toBigInt"
+		})
+	}
+
+	/// Calls the helper with `arguments`:
+	///
+	/// - `Panic`: the message
+	/// - `CheckInteger`: the result, the least and greatest values of its type,
+	///   and the name of the operation, such as `"add"`
+	/// - `CheckWhole` and `ToBigInt`: the value
+	pub fn call(self, arguments: Vec<Expression>) -> Expression {
+		Expression::call(Expression::Identifier(self.name()), arguments)
+	}
+
+	fn declaration(self) -> Function {
+		let (parameters, body) = match self {
+			Self::Panic => (vec!["message"], vec![
+				Statement::Throw(Expression::New {
+					callee: Box::new(identifier("Error")),
+					arguments: vec![identifier("message")]
+				})
+			]),
+
+			Self::CheckInteger => (vec!["value", "min", "max", "operation"], vec![
+				Statement::If {
+					test: binary(BinaryOperator::LogicalOr,
+						binary(BinaryOperator::Less, identifier("value"), identifier("min")),
+						binary(BinaryOperator::Greater, identifier("value"), identifier("max"))),
+					consequent: Box::new(panic(join(BinaryOperator::Add, [
+						Expression::string("attempt to "),
+						identifier("operation"),
+						Expression::string(" with overflow")
+					]))),
+					alternate: None
+				},
+				Statement::Return(Some(identifier("value")))
+			]),
+
+			Self::CheckWhole => (vec!["value"], vec![
+				Statement::If {
+					test: binary(BinaryOperator::LogicalAnd,
+						binary(BinaryOperator::StrictNotEqual, type_of("value"),
+							Expression::string("bigint")),
+						Expression::Unary {
+							operator: UnaryOperator::Not,
+							argument: Box::new(Expression::call(
+								Expression::member(identifier("Number"), "isInteger"),
+								vec![identifier("value")]))
+						}),
+					consequent: Box::new(panic(binary(BinaryOperator::Add,
+						Expression::string("expected an integer, found "), identifier("value")))),
+					alternate: None
+				},
+				Statement::Return(Some(identifier("value")))
+			]),
+
+			Self::ToBigInt => (vec!["value"], vec![
+				Statement::If {
+					test: binary(BinaryOperator::StrictEqual, type_of("value"),
+						Expression::string("bigint")),
+					consequent: Box::new(Statement::Return(Some(identifier("value")))),
+					alternate: None
+				},
+				Statement::Return(Some(Expression::Conditional {
+					test: Box::new(Expression::call(
+						Expression::member(identifier("Number"), "isFinite"),
+						vec![identifier("value")])),
+					consequent: Box::new(Expression::call(identifier("BigInt"), vec![
+						Expression::call(Expression::member(identifier("Math"), "trunc"),
+							vec![identifier("value")])
+					])),
+					alternate: Box::new(Expression::Literal(Literal::BigInt("0".into())))
+				}))
+			]),
+		};
+
+		Function {
+			name: Some(self.name()),
+			parameters: parameters.into_iter()
+				.map(|parameter| Pattern::Identifier(parameter.into()))
+				.collect(),
+			body: Block(body)
+		}
+	}
+}
+
+/// Declares each helper `statements` refer to, and each helper those refer to
+/// in turn, in a fixed order.
+pub fn declarations(statements: &[Statement]) -> Vec<Statement> {
+	let mut used = BTreeSet::new();
+	let mut pending = referenced(statements);
+	while let Some(helper) = pending.pop() {
+		if used.insert(helper) {pending.extend(referenced(&helper.declaration()))}
+	}
+
+	used.into_iter()
+		.map(|helper| Statement::Function(helper.declaration()))
+		.collect()
+}
+
+/// The helpers something refers to. Only helpers have names beginning with
+/// `$$` followed by a letter, so no other name is mistaken for one.
+fn referenced(item: &(impl References + ?Sized)) -> Vec<Helper> {
+	let mut helpers = Vec::new();
+	item.references(&mut |name| helpers.extend(Helper::ALL.into_iter()
+		.find(|helper| *helper.name() == *name)));
+	helpers
+}
+
+fn identifier(name: &str) -> Expression {
+	Expression::Identifier(name.into())
+}
+
+fn type_of(name: &str) -> Expression {
+	Expression::Unary {operator: UnaryOperator::TypeOf, argument: Box::new(identifier(name))}
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+	Expression::Binary {operator, left: Box::new(left), right: Box::new(right)}
+}
+
+/// Joins operands with an operator, grouping them from the left as JavaScript
+/// does, so that they need no parentheses.
+fn join(operator: BinaryOperator, operands: impl IntoIterator<Item = Expression>)
+		-> Expression {
+	operands.into_iter()
+		.reduce(|left, right| binary(operator, left, right))
+		.expect("there is something to join")
+}
+
+fn panic(message: Expression) -> Statement {
+	Statement::Expression(Helper::Panic.call(vec![message]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{declarations, Helper};
+	use crate::backend::javascript::{ast::{Block, Expression, Statement}, printer::Print};
+	use std::{io::Write, process::{Command, Stdio}};
+
+	/// The names of the helpers declared for a call to each of `helpers`.
+	fn declared(helpers: &[Helper]) -> Vec<Box<str>> {
+		let calls: Vec<_> = helpers.iter()
+			.map(|helper| Statement::Expression(helper.call(Vec::new())))
+			.collect();
+		declarations(&calls).into_iter()
+			.map(|declaration| match declaration {
+				Statement::Function(function) => function.name.unwrap(),
+				declaration => panic!("expected a function, found {:?}", declaration)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn only_the_helpers_used_are_declared() {
+		assert!(declared(&[]).is_empty());
+		assert_eq!(declared(&[Helper::ToBigInt]), ["$$toBigInt".into()]);
+		// Helpers that panic rely on `Panic`, which is declared with them.
+		assert_eq!(declared(&[Helper::CheckWhole, Helper::CheckInteger, Helper::CheckWhole]),
+			["$$panic".into(), "$$checkInteger".into(), "$$checkWhole".into()]);
+	}
+
+	#[test]
+	fn helpers_behave_as_documented() {
+		let script = Block(declarations(&[Statement::Expression(Expression::Array(
+			Helper::ALL.map(|helper| helper.call(Vec::new())).into()))])).printed() + r#"
+			const attempt = f => {try {return f()} catch (error) {return error.message}};
+			console.log(attempt(() => $$checkInteger(300, 0, 255, "add")));
+			console.log(attempt(() => $$checkInteger(-3n, -4n, 3n, "negate")));
+			console.log(attempt(() => $$checkWhole(1.5)));
+			console.log(attempt(() => $$checkWhole(2n ** 70n)));
+			console.log(attempt(() => $$panic("stopped")));
+			console.log([-1.5, NaN, Infinity, 5n, 2 ** 60].map($$toBigInt));
+		"#;
+
+		let Ok(mut node) = Command::new("node")
+			.arg("-")
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.spawn()
+			else {return};
+		node.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+		let output = node.wait_with_output().unwrap();
+		assert_eq!(String::from_utf8_lossy(&output.stdout), "\
+			attempt to add with overflow\n-3n\nexpected an integer, found 1.5\n\
+			1180591620717411303424n\nstopped\n[ -1n, 0n, 0n, 5n, 1152921504606846976n ]\n");
+	}
+}
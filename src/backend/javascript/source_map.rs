@@ -0,0 +1,163 @@
+//! Source maps, in version 3 of the format, mapping the JavaScript of a module
+//! back to the rustscript it was compiled from.
+
+use super::printer::Mapping;
+use itertools::Itertools;
+use serde_json::json;
+use std::path::{absolute, Component, Path, PathBuf};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Writes the source map of the JavaScript file `file`, which was compiled
+/// entirely from `source`, as JSON. The text of the source is included when it
+/// is known, so that the map is of use wherever it is read.
+pub fn source_map(file: &str, source: &str, content: Option<&str>, mappings: &[Mapping])
+		-> String {
+	json!({
+		"version": 3,
+		"file": file,
+		"sources": [source],
+		"sourcesContent": [content],
+		"names": [],
+		"mappings": encode(mappings)
+	}).to_string()
+}
+
+/// The path of the source `to` relative to the directory `from`, as a source
+/// map refers to it, with `/` between its components as in a URL.
+pub fn relative_source(from: &Path, to: &Path) -> String {
+	// Neither path need exist, so they are only resolved textually. An empty
+	// path is the current directory.
+	let resolve = |path: &Path| absolute(Path::new(".").join(path))
+		.unwrap_or_else(|_| path.to_owned())
+		.components()
+		.fold(PathBuf::new(), |mut resolved, component| {
+			match component {
+				Component::CurDir => (),
+				Component::ParentDir => {resolved.pop();},
+				component => resolved.push(component)
+			}
+			resolved
+		});
+	let (from, to) = (resolve(from), resolve(to));
+
+	let common = from.components().zip(to.components())
+		.take_while(|(from, to)| from == to)
+		.count();
+	from.components().skip(common).map(|_| "..".into())
+		.chain(to.components().skip(common)
+			.map(|component| component.as_os_str().to_string_lossy()))
+		.join("/")
+}
+
+/// The comment that links a JavaScript file to its source map at `url`, which
+/// must begin a line of its own.
+pub fn link(url: &str) -> String {
+	format!("//# sourceMappingURL={}", url)
+}
+
+/// A data URL holding a source map, for embedding it in the file it maps.
+pub fn data_url(map: &str) -> String {
+	format!("data:application/json;charset=utf-8;base64,{}", base64(map.as_bytes()))
+}
+
+/// Encodes mappings, which must be in the order they occur in the output. Each
+/// field of a segment is relative to the same field in the segment before it,
+/// except the generated column, which starts again from zero on each line.
+fn encode(mappings: &[Mapping]) -> String {
+	let mut encoded = String::new();
+	let (mut line, mut column) = (0, 0);
+	let mut previous = (0, 0);
+
+	for mapping in mappings {
+		let generated = mapping.generated;
+		if generated.line > line {
+			encoded += &";".repeat((generated.line - line) as usize);
+			(line, column) = (generated.line, 0);
+		} else if !encoded.is_empty() && !encoded.ends_with(';') {
+			encoded.push(',');
+		}
+
+		vlq(&mut encoded, generated.column as i64 - column as i64);
+		column = generated.column;
+		if let Some(original) = mapping.original {
+			// Every module is compiled from a single source, the first.
+			vlq(&mut encoded, 0);
+			vlq(&mut encoded, original.line as i64 - previous.0 as i64);
+			vlq(&mut encoded, original.column as i64 - previous.1 as i64);
+			previous = (original.line, original.column);
+		}
+	}
+
+	encoded
+}
+
+/// Writes a number as a base 64 variable length quantity: five bits at a time
+/// from the least significant, with the sign in the lowest bit of the first
+/// group and the sixth bit of each digit set when more digits follow.
+fn vlq(output: &mut String, value: i64) {
+	let mut value = (value.unsigned_abs() << 1) | (value < 0) as u64;
+	loop {
+		let digit = (value & 0b11111) as usize;
+		value >>= 5;
+		match value {
+			0 => break output.push(BASE64[digit] as char),
+			_ => output.push(BASE64[digit | 0b100000] as char)
+		}
+	}
+}
+
+fn base64(bytes: &[u8]) -> String {
+	bytes.chunks(3)
+		.flat_map(|chunk| {
+			let group = chunk.iter().enumerate()
+				.fold(0u32, |group, (index, byte)| group | (*byte as u32) << (16 - 8 * index));
+			(0..4).map(move |index| match index <= chunk.len() {
+				true => BASE64[(group >> (18 - 6 * index) & 0b111111) as usize] as char,
+				false => '='
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{base64, encode, relative_source, vlq, Mapping};
+	use crate::frontend::tokenizer::Location;
+	use std::path::Path;
+
+	#[test]
+	fn numbers_are_base64_vlqs() {
+		let encoded = |value| {
+			let mut output = String::new();
+			vlq(&mut output, value);
+			output
+		};
+		assert_eq!([0, 1, -1, 15, 16, -17, 1000].map(encoded),
+			["A", "C", "D", "e", "gB", "jB", "w+B"]);
+		assert_eq!(["Man", "Ma", "M", ""].map(|text| base64(text.as_bytes())),
+			["TWFu", "TWE=", "TQ==", ""]);
+	}
+
+	#[test]
+	fn mappings_are_relative_to_the_one_before() {
+		let at = |line, column| Location {line, column};
+		let mappings = [
+			Mapping {generated: at(0, 0), original: Some(at(0, 0))},
+			Mapping {generated: at(0, 4), original: None},
+			Mapping {generated: at(2, 2), original: Some(at(1, 4))},
+			Mapping {generated: at(2, 3), original: Some(at(0, 1))}
+		];
+		assert_eq!(encode(&mappings), "AAAA,I;;EACI,CADH");
+	}
+
+	#[test]
+	fn sources_are_relative_to_the_javascript() {
+		let relative = |from, to| relative_source(Path::new(from), Path::new(to));
+		assert_eq!(relative("/crate", "/crate/main.rsst"), "main.rsst");
+		assert_eq!(relative("/crate/m", "/crate/m.rsst"), "../m.rsst");
+		assert_eq!(relative("/out/m", "/crate/m/n.rsst"), "../../crate/m/n.rsst");
+		assert_eq!(relative("/crate/./out/..", "/crate/src/../main.rsst"), "main.rsst");
+		assert_eq!(relative("", "main.rsst"), "main.rsst");
+	}
+}
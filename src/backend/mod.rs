@@ -1 +1,3 @@
+pub mod interpreter;
 pub mod javascript;
+pub mod typescript;
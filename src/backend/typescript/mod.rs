@@ -0,0 +1,481 @@
+use super::{super::frontend::{module::ItemKind, Code, DataFormat, EnumVariantFormat,
+	Foreign, IStr, Scope, ScopeRef, Type}, javascript::{class_fields, exported_names,
+	imported_names, is_exported, module_file, module_specifier, Imported,
+	ast::{DocComment, StringLiteral}, integer, names::{binding, field, is_identifier,
+	is_identifier_name, positional, VARIANT}}};
+use itertools::Itertools;
+use std::{collections::BTreeMap, fmt::{Display, Formatter, Result as FMTResult}};
+
+/// The contents of a `.d.ts` file, describing a module emitted by the
+/// JavaScript backend.
+#[derive(Debug)]
+pub struct DeclarationFile(Vec<Declaration>);
+
+impl Display for DeclarationFile {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		self.0.iter().try_for_each(|declaration| writeln!(f, "{}", declaration))
+	}
+}
+
+#[derive(Debug)]
+pub enum Declaration {
+	/// Each name imported, along with the name it is bound to.
+	Import {
+		names: Vec<(Box<str>, Box<str>)>,
+		from: Box<str>
+	},
+	NamespaceImport {
+		name: Box<str>,
+		from: Box<str>
+	},
+	/// Each name exported, along with the name it is exported as.
+	Export(Vec<(Box<str>, Box<str>)>),
+	NamespaceExport {
+		name: Box<str>,
+		from: Box<str>
+	},
+
+	Class(Class),
+	Union(Union),
+	Function(FunctionSignature),
+	/// A foreign class, bound to a name of its own.
+	Alias {
+		name: Box<str>,
+		r#type: Box<str>
+	},
+	DocComment(DocComment)
+}
+
+impl Display for Declaration {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Import {names, from} =>
+				write!(f, "import {{{}}} from {};", aliased(names), StringLiteral(from)),
+			Self::NamespaceImport {name, from} =>
+				write!(f, "import * as {} from {};", name, StringLiteral(from)),
+			Self::Export(names) => write!(f, "export {{{}}};", aliased(names)),
+			Self::NamespaceExport {name, from} =>
+				write!(f, "export * as {} from {};", name, StringLiteral(from)),
+
+			Self::Class(class) => class.fmt(f),
+			Self::Union(union) => union.fmt(f),
+			Self::Function(function) => function.fmt(f),
+			Self::Alias {name, r#type} => write!(f,
+				"declare const {name}: typeof {type};\ntype {name} = {type};",
+				name = name, type = r#type),
+			Self::DocComment(comment) => comment.fmt(f)
+		}
+	}
+}
+
+/// Lists names as an import or export does, with the names they are bound to or
+/// exported as when those differ.
+fn aliased(names: &[(Box<str>, Box<str>)]) -> String {
+	names.iter()
+		.map(|(name, alias)| match name == alias {
+			true => name.to_string(),
+			false => format!("{} as {}", name, alias)
+		})
+		.join(", ")
+}
+
+/// A data type with a single variant.
+#[derive(Debug)]
+pub struct Class {
+	name: Box<str>,
+	fields: Vec<(Box<str>, Box<str>)>
+}
+
+impl Display for Class {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		writeln!(f, "declare class {} {{", self.name)?;
+		// Parameters are positional, as in the JavaScript constructor, since
+		// fields may be named like reserved words.
+		writeln!(f, "\tconstructor({});", self.fields.iter().enumerate()
+			.map(|(index, (_, r#type))| format!("{}: {}", positional(index), r#type))
+			.join(", "))?;
+		self.fields.iter()
+			.try_for_each(|(name, r#type)| writeln!(f, "\t{}: {};", name, r#type))?;
+		write!(f, "}}")
+	}
+}
+
+/// The types of a variant's fields, by name.
+pub type VariantFields = BTreeMap<Box<str>, Box<str>>;
+
+/// A data type with multiple variants, described as a union discriminated by
+/// `_variant`, along with the constructor of the class representing it.
+#[derive(Debug)]
+pub struct Union {
+	name: Box<str>,
+	/// The parameters of the constructor, in order.
+	parameters: Vec<Box<str>>,
+	variants: Vec<(Box<str>, VariantFields)>
+}
+
+impl Display for Union {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "type {} = {};", self.name, self.variants.iter()
+			.map(|(variant, fields)| format!("{{{}: {}{}}}", VARIANT, StringLiteral(variant),
+				fields.iter()
+					.map(|(name, r#type)| format!("; {}: {}", name, r#type))
+					.join("")))
+			.join(" | "))?;
+
+		writeln!(f, "\ndeclare const {}: {{", self.name)?;
+		self.variants.iter().try_for_each(|(variant, fields)| writeln!(f,
+			"\tnew ({}): {};", self.parameters.iter().enumerate()
+				.map(|(index, parameter)| format!("{}: {}", positional(index), match &**parameter {
+					VARIANT => StringLiteral(variant).to_string(),
+					parameter => fields.get(parameter).map(|r#type| &**r#type)
+						.unwrap_or("undefined").into()
+				}))
+				.join(", "), self.name))?;
+		write!(f, "}};")
+	}
+}
+
+#[derive(Debug)]
+pub struct FunctionSignature {
+	name: Box<str>,
+	arguments: Vec<(Box<str>, Box<str>)>,
+	r#return: Box<str>
+}
+
+impl Display for FunctionSignature {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "declare function {}({}): {};", self.name, self.arguments.iter()
+			.map(|(name, r#type)| format!("{}: {}", name, r#type))
+			.join(", "), self.r#return)
+	}
+}
+
+/// The declarations of a JavaScript module, to be written alongside it.
+#[derive(Debug)]
+pub struct Module {
+	/// The path of the module from the crate root.
+	pub path: Vec<Box<str>>,
+	pub declarations: DeclarationFile
+}
+
+impl Module {
+	/// The file these declarations are written to, relative to the directory of
+	/// the crate root's file, `root`.
+	pub fn file(&self, root: &str) -> String {
+		declaration_file(&module_file(&self.path, root))
+	}
+}
+
+/// The name TypeScript looks for the declarations of a JavaScript file at.
+fn declaration_file(file: &str) -> String {
+	[(".js", ".d.ts"), (".mjs", ".d.mts"), (".cjs", ".d.cts")].into_iter()
+		.find_map(|(extension, declaration)| file.strip_suffix(extension)
+			.map(|file| format!("{}{}", file, declaration)))
+		.unwrap_or_else(|| format!("{}.d.ts", file))
+}
+
+/// Describes each module `javascript::from_main_representation` produces for a
+/// crate. `root` is the name of the file the crate root is written to.
+pub fn from_main_representation(code: &Code, root: &str) -> Vec<Module> {
+	fn from_module(code: &Code, documentation: Option<&str>, root: &str, path: &[Box<str>],
+			imported: &Imported, modules: &mut Vec<Module>) {
+		let prelude = Scope::prelude();
+		let prelude = ScopeRef::new(&prelude);
+		let scope = prelude.r#in(&code.scope);
+
+		// The module is documented as it is in its JavaScript.
+		let documentation = DocComment::new([documentation, code.documentation.as_deref()])
+			.map(|DocComment(text)|
+				Declaration::DocComment(DocComment(format!("{}\n @module", text).into())));
+		let declarations = documentation.into_iter()
+			.chain(import_declarations(code, root, path))
+			.chain(type_declarations(code, scope, path, imported))
+			.chain(function_declarations(code, scope, path, imported))
+			.chain(export_declarations(code, root, path, imported))
+			.collect();
+		modules.push(Module {path: path.to_vec(), declarations: DeclarationFile(declarations)});
+
+		code.scope.modules.iter()
+			.sorted_by(|(a, _), (b, _)| a.cmp(b))
+			.for_each(|(name, module)| {
+				let path: Vec<_> = path.iter().cloned().chain([Box::from(&**name)]).collect();
+				from_module(&module.code, module.documentation.as_deref(), root, &path,
+					imported, modules)
+			});
+	}
+
+	let mut imported = BTreeMap::new();
+	imported_names(code, &mut imported);
+	let mut modules = Vec::new();
+	from_module(code, None, root, &[], &imported, &mut modules);
+	modules
+}
+
+fn import_declarations(code: &Code, root: &str, path: &[Box<str>])
+		-> Vec<Declaration> {
+	let (modules, items): (Vec<_>, Vec<_>) = code.scope.imports.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.partition(|(_, import)| import.kind == ItemKind::Module);
+
+	let modules = modules.into_iter()
+		.map(|(name, import)| {
+			let to: Vec<Box<str>> = import.path.iter().map(|name| Box::from(&**name)).collect();
+			Declaration::NamespaceImport {
+				name: binding(name),
+				from: module_specifier(path, &to, root)
+			}
+		});
+
+	let items = items.into_iter()
+		.fold(BTreeMap::<_, Vec<_>>::new(), |mut items, (name, import)| {
+			let from: Vec<Box<str>> = import.path[..import.path.len() - 1].iter()
+				.map(|name| Box::from(&**name))
+				.collect();
+			items.entry(from).or_default().push((Box::from(&**name), binding(name)));
+			items
+		})
+		.into_iter()
+		.map(|(from, names)| Declaration::Import {
+			names,
+			from: module_specifier(path, &from, root)
+		});
+
+	modules.chain(items).collect()
+}
+
+/// Exports what the module's JavaScript does: its public items, and those other
+/// modules import from it.
+fn export_declarations(code: &Code, root: &str, path: &[Box<str>], imported: &Imported)
+		-> Vec<Declaration> {
+	let (modules, names): (Vec<_>, Vec<_>) = exported_names(code, path, imported).into_iter()
+		.partition(|name| code.scope.modules.contains_key(*name));
+
+	let modules = modules.into_iter()
+		.map(|name| {
+			let to: Vec<_> = path.iter().cloned().chain([Box::from(name)]).collect();
+			Declaration::NamespaceExport {
+				name: name.into(),
+				from: module_specifier(path, &to, root)
+			}
+		});
+
+	// An empty export still marks the file as a module.
+	let names = names.iter().map(|name| (binding(name), Box::from(*name))).collect();
+	modules.chain([Declaration::Export(names)]).collect()
+}
+
+fn type_declarations(code: &Code, scope: ScopeRef, path: &[Box<str>], imported: &Imported)
+		-> Vec<Declaration> {
+	code.scope.types.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.filter_map(|(name, r#type)| Some((r#type, match r#type {
+			Type::User {format: format @ DataFormat::Named {variants, ..}, ..}
+					if !variants.is_empty() => Declaration::Union(Union {
+				name: binding(name),
+				parameters: class_fields(format),
+				variants: variants.iter()
+					.sorted_by(|(a, _), (b, _)| a.cmp(b))
+					.map(|(variant, format)| (Box::from(&**variant), match format {
+						EnumVariantFormat::Marker => BTreeMap::new(),
+						EnumVariantFormat::Unnamed {fields} => fields.iter().enumerate()
+							.map(|(index, r#type)|
+								(positional(index), type_reference(r#type, scope)))
+							.collect(),
+						EnumVariantFormat::Named {fields, ..} => fields.iter()
+							.map(|(name, r#type)| (field(name), type_reference(r#type, scope)))
+							.collect()
+					}))
+					.collect()
+			}),
+
+			Type::User {format, ..} => {
+				let fields = class_fields(format);
+				// Named fields are taken in the order of the names they are stored
+				// under, as the constructor takes them.
+				let types: Vec<_> = match format {
+					DataFormat::Marker => Vec::new(),
+					DataFormat::Unnamed {fields} => fields.iter().collect(),
+					DataFormat::Named {fields: types, ..} => types.iter()
+						.sorted_by_key(|(name, _)| field(name))
+						.map(|(_, r#type)| r#type)
+						.collect()
+				};
+
+				Declaration::Class(Class {
+					name: binding(name),
+					fields: fields.iter().cloned()
+						.zip(types)
+						.map(|(field, r#type)| (field, type_reference(r#type, scope)))
+						.collect()
+				})
+			},
+
+			// Foreign types need a name of their own only when they are exported,
+			// just as in the JavaScript output.
+			Type::Foreign {binding: foreign, ..} if is_exported(code, name, path, imported) =>
+				Declaration::Alias {
+					name: binding(name),
+					r#type: foreign_reference(foreign)
+				},
+
+			_ => return None
+		})))
+		.flat_map(|(r#type, declaration)| {
+			let documentation = match r#type {
+				Type::User {documentation, ..} => documentation.as_deref(),
+				_ => None
+			};
+			documented(DocComment::new([documentation]), declaration)
+		})
+		.collect()
+}
+
+fn function_declarations(code: &Code, scope: ScopeRef, path: &[Box<str>], imported: &Imported)
+		-> Vec<Declaration> {
+	code.scope.functions.iter()
+		.sorted_by(|(a, _), (b, _)| a.cmp(b))
+		.filter(|(name, _)| is_exported(code, name, path, imported))
+		.flat_map(|(name, function)| documented(DocComment::new([
+			function.documentation.as_deref(),
+			function.code.documentation.as_deref()
+		]), Declaration::Function(FunctionSignature {
+			name: binding(name),
+			arguments: function.arguments.iter()
+				.map(|(name, r#type)| (binding(name), type_reference(&r#type.name, scope)))
+				.collect(),
+			r#return: function.r#return.as_ref()
+				.map(|r#type| type_reference(&r#type.name, scope))
+				.unwrap_or_else(|| "void".into())
+		})))
+		.collect()
+}
+
+/// Precedes a declaration with its documentation, if it has any.
+fn documented(documentation: Option<DocComment>, declaration: Declaration)
+		-> impl Iterator<Item = Declaration> {
+	documentation.map(Declaration::DocComment).into_iter().chain([declaration])
+}
+
+/// The TypeScript type a rustscript type is represented by.
+fn type_reference(r#type: &IStr, scope: ScopeRef) -> Box<str> {
+	match scope.type_ref(r#type) {
+		Some(Type::Integer(integer)) if integer::is_big(*integer) => "bigint".into(),
+		Some(Type::Integer(_) | Type::Float) => "number".into(),
+		Some(Type::Boolean) => "boolean".into(),
+		Some(Type::String | Type::Character) => "string".into(),
+		Some(Type::Foreign {binding, ..}) => foreign_reference(binding),
+		// User types, including imported ones, are referred to by name.
+		Some(Type::User {..}) | None => binding(r#type)
+	}
+}
+
+/// The type a foreign item is referred to by. Types can only be named by paths
+/// of valid names, so any other is left as `unknown`.
+fn foreign_reference(foreign: &Foreign) -> Box<str> {
+	let mut path = foreign.name.split('.');
+	let named = match &foreign.from {
+		Some(_) => path.all(is_identifier_name),
+		None => path.next().is_some_and(is_identifier) && path.all(is_identifier_name)
+	};
+
+	match (named, &foreign.from) {
+		(false, _) => "unknown".into(),
+		(true, Some(from)) => format!("import({}).{}", StringLiteral(from), foreign.name).into(),
+		(true, None) => foreign.name.clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::compiler::{Compiler, Emit, Options};
+	use std::{env::temp_dir, fs::{create_dir_all, remove_dir_all, write}, path::Path,
+		process::{self, Command}};
+
+	fn declarations(source: &str) -> Vec<(String, String)> {
+		let options = Options {emit: vec![Emit::Declarations], ..Options::default()};
+		Compiler::new(options).compile_str(source, Path::new("")).unwrap().files.into_iter()
+			.map(|file| (file.path, file.contents))
+			.collect()
+	}
+
+	/// Checks declaration files with the TypeScript compiler, if it can be run.
+	fn type_check(name: &str, files: &[(String, String)]) {
+		let directory = temp_dir().join(format!("rustscript-{}-{}", name, process::id()));
+		let paths: Vec<_> = files.iter()
+			.map(|(path, contents)| {
+				let path = directory.join(path);
+				create_dir_all(path.parent().unwrap()).unwrap();
+				write(&path, contents).unwrap();
+				path
+			})
+			.collect();
+
+		let tsc = Command::new("tsc")
+			.args(["--noEmit", "--strict", "--target", "es2022", "--module", "esnext",
+				"--moduleResolution", "bundler"])
+			.args(&paths)
+			.output();
+		remove_dir_all(&directory).ok();
+		if let Ok(tsc) = tsc {
+			assert!(tsc.status.success(), "{}", String::from_utf8_lossy(&tsc.stdout))
+		}
+	}
+
+	#[test]
+	fn modules_are_documented() {
+		let files = declarations("//! The crate.\n\
+			/// A module.\nmod m {\n//! Its items.\npub fn f() {}\n}\nmod n {}");
+		let [(root, root_file), (m, m_file), (n, n_file)] = &files[..] else {
+			panic!("expected three files, found {:?}", files)
+		};
+
+		assert_eq!((&**root, &**m, &**n), ("main.d.ts", "m.d.ts", "n.d.ts"));
+		assert!(root_file.starts_with("/**\n * The crate.\n * @module\n */\n"), "{}", root_file);
+		assert!(m_file.starts_with("/**\n * A module.\n * Its items.\n * @module\n */\n"),
+			"{}", m_file);
+		assert!(!n_file.contains("@module"), "{}", n_file);
+	}
+
+	#[test]
+	fn modules_export_what_other_modules_import() {
+		let files = declarations("mod a {fn f() {} data Hidden; fn unused() {} \
+			mod b {use super::f; use super::Hidden; pub fn g(h: Hidden) {f();}}}");
+		let [_, (a, a_file), (b, b_file)] = &files[..] else {
+			panic!("expected three files, found {:?}", files)
+		};
+
+		assert_eq!((&**a, &**b), ("a.d.ts", "a/b.d.ts"));
+		assert!(a_file.contains("declare function f(): void;\n"), "{}", a_file);
+		assert!(!a_file.contains("unused"), "{}", a_file);
+		assert!(a_file.ends_with("export {Hidden, f};\n"), "{}", a_file);
+		assert!(b_file.starts_with("import {Hidden, f} from \"../a.js\";\n"), "{}", b_file);
+		type_check("imports", &files);
+	}
+
+	#[test]
+	fn fields_may_be_named_like_reserved_words() {
+		let files = declarations("pub data P {new: i64, class: bool} \
+			pub data E {A {delete: i32}, B(u8)}");
+		let [(_, file)] = &files[..] else {panic!("expected one file, found {:?}", files)};
+
+		assert!(file.contains("\tconstructor(_0: boolean, _1: bigint);\n\tclass: boolean;\n"),
+			"{}", file);
+		assert!(file.contains("\tnew (_0: \"A\", _1: number, _2: undefined): E;\n"), "{}", file);
+		type_check("reserved", &files);
+	}
+
+	#[test]
+	fn items_named_like_reserved_words_are_exported_as_themselves() {
+		let files = declarations("mod m {pub fn delete() {} pub mod class {}} \
+			use m::delete; use m::class; pub fn main() {delete();}");
+		let [(_, root_file), (_, m_file), _] = &files[..] else {
+			panic!("expected three files, found {:?}", files)
+		};
+
+		assert!(root_file.starts_with("import * as class$ from \"./m/class.js\";\n\
+			import {delete as delete$} from \"./m.js\";\n"), "{}", root_file);
+		assert!(m_file.ends_with("export * as class from \"./m/class.js\";\n\
+			export {delete$ as delete};\n"), "{}", m_file);
+		type_check("reserved-exports", &files);
+	}
+}
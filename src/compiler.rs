@@ -0,0 +1,543 @@
+//! The compiler as a library: a `Compiler` runs every stage of compilation on a
+//! crate and collects what was asked of it, reporting anything short of an
+//! error to a diagnostics sink.
+
+use crate::{backend::{javascript::{self, integer::Overflow, printer::{Layout, Print,
+		Printer}, source_map, ModuleSystem}, typescript},
+	frontend::{construct_main_representation, error::{error, Error, ErrorCode},
+		lint::confusable_identifiers, module::{load, load_source, Declarations, ModuleRef},
+		parser::Block, tokenizer::{Token, Tokenizer}, Code, Scope, ScopeRef}};
+use itertools::Itertools;
+use serde::Serialize;
+use std::{collections::BTreeSet, fmt::{Display, Formatter, Result as FMTResult},
+	fs::read_to_string, io, path::{Path, PathBuf}};
+
+/// A kind of file written for a crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+	Js,
+	/// TypeScript `.d.ts` files describing the JavaScript.
+	Declarations
+}
+
+impl Emit {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"js" => Some(Self::Js),
+			"declarations" => Some(Self::Declarations),
+			_ => None
+		}
+	}
+}
+
+/// Where the source maps of emitted JavaScript are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceMaps {
+	/// To a `.js.map` file next to each JavaScript file.
+	File,
+	/// Into each JavaScript file itself, as a data URL.
+	Inline
+}
+
+impl SourceMaps {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"file" => Some(Self::File),
+			"inline" => Some(Self::Inline),
+			_ => None
+		}
+	}
+}
+
+/// A stage of compilation whose output can be dumped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dump {
+	Tokens,
+	Ast,
+	MainIr,
+	JsIr
+}
+
+impl Dump {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"tokens" => Some(Self::Tokens),
+			"ast" => Some(Self::Ast),
+			"main-ir" => Some(Self::MainIr),
+			"js-ir" => Some(Self::JsIr),
+			_ => None
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Tokens => "tokens",
+			Self::Ast => "ast",
+			Self::MainIr => "main-ir",
+			Self::JsIr => "js-ir"
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+	/// Source-like text where the stage has a source form, and debug output
+	/// otherwise.
+	#[default]
+	Pretty,
+	Json
+}
+
+impl DumpFormat {
+	/// The extension of the files dumps in this format are written to.
+	pub fn extension(self) -> &'static str {
+		match self {
+			Self::Pretty => "txt",
+			Self::Json => "json"
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+	pub target: ModuleSystem,
+	/// The name of the file the crate root's JavaScript is written to, which
+	/// every other module is imported relative to. By default it is named after
+	/// the crate root, or `main.js` if it was not read from a file.
+	pub root: Option<String>,
+	pub emit: Vec<Emit>,
+	/// How emitted JavaScript is laid out.
+	pub layout: Layout,
+	/// Maps emitted JavaScript back to the source it was compiled from.
+	pub source_maps: Option<SourceMaps>,
+	/// The directory the crate root's JavaScript is written to, which source
+	/// maps name sources relative to. By default it is the crate root's own
+	/// directory.
+	pub directory: Option<PathBuf>,
+	/// What the JavaScript does when an integer from outside of rustscript is
+	/// out of range for its type.
+	pub overflow: Overflow,
+	pub dump: Vec<Dump>,
+	pub dump_format: DumpFormat,
+	/// Makes the crate root call its `main` function once it is loaded, which
+	/// it is an error for the crate not to have.
+	pub call_main: bool
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			target: ModuleSystem::default(),
+			root: None,
+			emit: vec![Emit::Js],
+			layout: Layout::default(),
+			source_maps: None,
+			directory: None,
+			overflow: Overflow::default(),
+			dump: Vec::new(),
+			dump_format: DumpFormat::default(),
+			call_main: false
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	/// Describes a stage of compilation as it happens.
+	Note,
+	Warning
+}
+
+/// Something the compiler reports about a crate that does not stop it from
+/// being compiled. Errors are returned instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub message: String
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self.severity {
+			Severity::Note => write!(f, "{}", self.message),
+			Severity::Warning => write!(f, "warning: {}", self.message)
+		}
+	}
+}
+
+/// Why a crate could not be compiled.
+#[derive(Debug)]
+pub enum Failure {
+	/// The crate root could not be read.
+	Io(PathBuf, io::Error),
+	/// The crate has an error.
+	Error(Error)
+}
+
+impl Display for Failure {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Io(path, cause) => write!(f, "error: could not read {:?}: {}", path, cause),
+			Self::Error(error) => write!(f, "{}", error)
+		}
+	}
+}
+
+impl From<Error> for Failure {
+	fn from(error: Error) -> Self {
+		Self::Error(error)
+	}
+}
+
+/// A file written for a crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputFile {
+	/// The path of the file, relative to the directory of the crate root's file.
+	pub path: String,
+	pub contents: String
+}
+
+/// Everything a compiler was asked to produce for a crate.
+#[derive(Debug, Default)]
+pub struct Output<'s> {
+	/// The crate's main representation, which the interpreter runs.
+	pub code: Code<'s>,
+	/// The file the crate root's JavaScript is written to.
+	pub root: String,
+	/// Every emitted file: JavaScript, then source maps, then declarations,
+	/// each with the crate root first.
+	pub files: Vec<OutputFile>,
+	/// Each dumped stage, in the order the stages run.
+	pub dumps: Vec<(Dump, String)>
+}
+
+pub struct Compiler<'d> {
+	pub options: Options,
+	diagnostics: Box<dyn FnMut(&Diagnostic) + 'd>,
+	sources: BTreeSet<PathBuf>
+}
+
+impl<'d> Compiler<'d> {
+	/// Creates a compiler that ignores diagnostics.
+	pub fn new(options: Options) -> Self {
+		Self {options, diagnostics: Box::new(|_| ()), sources: BTreeSet::new()}
+	}
+
+	/// Reports diagnostics to `sink` as they happen.
+	pub fn with_diagnostics(self, sink: impl FnMut(&Diagnostic) + 'd) -> Self {
+		Self {diagnostics: Box::new(sink), ..self}
+	}
+
+	/// Every path the source of the last crate compiled was looked for at,
+	/// whether or not a file was found there. The crate will compile
+	/// differently only if one of these files changes.
+	pub fn sources(&self) -> &BTreeSet<PathBuf> {
+		&self.sources
+	}
+
+	/// Compiles the crate root at `path`, along with every module it declares.
+	pub fn compile_file(&mut self, path: &Path) -> Result<Output<'static>, Failure> {
+		self.sources = BTreeSet::from([path.to_owned()]);
+		let source = read_to_string(path)
+			.map_err(|cause| Failure::Io(path.to_owned(), cause))?;
+		let root = path.file_name()
+			.map(|name| Path::new(name).with_extension("js").to_string_lossy().into_owned());
+		self.note(format_args!("loading {}", path.display()));
+
+		self.compile(&source, root, Some(path), |sources| load(path, sources))
+			.map_err(|error| Failure::Error(error.in_file(path)))
+	}
+
+	/// Compiles a crate root given as source text, such as one read from
+	/// standard input, loading the modules it declares from `directory`.
+	pub fn compile_str<'s>(&mut self, source: &'s str, directory: &Path)
+			-> Result<Output<'s>, Failure> {
+		self.sources = BTreeSet::new();
+		self.note("loading <stdin>");
+		self.compile(source, None, None, |sources| load_source(source, directory, sources))
+			.map_err(|error| Failure::Error(error.in_file(Path::new("<stdin>"))))
+	}
+
+	/// Compiles a crate root, read from the file at `path` if it was read from
+	/// a file.
+	fn compile<'s>(&mut self, source: &str, root: Option<String>, path: Option<&Path>,
+			load: impl FnOnce(&mut BTreeSet<PathBuf>) -> Result<Block<'s>, Error>)
+			-> Result<Output<'s>, Error> {
+		let root = self.options.root.clone()
+			.or(root)
+			.unwrap_or_else(|| "main.js".into());
+		let mut output = Output {root: root.clone(), ..Output::default()};
+
+		if self.dumps(Dump::Tokens) {
+			let tokens = Tokenizer::new(source).collect::<Result<Vec<_>, _>>()?;
+			output.dumps.push((Dump::Tokens,
+				self.dump(&tokens, tokens.iter().map(Token::to_string).join("\n"))));
+		}
+
+		let block = load(&mut self.sources)?;
+		if self.dumps(Dump::Ast) {output.dumps.push((Dump::Ast, self.dump(&block, &block)))}
+
+		confusable_identifiers(&block).into_iter().for_each(|(first, second)|
+			self.warn(format_args!("identifiers {:?} and {:?} could be confused",
+				first, second)));
+
+		self.note("checking");
+		let items = Declarations::declare(&block)?;
+		let scope = Scope::prelude();
+		let code = construct_main_representation(&block, ScopeRef::new(&scope),
+			ModuleRef::new(&items))?;
+		if self.dumps(Dump::MainIr) {output.dumps.push((Dump::MainIr, self.dump(&code, &code)))}
+
+		let modules = self.generate(&code, &root)?;
+		if self.dumps(Dump::JsIr) {
+			output.dumps.push((Dump::JsIr, self.dump(&modules, format_args!("{:#?}", modules))))
+		}
+
+		if self.options.emit.contains(&Emit::Js) {
+			let mut maps = Vec::new();
+			for module in &modules {
+				let (file, map) = self.javascript(module, module.file(&root), &code,
+					(source, path));
+				output.files.push(file);
+				maps.extend(map);
+			}
+			output.files.extend(maps);
+		}
+		if self.options.emit.contains(&Emit::Declarations) {
+			output.files.extend(typescript::from_main_representation(&code, &root).iter()
+				.map(|module| OutputFile {
+					path: module.file(&root),
+					contents: module.declarations.to_string()
+				}));
+		}
+
+		Ok(Output {code, ..output})
+	}
+
+	fn generate(&mut self, code: &Code, root: &str) -> Result<Vec<javascript::Module>, Error> {
+		self.note("generating JavaScript");
+		let mut modules = javascript::from_main_representation(code, root, self.options.target,
+			self.options.overflow);
+
+		// Output Collision Checks
+		if let Some(module) = modules.iter()
+				.find(|module| !module.path.is_empty() && module.file(root) == root) {
+			return Err(error(ErrorCode::OutputCollision, format!(
+				"module {:?} would be written to {:?}, the crate root's file; \
+					rename the module or the output file",
+				module.path.join("::"), root)))
+		}
+
+		if self.options.call_main {
+			if !code.scope.functions.contains_key("main")
+				{return Err(error(ErrorCode::MissingMain, "the crate has no `main` function"))}
+			modules.iter_mut()
+				.find(|module| module.path.is_empty())
+				.expect("the crate root is always a module")
+				.block.call("main");
+		}
+
+		Ok(modules)
+	}
+
+	/// Writes a module's JavaScript to `file`, along with its source map if
+	/// one was asked for, which is either embedded in it or returned as a file
+	/// of its own. `root` is the source of the crate root, and the file it was
+	/// read from, if any.
+	fn javascript(&self, module: &javascript::Module, file: String, code: &Code,
+			root: (&str, Option<&Path>)) -> (OutputFile, Option<OutputFile>) {
+		let mut printer = Printer::new(self.options.layout);
+		module.block.print(&mut printer).expect("printing to a string cannot fail");
+		let (mut contents, mappings) = printer.finish();
+		// Readable files end their last line, as text files do.
+		let readable = self.options.layout == Layout::Readable;
+		let end_line = |contents: &mut String| if !contents.is_empty() && !contents.ends_with('\n')
+			{contents.push('\n')};
+		if readable {end_line(&mut contents)}
+
+		let Some(kind) = self.options.source_maps else {
+			return (OutputFile {path: file, contents}, None)
+		};
+
+		// A module is compiled from the file of the nearest module around it
+		// that was loaded from a file.
+		let mut source = root.1.map(Path::to_path_buf);
+		let mut current = code;
+		for name in &module.path {
+			let nested = &current.scope.modules[&**name];
+			if nested.file.is_some() {source = nested.file.clone()}
+			current = &nested.code;
+		}
+		let content = match &source {
+			Some(source) if Some(&**source) != root.1 => read_to_string(source).ok(),
+			_ => Some(root.0.into())
+		};
+		// Sources are named relative to the JavaScript, as that is where they
+		// are looked for from, so that the two may be moved together.
+		let name = match &source {
+			Some(source) => {
+				let directory = self.options.directory.as_deref()
+					.or_else(|| root.1.and_then(Path::parent))
+					.unwrap_or_else(|| Path::new(""))
+					.join(Path::new(&file).parent().unwrap_or_else(|| Path::new("")));
+				source_map::relative_source(&directory, source)
+			},
+			None => "<stdin>".into()
+		};
+
+		let file_name = Path::new(&file).file_name()
+			.map(|name| name.to_string_lossy().into_owned())
+			.unwrap_or_else(|| file.clone());
+		let map = source_map::source_map(&file_name, &name, content.as_deref(), &mappings);
+		let (url, map) = match kind {
+			SourceMaps::Inline => (source_map::data_url(&map), None),
+			SourceMaps::File => (format!("{}.map", file_name),
+				Some(OutputFile {path: format!("{}.map", file), contents: map}))
+		};
+		end_line(&mut contents);
+		contents += &source_map::link(&url);
+		if readable {end_line(&mut contents)}
+
+		(OutputFile {path: file, contents}, map)
+	}
+
+	fn dumps(&self, stage: Dump) -> bool {
+		self.options.dump.contains(&stage)
+	}
+
+	/// Writes the output of a stage of compilation, either as JSON or in the
+	/// given pretty form.
+	fn dump(&self, value: &impl Serialize, pretty: impl Display) -> String {
+		match self.options.dump_format {
+			DumpFormat::Pretty => pretty.to_string(),
+			DumpFormat::Json => serde_json::to_string_pretty(value)
+				.expect("dumps are always serializable")
+		}
+	}
+
+	fn note(&mut self, message: impl Display) {
+		(self.diagnostics)(&Diagnostic {severity: Severity::Note, message: message.to_string()})
+	}
+
+	fn warn(&mut self, message: impl Display) {
+		(self.diagnostics)(&Diagnostic {severity: Severity::Warning, message: message.to_string()})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Compiler, Dump, Emit, Failure, Options, Severity, SourceMaps};
+	use crate::frontend::error::ErrorCode::*;
+	use serde_json::Value;
+	use std::{env::temp_dir, fs::{create_dir_all, remove_dir_all, write}, path::Path, process};
+
+	fn compile(source: &str, options: Options) -> Result<Vec<String>, Failure> {
+		Compiler::new(options).compile_str(source, Path::new(""))
+			.map(|output| output.files.into_iter().map(|file| file.path).collect())
+	}
+
+	#[test]
+	fn crates_compile_from_source_text() {
+		let options = Options {
+			emit: vec![Emit::Js, Emit::Declarations],
+			dump: vec![Dump::Ast],
+			..Options::default()
+		};
+		let output = Compiler::new(options)
+			.compile_str("mod m {pub fn f() {}}\nfn main() {}", Path::new(""))
+			.unwrap();
+
+		assert_eq!(output.root, "main.js");
+		assert_eq!(output.files.iter().map(|file| &*file.path).collect::<Vec<_>>(),
+			["main.js", "m.js", "main.d.ts", "m.d.ts"]);
+		assert!(output.code.scope.functions.contains_key("main"));
+		assert_eq!(output.dumps.iter().map(|(stage, _)| *stage).collect::<Vec<_>>(), [Dump::Ast]);
+
+		match compile("fn main() {g();}", Options::default()) {
+			Err(Failure::Error(error)) => assert_eq!(error.code, UnknownFunction),
+			result => panic!("expected an unknown function, found {:?}", result)
+		}
+	}
+
+	#[test]
+	fn diagnostics_are_reported_to_the_sink() {
+		let mut diagnostics = Vec::new();
+		Compiler::new(Options::default())
+			.with_diagnostics(|diagnostic| diagnostics.push(diagnostic.clone()))
+			.compile_str("fn a() {}\nfn \u{430}() {}", Path::new(""))
+			.unwrap();
+
+		let warnings = diagnostics.iter()
+			.filter(|diagnostic| diagnostic.severity == Severity::Warning)
+			.map(ToString::to_string)
+			.collect::<Vec<_>>();
+		assert_eq!(warnings, ["warning: identifiers \"a\" and \"\u{430}\" could be confused"]);
+		assert!(diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Note));
+	}
+
+	#[test]
+	fn source_maps_name_sources_relative_to_the_javascript() {
+		let directory = temp_dir().join(format!("rustscript-maps-{}", process::id()));
+		create_dir_all(directory.join("m")).unwrap();
+		write(directory.join("main.rsst"), "mod m;").unwrap();
+		write(directory.join("m.rsst"), "mod n;").unwrap();
+		write(directory.join("m/n.rsst"), "").unwrap();
+
+		let sources = |options: Options| {
+			let options = Options {source_maps: Some(SourceMaps::File), ..options};
+			Compiler::new(options).compile_file(&directory.join("main.rsst")).unwrap().files
+				.into_iter()
+				.filter(|file| file.path.ends_with(".map"))
+				.map(|file| {
+					let map: Value = serde_json::from_str(&file.contents).unwrap();
+					(file.path, map["sources"][0].as_str().unwrap().to_owned())
+				})
+				.collect::<Vec<_>>()
+		};
+		let pairs = |pairs: &[(&str, &str)]| pairs.iter()
+			.map(|(file, source)| (file.to_string(), source.to_string()))
+			.collect::<Vec<_>>();
+
+		assert_eq!(sources(Options::default()), pairs(&[("main.js.map", "main.rsst"),
+			("m.js.map", "m.rsst"), ("m/n.js.map", "n.rsst")]));
+		let options = Options {directory: Some(directory.join("out")), ..Options::default()};
+		assert_eq!(sources(options), pairs(&[("main.js.map", "../main.rsst"),
+			("m.js.map", "../m.rsst"), ("m/n.js.map", "../../m/n.rsst")]));
+
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn failures_say_where_errors_are() {
+		let directory = temp_dir().join(format!("rustscript-failures-{}", process::id()));
+		create_dir_all(&directory).unwrap();
+		write(directory.join("main.rsst"), "fn main() {\n\tg();\n}").unwrap();
+		write(directory.join("other.rsst"), "mod m;").unwrap();
+		write(directory.join("m.rsst"), "pub fn f() {}\nlet x: bool = 1;").unwrap();
+
+		let failure = |file| Compiler::new(Options::default())
+			.compile_file(&directory.join(file)).unwrap_err().to_string();
+		assert_eq!(failure("main.rsst"), format!("error[RS0011]: unknown function \"g\"\n \
+			--> {}:2:2", directory.join("main.rsst").display()));
+		assert!(failure("other.rsst").ends_with(&format!(" --> {}:2:1",
+			directory.join("m.rsst").display())));
+
+		let failure = compile("fn main() {}\nlet x: u8 = 256;", Options::default()).unwrap_err();
+		assert!(failure.to_string().ends_with("\n --> <stdin>:2:1"), "{}", failure);
+
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn modules_are_not_written_over_the_crate_root() {
+		match compile("mod main {}", Options::default()) {
+			Err(Failure::Error(error)) => assert_eq!(error.code, OutputCollision),
+			result => panic!("expected a collision, found {:?}", result)
+		}
+
+		let options = Options {root: Some("app.js".into()), ..Options::default()};
+		assert_eq!(compile("mod main {}", options).unwrap(), ["app.js", "main.js"]);
+		assert_eq!(compile("mod m {mod main {}}", Options::default()).unwrap(),
+			["main.js", "m.js", "m/main.js"]);
+	}
+}
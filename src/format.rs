@@ -0,0 +1,379 @@
+//! The source formatter. Every token is written exactly as it is in the
+//! source, and every comment is kept; only the whitespace between them is
+//! chosen by the formatter. Items and the fields and variants of data each
+//! go on their own line, and argument lists longer than the line width are
+//! broken onto a line per argument.
+//!
+//! Formatting is idempotent: formatting formatted source changes nothing. A
+//! single blank line is kept wherever the source had one or more between two
+//! lines, and is always left between top-level items.
+
+use crate::frontend::{error::Error, module::load, parser::Parser,
+	tokenizer::{Located, Token, Tokenizer}};
+use std::{collections::BTreeSet, ops::Range, path::{Path, PathBuf}};
+
+/// How each level of indentation is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+	Tabs,
+	Spaces(usize)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+	pub indent: Indent,
+	/// The number of columns lines are kept within where possible, counting
+	/// each tab as four.
+	pub width: usize
+}
+
+impl Default for Style {
+	fn default() -> Self {
+		Self {indent: Indent::Tabs, width: 100}
+	}
+}
+
+/// The number of columns a tab is counted as.
+const TAB_WIDTH: usize = 4;
+
+/// Formats the source of a file, which must parse.
+pub fn format(source: &str, style: &Style) -> Result<String, Error> {
+	Parser::new(Located::new(source)).parse_block()?;
+	let pieces = pieces(source)?;
+
+	let mut formatted = String::new();
+	for line in lines(&pieces) {
+		if line.blank_before {formatted.push('\n')}
+		render(&pieces, &line, style).into_iter().for_each(|line| {
+			formatted += &line;
+			formatted.push('\n');
+		});
+	}
+
+	// Nothing but whitespace may change.
+	let tokens = |source| Tokenizer::new(source).collect::<Result<Vec<_>, _>>();
+	assert_eq!(tokens(source)?, tokens(&formatted)?, "formatting changed the tokens of a file");
+
+	Ok(formatted)
+}
+
+/// The files of the crate rooted at `path`: the root, followed by every file it
+/// loads as a module.
+pub fn files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+	let mut sources = BTreeSet::new();
+	load(path, &mut sources)?;
+
+	Ok([path.to_owned()].into_iter()
+		.chain(sources.into_iter().filter(|source| source != path && source.is_file()))
+		.collect())
+}
+
+enum Kind<'s> {
+	Token(Token<'s>),
+	/// An ordinary comment, which the tokenizer discards.
+	Comment {
+		line: bool
+	}
+}
+
+/// A token or comment, as it is written in the source.
+struct Piece<'s> {
+	kind: Kind<'s>,
+	text: &'s str,
+	/// The number of line breaks between this piece and the one before it.
+	breaks: usize
+}
+
+impl Piece<'_> {
+	fn is(&self, token: Token) -> bool {
+		matches!(&self.kind, Kind::Token(own) if *own == token)
+	}
+}
+
+/// Splits source into its tokens and the comments between them.
+fn pieces(source: &str) -> Result<Vec<Piece<'_>>, Error> {
+	let mut tokenizer = Tokenizer::new(source);
+	let mut pieces = Vec::new();
+	let mut end = 0;
+
+	while let Some((range, token)) = tokenizer.next_spanned()? {
+		let gap = comments(&source[end..range.start], &mut pieces);
+		// Line doc comments end before the line break, which may be `\r\n`.
+		let text = source[range.clone()].trim_end_matches('\r');
+		pieces.push(Piece {kind: Kind::Token(token), text, breaks: gap.matches('\n').count()});
+		end = range.start + text.len();
+	}
+	comments(&source[end..], &mut pieces);
+
+	Ok(pieces)
+}
+
+/// Adds the comments in the whitespace between two tokens, returning the
+/// whitespace after the last of them.
+fn comments<'s>(mut gap: &'s str, pieces: &mut Vec<Piece<'s>>) -> &'s str {
+	loop {
+		let trimmed = gap.trim_start();
+		let length = match trimmed {
+			_ if trimmed.starts_with("//") => trimmed.find('\n').unwrap_or(trimmed.len()),
+			_ if trimmed.starts_with("/*") => {
+				// Block comments nest.
+				let (mut depth, mut index) = (0, 0);
+				loop {
+					let rest = &trimmed[index..];
+					if rest.starts_with("/*") {
+						depth += 1;
+						index += 2;
+					} else if rest.starts_with("*/") {
+						depth -= 1;
+						index += 2;
+						if depth == 0 {break index}
+					} else {
+						index += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+					}
+				}
+			},
+			_ => break gap
+		};
+
+		let text = trimmed[..length].trim_end();
+		let breaks = gap[..gap.len() - trimmed.len()].matches('\n').count();
+		pieces.push(Piece {kind: Kind::Comment {line: text.starts_with("//")}, text, breaks});
+		gap = &trimmed[length..];
+	}
+}
+
+/// A line of formatted source.
+struct Line {
+	indent: usize,
+	/// The indices of the pieces on the line.
+	pieces: Vec<usize>,
+	blank_before: bool
+}
+
+/// Splits pieces into lines, breaking them wherever an item, field or variant
+/// ends, and within braces.
+fn lines(pieces: &[Piece]) -> Vec<Line> {
+	let mut lines: Vec<Line> = Vec::new();
+	let mut current: Option<Line> = None;
+	// Whether each open bracket is a brace, rather than a parenthesis.
+	let mut brackets: Vec<bool> = Vec::new();
+	// Whether the next piece begins a new line.
+	let mut broken = false;
+
+	for (index, piece) in pieces.iter().enumerate() {
+		let previous = index.checked_sub(1).map(|index| &pieces[index]);
+		let next = pieces.get(index + 1);
+		let closing = piece.is(Token::BraceRight);
+
+		// Comments stay on the line they were on, and so do empty braces.
+		let breaks = match piece.kind {
+			Kind::Comment {..} => piece.breaks > 0,
+			_ if closing => !previous.is_some_and(|previous| previous.is(Token::BraceLeft)),
+			_ => broken
+		};
+		if breaks {lines.extend(current.take())}
+
+		let line = current.get_or_insert_with(|| {
+			let braces = brackets.iter().filter(|brace| **brace).count();
+			let parenthesized = matches!(brackets.last(), Some(false));
+			let follows_brace = lines.last()
+				.and_then(|line| line.pieces.last())
+				.is_some_and(|last| pieces[*last].is(Token::BraceLeft));
+			// Comments after the end of an item, on its line, do not end it.
+			let follows_item = brackets.is_empty() && starts_item(&pieces[index..]) && lines.last()
+				.and_then(|line| line.pieces.iter().rfind(|&&index|
+					!matches!(pieces[index].kind, Kind::Comment {..})))
+				.is_some_and(|last| pieces[*last].is(Token::SemiColon)
+					|| pieces[*last].is(Token::BraceRight));
+			Line {
+				indent: braces.saturating_sub(closing as usize) + parenthesized as usize,
+				pieces: Vec::new(),
+				blank_before: (piece.breaks >= 2 || follows_item) && !lines.is_empty()
+					&& !follows_brace && !closing
+			}
+		});
+		let trailing = line.pieces.is_empty() || piece.breaks == 0;
+		line.pieces.push(index);
+
+		let in_braces = !matches!(brackets.last(), Some(false));
+		broken = match &piece.kind {
+			Kind::Token(Token::BraceLeft) => {
+				brackets.push(true);
+				!next.is_some_and(|next| next.is(Token::BraceRight))
+			},
+			Kind::Token(Token::BraceRight) => {
+				brackets.pop();
+				!matches!(brackets.last(), Some(false)) && !next.is_some_and(|next| [
+					Token::SemiColon, Token::Comma, Token::ParenRight
+				].into_iter().any(|token| next.is(token)))
+			},
+			Kind::Token(Token::ParenLeft) => {
+				brackets.push(false);
+				false
+			},
+			Kind::Token(Token::ParenRight) => {
+				brackets.pop();
+				false
+			},
+			Kind::Token(Token::SemiColon | Token::Comma) => in_braces,
+			Kind::Token(Token::DocComment(_) | Token::InnerDocComment(_)) => true,
+			Kind::Token(_) => false,
+			Kind::Comment {line: true} => true,
+			// A block comment after a piece that ends a line does not stop it
+			// from ending.
+			Kind::Comment {line: false} =>
+				(trailing && broken) || next.is_some_and(|next| next.breaks > 0)
+		};
+	}
+	lines.extend(current);
+
+	lines
+}
+
+/// Whether the first of `pieces` begins an item, or begins comments before one.
+fn starts_item(pieces: &[Piece]) -> bool {
+	pieces.iter()
+		.find(|piece| !matches!(piece.kind, Kind::Comment {..}))
+		.is_some_and(|piece| [Token::KeywordData, Token::KeywordFn, Token::KeywordLet,
+			Token::KeywordMod, Token::KeywordUse, Token::KeywordExtern, Token::KeywordPub]
+			.into_iter().any(|token| piece.is(token))
+			|| matches!(piece.kind, Kind::Token(Token::DocComment(_))))
+}
+
+/// Writes a line, breaking its first nonempty parenthesized list onto a line
+/// per element if it is too wide.
+fn render(pieces: &[Piece], line: &Line, style: &Style) -> Vec<String> {
+	let indent = match style.indent {
+		Indent::Tabs => "\t".repeat(line.indent),
+		Indent::Spaces(spaces) => " ".repeat(spaces * line.indent)
+	};
+	let mut text = indent;
+	for (position, &index) in line.pieces.iter().enumerate() {
+		if position > 0 && spaced(&pieces[line.pieces[position - 1]], &pieces[index])
+			{text.push(' ')}
+		match pieces[index].kind {
+			// Unlike tokens, comments may have their line breaks normalized.
+			Kind::Comment {..} => text += &pieces[index].text.replace("\r\n", "\n"),
+			Kind::Token(_) => text += pieces[index].text
+		}
+	}
+	let width = text.lines()
+		.map(|line| line.chars().map(|character| match character {
+			'\t' => TAB_WIDTH,
+			_ => 1
+		}).sum::<usize>())
+		.max()
+		.unwrap_or_default();
+	if width <= style.width {return vec![text]}
+
+	match list(pieces, &line.pieces) {
+		Some((open, elements, close)) => {
+			let part = |pieces: Vec<usize>, indent| Line {indent, pieces, blank_before: false};
+			let mut lines = render(pieces, &part(line.pieces[..=open].to_vec(), line.indent),
+				style);
+			elements.into_iter().for_each(|element| lines.extend(render(pieces,
+				&part(line.pieces[element].to_vec(), line.indent + 1), style)));
+			lines.extend(render(pieces, &part(line.pieces[close..].to_vec(), line.indent),
+				style));
+			lines
+		},
+		None => vec![text]
+	}
+}
+
+/// Finds the first nonempty parenthesized list that both opens and closes
+/// within a line, returning the positions of its parentheses and the range of
+/// each element, which includes the comma after it.
+#[allow(clippy::type_complexity)]
+fn list(pieces: &[Piece], line: &[usize])
+		-> Option<(usize, Vec<Range<usize>>, usize)> {
+	let mut open = None;
+	let mut depth = 0;
+	let mut elements = Vec::new();
+	let mut start = 0;
+
+	for (position, &index) in line.iter().enumerate() {
+		let piece = &pieces[index];
+		if piece.is(Token::ParenLeft) {
+			depth += 1;
+			if depth == 1 && open.is_none() {
+				open = Some(position);
+				start = position + 1;
+			}
+		} else if piece.is(Token::ParenRight) {
+			depth -= 1;
+			if depth == 0 && open.is_some() {
+				if start < position {elements.push(start..position)}
+				match elements.is_empty() {
+					true => open = None,
+					false => return Some((open?, elements, position))
+				}
+			}
+		} else if piece.is(Token::Comma) && depth == 1 && open.is_some() {
+			elements.push(start..position + 1);
+			start = position + 1;
+		}
+	}
+
+	None
+}
+
+/// Whether a space separates two pieces on the same line.
+fn spaced(previous: &Piece, next: &Piece) -> bool {
+	use Token::*;
+	match (&previous.kind, &next.kind) {
+		(Kind::Token(previous), Kind::Token(next)) => !matches!((previous, next),
+			(_, Comma | SemiColon | Colon | DoubleColon | Period | ParenRight | BracketRight)
+				| (ParenLeft | BracketLeft | DoubleColon | Period, _)
+				| (Identifier(_), ParenLeft)
+				| (BraceLeft, BraceRight)),
+		_ => true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{format, Indent, Style};
+	use std::fs::{read_dir, read_to_string};
+
+	const UNFORMATTED: &str = "data   P{x:i32,/* kept */y:i32}\n\n\n\nfn   f(a:i32,b:i32){\n\
+		// note\nlet  x:i32=1;}\n";
+
+	#[test]
+	fn only_whitespace_changes() {
+		assert_eq!(format(UNFORMATTED, &Style::default()).unwrap(), "data P {\n\
+			\tx: i32, /* kept */\n\ty: i32\n}\n\nfn f(a: i32, b: i32) {\n\t// note\n\
+			\tlet x: i32 = 1;\n}\n");
+	}
+
+	#[test]
+	fn top_level_items_are_set_apart() {
+		let source = "use a::b;\nuse a::c; data A; data B;\nfn f() {let x: i32 = 1;\n\
+			let y: i32 = 2;}\n/// Doc.\nfn g() {} // trailing\n// leading\nfn h() {}\n\
+			let x: i32 = 1;\nf();\n// note\nf();\n";
+		assert_eq!(format(source, &Style::default()).unwrap(), "use a::b;\n\nuse a::c;\n\n\
+			data A;\n\ndata B;\n\nfn f() {\n\tlet x: i32 = 1;\n\tlet y: i32 = 2;\n}\n\n\
+			/// Doc.\nfn g() {} // trailing\n\n// leading\nfn h() {}\n\n\
+			let x: i32 = 1;\nf();\n// note\nf();\n");
+	}
+
+	#[test]
+	fn long_argument_lists_are_broken() {
+		let style = Style {indent: Indent::Spaces(2), width: 24};
+		assert_eq!(format("fn f(first: i32, second: i32) {\ng(first, second);}", &style).unwrap(),
+			"fn f(\n  first: i32,\n  second: i32\n) {\n  g(first, second);\n}\n");
+	}
+
+	#[test]
+	fn formatting_is_idempotent() {
+		let style = Style::default();
+		let once = format(UNFORMATTED, &style).unwrap();
+		assert_eq!(format(&once, &style).unwrap(), once);
+
+		// The test crates are already formatted.
+		for path in read_dir("test").unwrap().map(|entry| entry.unwrap().path()) {
+			let source = read_to_string(&path).unwrap();
+			assert_eq!(format(&source, &style).unwrap(), source, "{}", path.display());
+		}
+	}
+}
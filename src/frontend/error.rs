@@ -0,0 +1,158 @@
+use super::tokenizer::Location;
+use std::{fmt::{Display, Formatter, Result as FMTResult}, ops::Range, path::{Path, PathBuf}};
+
+macro_rules! error_codes {
+	($($code:ident = $name:literal,)*) => {
+		/// A kind of error the frontend reports. Each has a stable code, such as
+		/// `RS0001`, and a long form explanation embedded in the compiler.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+		pub enum ErrorCode {
+			$($code,)*
+		}
+
+		impl ErrorCode {
+			pub const ALL: &'static [Self] = &[$(Self::$code,)*];
+
+			pub fn name(&self) -> &'static str {
+				match self {
+					$(Self::$code => $name,)*
+				}
+			}
+
+			/// The explanation printed by `rustscript --explain`.
+			pub fn explanation(&self) -> &'static str {
+				match self {
+					$(Self::$code => include_str!(concat!("explanations/", $name, ".md")),)*
+				}
+			}
+		}
+	};
+}
+
+// Codes are never reused or renumbered; new errors are added at the end.
+error_codes! {
+	DuplicateType = "RS0001",
+	UnknownType = "RS0002",
+	DuplicateVariant = "RS0003",
+	DuplicateField = "RS0004",
+	DuplicateFunction = "RS0005",
+	DuplicateModule = "RS0006",
+	DuplicateImport = "RS0007",
+	DuplicateItem = "RS0008",
+	DuplicateGlobal = "RS0009",
+	UnknownValue = "RS0010",
+	UnknownFunction = "RS0011",
+	ArgumentCount = "RS0012",
+	MismatchedTypes = "RS0013",
+	LiteralOutOfRange = "RS0014",
+	UnsupportedAbi = "RS0015",
+	NestedModule = "RS0016",
+	ModuleNotFound = "RS0017",
+	AmbiguousModule = "RS0018",
+	ModuleReloaded = "RS0019",
+	UnresolvedName = "RS0020",
+	PrivateItem = "RS0021",
+	CyclicImport = "RS0022",
+	TooManySupers = "RS0023",
+	MisplacedPathSegment = "RS0024",
+	NotAModule = "RS0025",
+	InvalidNumber = "RS0026",
+	IntegerTooLarge = "RS0027",
+	InvalidEscape = "RS0028",
+	InvalidCharacter = "RS0029",
+	Unterminated = "RS0030",
+	UnexpectedCharacter = "RS0031",
+	UnexpectedToken = "RS0032",
+	MissingMain = "RS0033",
+	UnsupportedReturnType = "RS0034",
+	NestedImport = "RS0035",
+	OutputCollision = "RS0036",
+}
+
+impl ErrorCode {
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|code| code.name() == name)
+	}
+}
+
+impl Display for ErrorCode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "{}", self.name())
+	}
+}
+
+/// An error in the program being compiled, as opposed to a bug in the compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	pub code: ErrorCode,
+	pub message: String,
+	/// Where in its source the error is, if it is anywhere in particular.
+	pub span: Option<Range<Location>>,
+	/// The module file the error is in, unless it is in the crate root's. The
+	/// compiler records the crate root's file as well, once an error stops it.
+	pub file: Option<PathBuf>
+}
+
+impl Error {
+	/// Places the error at `span`.
+	pub fn at(self, span: Range<Location>) -> Self {
+		Self {span: Some(span), ..self}
+	}
+
+	/// Places the error at `span`, unless it was already placed somewhere more
+	/// precise within it.
+	pub fn or_at(self, span: Range<Location>) -> Self {
+		match self.span {
+			Some(_) => self,
+			None => self.at(span)
+		}
+	}
+
+	/// Records that the error is in the module file at `file`, unless it was
+	/// found in a module that file declares.
+	pub fn in_file(self, file: &Path) -> Self {
+		Self {file: self.file.or_else(|| Some(file.to_owned())), ..self}
+	}
+}
+
+/// Written as `error[RS0010]: message`, followed by where the error is on a
+/// line of its own, as `--> path:line:column`, counting from one.
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "error[{}]: {}", self.code, self.message)?;
+		let start = self.span.as_ref().map(|span| span.start);
+		match (&self.file, start) {
+			(Some(file), Some(start)) => write!(f, "\n --> {}:{}:{}", file.display(),
+				start.line + 1, start.column + 1),
+			(Some(file), None) => write!(f, "\n --> {}", file.display()),
+			(None, Some(start)) => write!(f, "\n --> {}:{}", start.line + 1, start.column + 1),
+			(None, None) => Ok(())
+		}
+	}
+}
+
+/// Creates an error, which abandons the current stage of the frontend when it
+/// is returned.
+pub fn error(code: ErrorCode, message: impl Into<String>) -> Error {
+	Error {code, message: message.into(), span: None, file: None}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{error, ErrorCode::UnknownValue};
+	use crate::frontend::tokenizer::Location;
+	use std::path::Path;
+
+	#[test]
+	fn errors_are_written_with_where_they_are() {
+		let at = |line, column| Location {line, column};
+		let unknown = || error(UnknownValue, "unknown value \"x\"");
+		assert_eq!(unknown().to_string(), "error[RS0010]: unknown value \"x\"");
+		assert_eq!(unknown().at(at(2, 4)..at(2, 5)).in_file(Path::new("src/a.rsst")).to_string(),
+			"error[RS0010]: unknown value \"x\"\n --> src/a.rsst:3:5");
+		assert_eq!(unknown().in_file(Path::new("a.rsst")).to_string(),
+			"error[RS0010]: unknown value \"x\"\n --> a.rsst");
+		assert_eq!(unknown().at(at(0, 0)..at(0, 1)).to_string(),
+			"error[RS0010]: unknown value \"x\"\n --> 1:1");
+	}
+}
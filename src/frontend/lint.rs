@@ -0,0 +1,109 @@
+use super::{parser::{Block, DataItem, DataVariant, Expression, ForeignItem, Statement}, IStr};
+use std::collections::{BTreeMap, BTreeSet};
+use unicode_security::confusable_detection::skeleton;
+
+/// Finds every pair of identifiers declared in a crate that are distinct but
+/// could be mistaken for one another, such as `a` and the Cyrillic `а`. As in
+/// Rust, pairs of ASCII identifiers (`l` and `1`) are assumed to be intended.
+pub fn confusable_identifiers<'s>(block: &Block<'s>) -> Vec<(IStr<'s>, IStr<'s>)> {
+	let mut names = BTreeSet::new();
+	declared_identifiers(block, &mut names);
+
+	let mut skeletons = BTreeMap::<String, Vec<IStr>>::new();
+	names.into_iter().for_each(|name| skeletons
+		.entry(skeleton(&name).collect())
+		.or_default()
+		.push(name));
+
+	skeletons.into_values()
+		.flat_map(|names| {
+			let mut pairs = Vec::new();
+			for (index, first) in names.iter().enumerate() {
+				for second in &names[index + 1..] {
+					if !first.is_ascii() || !second.is_ascii()
+						{pairs.push((first.clone(), second.clone()))}
+				}
+			}
+			pairs
+		})
+		.collect()
+}
+
+/// Gathers the name of every item, field, variant, argument and variable
+/// declared within a block, including within nested blocks.
+fn declared_identifiers<'s>(block: &Block<'s>, names: &mut BTreeSet<IStr<'s>>) {
+	fn variant_identifiers<'s>(variant: &DataVariant<'s>, names: &mut BTreeSet<IStr<'s>>) {
+		names.insert(variant.name().clone());
+		if let DataVariant::Struct {fields, ..} = variant
+			{names.extend(fields.iter().map(|(name, _)| name.clone()))}
+	}
+
+	fn expression_identifiers<'s>(expression: &Expression<'s>,
+			names: &mut BTreeSet<IStr<'s>>) {
+		match expression {
+			Expression::Block(block) => declared_identifiers(block, names),
+			Expression::FunctionCall {arguments, ..} => arguments.iter()
+				.for_each(|argument| expression_identifiers(argument, names)),
+			_ => ()
+		}
+	}
+
+	block.0.iter().for_each(|statement| match statement {
+		Statement::DataItem(_, DataItem::Single(variant)) =>
+			variant_identifiers(variant, names),
+		Statement::DataItem(_, DataItem::Multiple {name, variants}) => {
+			names.insert(name.clone());
+			variants.iter().for_each(|variant| variant_identifiers(variant, names));
+		},
+		Statement::FunctionItem(_, function) => {
+			names.insert(function.name.clone());
+			names.extend(function.arguments.iter().map(|(name, _)| name.clone()));
+			declared_identifiers(&function.body, names);
+		},
+		Statement::ModItem(_, module) => {
+			names.insert(module.name.clone());
+			if let Some(body) = &module.body {declared_identifiers(body, names)}
+		},
+		Statement::UseItem(_, r#use) => {names.insert(r#use.name().clone());},
+		Statement::ExternItem(r#extern) => r#extern.items.iter()
+			.for_each(|(_, item)| {
+				names.insert(item.name().clone());
+				if let ForeignItem::Function {arguments, ..} = item
+					{names.extend(arguments.iter().map(|(name, _)| name.clone()))}
+			}),
+		Statement::LetItem(_, variable) => {
+			names.insert(variable.name.clone());
+			expression_identifiers(&variable.expression, names);
+		},
+		Statement::Expression(_, expression) =>
+			expression_identifiers(expression, names),
+		Statement::Documentation(_) => ()
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::{parser::Parser, tokenizer::Located}, confusable_identifiers};
+
+	fn confusable(source: &str) -> Vec<(String, String)> {
+		let block = Parser::new(Located::new(source)).parse_block().unwrap();
+		confusable_identifiers(&block).into_iter()
+			.map(|(first, second)| (first.into(), second.into()))
+			.collect()
+	}
+
+	#[test]
+	fn confusable_pairs_are_found_anywhere() {
+		// The second `a` is Cyrillic.
+		assert_eq!(confusable("fn a() {} fn f() {let \u{430}: i32 = 1;}"),
+			[("a".into(), "\u{430}".into())]);
+		assert_eq!(confusable("data P {x: i32} fn f(\u{445}: bool) {}"),
+			[("x".into(), "\u{445}".into())]);
+		assert!(confusable("fn a() {} fn b() {let \u{65e5}: i32 = 1;}").is_empty());
+	}
+
+	#[test]
+	fn ascii_pairs_are_intended() {
+		assert!(confusable("fn rn() {} fn m() {} fn l() {} fn I() {}").is_empty());
+	}
+}
@@ -1,36 +1,124 @@
 pub mod tokenizer;
 pub mod parser;
+pub mod module;
+pub mod lint;
+pub mod error;
+mod serialize;
 
-use parser::{Block, DataItem, DataVariant, Statement};
-use std::{collections::{HashMap, HashSet}, marker::PhantomData};
+use error::{error, Error, ErrorCode::*};
+use itertools::Itertools;
+use module::{ItemKind, ModuleRef};
+use parser::{Block, DataItem, DataVariant, ForeignItem, Visibility};
+use serde::Serialize;
+use serialize::Variants;
+use std::{borrow::Cow, collections::{HashMap, HashSet},
+	fmt::{Display, Formatter, Result as FMTResult}, path::PathBuf};
+use tokenizer::Location;
 
-pub type IStr<'s> = (PhantomData<&'s ()>, Box<str>);
+pub type IStr<'s> = Cow<'s, str>;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum Type<'s> {
 	User {
-		format: DataFormat<'s>
+		format: DataFormat<'s>,
+		documentation: Option<Box<str>>,
+		location: Location
 	},
-	Integer
+	Foreign {
+		binding: Foreign,
+		location: Location
+	},
+	Integer(IntegerType),
+	Float,
+	Boolean,
+	String,
+	Character
 }
 
 impl<'s> Type<'s> {
 	pub fn format_ref(&self) -> Option<&DataFormat<'s>> {
 		match self {
-			Self::User {format} => Some(format),
+			Self::User {format, ..} => Some(format),
 			_ => None
 		}
 	}
+
+	/// Where the type is declared, if it is not built in.
+	pub fn location(&self) -> Option<Location> {
+		match self {
+			Self::User {location, ..} | Self::Foreign {location, ..} => Some(*location),
+			_ => None
+		}
+	}
+
+	/// Writes the type as the item that declares it under `name`, without its
+	/// doc comments or visibility.
+	pub fn declaration(&self, name: &str) -> String {
+		match self {
+			Self::User {format, ..} => format!("data {}", format_source(name, format,
+				|variants| variants.iter()
+					.sorted_by_key(|(name, _)| *name)
+					.map(|(name, variant)| format_source(name, variant, |_| Vec::new()))
+					.collect())
+				+ if matches!(format, GenericFormat::Named {..}) {""} else {";"}),
+			Self::Foreign {binding, ..} =>
+				format!("{}data {} = {:?};", extern_prefix(binding), name, binding.name),
+			_ => format!("data {};", name)
+		}
+	}
+}
+
+/// One of the built in integer types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct IntegerType {
+	pub signed: bool,
+	pub bits: u32
 }
 
-#[derive(Debug)]
+impl IntegerType {
+	/// Every integer type, by name. Pointer sized integers are 32 bits wide, as
+	/// they are on `wasm32`.
+	pub const ALL: [(&'static str, Self); 12] = [
+		("i8", Self {signed: true, bits: 8}),
+		("i16", Self {signed: true, bits: 16}),
+		("i32", Self {signed: true, bits: 32}),
+		("i64", Self {signed: true, bits: 64}),
+		("i128", Self {signed: true, bits: 128}),
+		("isize", Self {signed: true, bits: 32}),
+		("u8", Self {signed: false, bits: 8}),
+		("u16", Self {signed: false, bits: 16}),
+		("u32", Self {signed: false, bits: 32}),
+		("u64", Self {signed: false, bits: 64}),
+		("u128", Self {signed: false, bits: 128}),
+		("usize", Self {signed: false, bits: 32})
+	];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter()
+			.find(|(integer, _)| *integer == name)
+			.map(|(_, integer)| *integer)
+	}
+
+	/// The largest value of this type.
+	pub fn max(&self) -> u128 {
+		match self.signed {
+			true => u128::MAX >> (129 - self.bits),
+			false => u128::MAX >> (128 - self.bits)
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+#[serde(bound(serialize = "V: Variants"))]
 pub enum GenericFormat<'s, V> {
 	Marker,
 	Unnamed {
 		fields: Vec<IStr<'s>>
 	},
 	Named {
+		#[serde(serialize_with = "serialize::name_map")]
 		fields: HashMap<IStr<'s>, IStr<'s>>,
+		#[serde(serialize_with = "serialize::variants")]
 		variants: V
 	}
 }
@@ -41,89 +129,747 @@ pub type DataFormat<'s> =
 pub type EnumVariantFormat<'s> =
 	GenericFormat<'s, ()>;
 
-#[derive(Debug)]
+/// An item defined outside of rustscript, bound through an extern block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Foreign {
+	pub abi: Box<str>,
+	/// The name of the item on the other side, which for JavaScript may be a
+	/// path such as `console.log`.
+	pub name: Box<str>,
+	/// The module the item is imported from, if it is not a global.
+	pub from: Option<Box<str>>
+}
+
+/// The item a type name refers to. Two types are the same only if they refer to
+/// the same item, whatever they are named where they are used.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum TypeItem<'s> {
+	/// One of the built in types, such as `i32`.
+	Builtin(IStr<'s>),
+	/// A type declared at the top level of a module, by its path from the crate
+	/// root, including its own name.
+	Module(Vec<IStr<'s>>),
+	/// A type declared within a block, by the module it is in and where it is
+	/// declared in the module's file.
+	Local {
+		module: Vec<IStr<'s>>,
+		location: Location
+	}
+}
+
+/// A type as it is written, along with the item it refers to where it is
+/// written.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TypeName<'s> {
+	pub name: IStr<'s>,
+	pub item: TypeItem<'s>
+}
+
+impl<'s> TypeName<'s> {
+	pub fn builtin(name: impl Into<IStr<'s>>) -> Self {
+		let name = name.into();
+		Self {item: TypeItem::Builtin(name.clone()), name}
+	}
+
+	/// The integer type this is, if it is one.
+	pub fn integer(&self) -> Option<IntegerType> {
+		match &self.item {
+			TypeItem::Builtin(name) => IntegerType::from_name(name),
+			_ => None
+		}
+	}
+
+	pub fn is_float(&self) -> bool {
+		matches!(&self.item, TypeItem::Builtin(name) if matches!(&**name, "f32" | "f64"))
+	}
+
+	/// Whether or not an expression of type `actual` may be used where one of
+	/// this type is.
+	pub fn accepts(&self, actual: &ExpressionType<'s>) -> bool {
+		match actual {
+			ExpressionType::Named(actual) => actual.item == self.item,
+			ExpressionType::Integer => self.integer().is_some(),
+			ExpressionType::Float => self.is_float(),
+			ExpressionType::Unit => false
+		}
+	}
+
+	/// The type's name, along with where it is declared if that is not where
+	/// built in types are.
+	pub fn qualified(&self) -> String {
+		match &self.item {
+			TypeItem::Builtin(_) => self.name.to_string(),
+			TypeItem::Module(path) =>
+				format!("crate::{}", path.iter().join("::")),
+			TypeItem::Local {location, ..} => format!("{} (declared at {}:{})",
+				self.name, location.line + 1, location.column + 1)
+		}
+	}
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct Function<'s> {
-	code: Code<'s>
+	pub arguments: Vec<(IStr<'s>, TypeName<'s>)>,
+	pub r#return: Option<TypeName<'s>>,
+	pub code: Code<'s>,
+	/// The binding this function refers to, if it was declared in an extern
+	/// block and therefore has no code.
+	pub foreign: Option<Foreign>,
+	pub documentation: Option<Box<str>>,
+	/// Where the function is declared, within the file of its module.
+	pub location: Location
+}
+
+impl<'s> Function<'s> {
+	/// Writes the function as the item that declares it under `name`, up to its
+	/// body. Foreign functions have no body, and end with the name they bind.
+	pub fn declaration(&self, name: &str) -> String {
+		let signature = format!("fn {}({}){}", name,
+			self.arguments.iter()
+				.map(|(name, r#type)| format!("{}: {}", name, r#type.name))
+				.join(", "),
+			self.r#return.as_ref()
+				.map(|r#type| format!(" -> {}", r#type.name))
+				.unwrap_or_default());
+
+		match &self.foreign {
+			Some(binding) =>
+				format!("{}{} = {:?};", extern_prefix(binding), signature, binding.name),
+			None => signature
+		}
+	}
+}
+
+/// A named value: a variable, a function argument or a foreign global.
+#[derive(Debug, Serialize)]
+pub struct Value<'s> {
+	pub r#type: TypeName<'s>,
+	pub foreign: Option<Foreign>
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Module<'s> {
+	pub code: Code<'s>,
+	/// The outer doc comments of the module's declaration. Those within the
+	/// module itself belong to its code.
+	pub documentation: Option<Box<str>>,
+	/// The file the module was loaded from, if it was not written inline.
+	pub file: Option<PathBuf>
+}
+
+/// An imported item, resolved to where it was originally declared.
+#[derive(Debug, Serialize)]
+pub struct Import<'s> {
+	/// The path of the item from the crate root, including its own name.
+	pub path: Vec<IStr<'s>>,
+	pub kind: ItemKind
+}
+
+#[derive(Debug, Serialize)]
+pub enum Expression<'s> {
+	Block(Box<Code<'s>>),
+	LiteralInteger {
+		value: u128,
+		/// The type the literal was suffixed with, if any.
+		suffix: Option<IStr<'s>>,
+		/// The integer type the literal is used as, if it is known: that of its
+		/// suffix, or otherwise that expected where it is used.
+		integer: Option<IntegerType>
+	},
+	LiteralFloat {
+		/// The literal in decimal, without any `_` separators.
+		value: Cow<'s, str>,
+		suffix: Option<IStr<'s>>
+	},
+	LiteralBoolean(bool),
+	LiteralString(Cow<'s, str>),
+	LiteralCharacter(char),
+
+	Variable {
+		name: IStr<'s>,
+		r#type: TypeName<'s>,
+		foreign: Option<Foreign>
+	},
+	FunctionCall {
+		name: IStr<'s>,
+		arguments: Vec<Expression<'s>>,
+		r#return: Option<TypeName<'s>>,
+		foreign: Option<Foreign>
+	}
+}
+
+impl<'s> Expression<'s> {
+	pub fn r#type(&self) -> ExpressionType<'s> {
+		match self {
+			Self::Block(_) => ExpressionType::Unit,
+			// Suffixes always name built in types, whatever else is in scope.
+			Self::LiteralInteger {suffix: Some(suffix), ..}
+				| Self::LiteralFloat {suffix: Some(suffix), ..} =>
+				ExpressionType::Named(TypeName::builtin(suffix.clone())),
+			Self::LiteralInteger {suffix: None, ..} => ExpressionType::Integer,
+			Self::LiteralFloat {suffix: None, ..} => ExpressionType::Float,
+			Self::LiteralBoolean(_) => ExpressionType::Named(TypeName::builtin("bool")),
+			Self::LiteralString(_) => ExpressionType::Named(TypeName::builtin("str")),
+			Self::LiteralCharacter(_) => ExpressionType::Named(TypeName::builtin("char")),
+			Self::Variable {r#type, ..} => ExpressionType::Named(r#type.clone()),
+			Self::FunctionCall {r#return, ..} => r#return.clone()
+				.map(ExpressionType::Named)
+				.unwrap_or(ExpressionType::Unit)
+		}
+	}
 }
 
-#[derive(Debug)]
+/// The type of an expression, as far as checking it against the type it is
+/// expected to have goes.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExpressionType<'s> {
+	Named(TypeName<'s>),
+	/// An integer literal, which may be of any integer type.
+	Integer,
+	/// A float literal, which may be of either float type.
+	Float,
+	Unit
+}
+
+impl Display for ExpressionType<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Named(r#type) => write!(f, "{}", r#type.name),
+			Self::Integer => write!(f, "{{integer}}"),
+			Self::Float => write!(f, "{{float}}"),
+			Self::Unit => write!(f, "()")
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct Variable<'s> {
+	pub name: IStr<'s>,
+	pub r#type: TypeName<'s>,
+	pub expression: Expression<'s>,
+	pub documentation: Option<Box<str>>,
+	pub location: Location
+}
+
+/// A statement within a block, run in order along with the others.
+#[derive(Debug, Serialize)]
+pub enum Statement<'s> {
+	Variable(Variable<'s>),
+	/// An expression evaluated only for its effects.
+	Expression {
+		expression: Expression<'s>,
+		location: Location
+	}
+}
+
+impl<'s> Statement<'s> {
+	pub fn expression(&self) -> &Expression<'s> {
+		match self {
+			Self::Variable(variable) => &variable.expression,
+			Self::Expression {expression, ..} => expression
+		}
+	}
+
+	pub fn variable_ref(&self) -> Option<&Variable<'s>> {
+		match self {
+			Self::Variable(variable) => Some(variable),
+			_ => None
+		}
+	}
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct Code<'s> {
-	pub scope: Scope<'s>
+	pub scope: Scope<'s>,
+	pub statements: Vec<Statement<'s>>,
+	/// The inner doc comments of the block, joined by newlines.
+	pub documentation: Option<Box<str>>
+}
+
+/// Writes the code as source, with every name resolved and items sorted by
+/// name, so that two versions of the compiler can be compared.
+impl Display for Code<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		let scope = &self.scope;
+		let public = |name: &IStr| match scope.exports.contains(name) {
+			true => "pub ",
+			false => ""
+		};
+
+		let inner = self.documentation.iter()
+			.map(|text| doc_comment("//!", text));
+		let modules = scope.modules.iter()
+			.sorted_by_key(|(name, _)| *name)
+			.map(|(name, module)| format!("{}{}mod {} {}",
+				outer_doc_comment(module.documentation.as_deref()),
+				public(name), name, braced(&module.code.to_string())));
+		let imports = scope.imports.iter()
+			.sorted_by_key(|(name, _)| *name)
+			.map(|(name, import)| format!("{}use crate::{};", public(name),
+				import.path.iter().join("::")));
+		let types = scope.types.iter()
+			.sorted_by_key(|(name, _)| *name)
+			.map(|(name, r#type)| {
+				let documentation = match r#type {
+					Type::User {documentation, ..} => documentation.as_deref(),
+					_ => None
+				};
+				format!("{}{}{}", outer_doc_comment(documentation), public(name),
+					r#type.declaration(name))
+			});
+		let functions = scope.functions.iter()
+			.sorted_by_key(|(name, _)| *name)
+			.map(|(name, function)| {
+				let documentation = outer_doc_comment(function.documentation.as_deref());
+				let body = match function.foreign {
+					Some(_) => String::new(),
+					None => format!(" {}", braced(&function.code.to_string()))
+				};
+
+				format!("{}{}{}{}", documentation, public(name), function.declaration(name),
+					body)
+			});
+		let globals = scope.values.iter()
+			.filter_map(|(name, value)| Some((name, value, value.foreign.as_ref()?)))
+			.sorted_by_key(|(name, _, _)| *name)
+			.map(|(name, value, binding)| format!("{}let {}: {} = {:?};",
+				extern_prefix(binding), name, value.r#type.name, binding.name));
+		let statements = self.statements.iter()
+			.map(|statement| match statement {
+				Statement::Variable(variable) => format!("{}let {}: {} = {};",
+					outer_doc_comment(variable.documentation.as_deref()),
+					variable.name, variable.r#type.name, variable.expression),
+				Statement::Expression {expression, ..} => format!("{};", expression)
+			});
+
+		write!(f, "{}", inner
+			.chain(modules)
+			.chain(imports)
+			.chain(types)
+			.chain(functions)
+			.chain(globals)
+			.chain(statements)
+			.join("\n\n"))
+	}
+}
+
+impl Display for Expression<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Block(code) => write!(f, "{}", braced(&code.to_string())),
+			Self::LiteralInteger {value, suffix, ..} => write!(f, "{}{}", value,
+				suffix.as_ref().map(|suffix| &**suffix).unwrap_or_default()),
+			Self::LiteralFloat {value, suffix} => write!(f, "{}{}", value,
+				suffix.as_ref().map(|suffix| &**suffix).unwrap_or_default()),
+			Self::LiteralBoolean(boolean) => write!(f, "{}", boolean),
+			Self::LiteralString(string) => write!(f, "{:?}", string),
+			Self::LiteralCharacter(character) => write!(f, "{:?}", character),
+			Self::Variable {name, ..} => write!(f, "{}", name),
+			Self::FunctionCall {name, arguments, ..} =>
+				write!(f, "{}({})", name, arguments.iter().join(", "))
+		}
+	}
 }
 
-#[derive(Debug, Default)]
+/// Writes a data format as the source that declares it, without the `data`
+/// keyword or a trailing `;`.
+fn format_source<V>(name: &str, format: &GenericFormat<V>,
+		variants: impl Fn(&V) -> Vec<String>) -> String {
+	match format {
+		GenericFormat::Marker => name.into(),
+		GenericFormat::Unnamed {fields} =>
+			format!("{}({})", name, fields.iter().join(", ")),
+		GenericFormat::Named {fields, variants: inner} => {
+			let members = fields.iter()
+				.map(|(name, r#type)| format!("{}: {}", name, r#type))
+				.sorted()
+				.chain(variants(inner))
+				.join(",\n");
+			format!("{} {}", name, braced(&members))
+		}
+	}
+}
+
+/// Writes the start of the extern block a foreign item is declared in, as if
+/// the block held only that item.
+fn extern_prefix(foreign: &Foreign) -> String {
+	match &foreign.from {
+		Some(from) => format!("extern {:?} from {:?} ", foreign.abi, from),
+		None => format!("extern {:?} ", foreign.abi)
+	}
+}
+
+/// Writes doc comments, each line of `text` beginning with `prefix`.
+fn doc_comment(prefix: &str, text: &str) -> String {
+	text.split('\n').map(|line| format!("{}{}", prefix, line)).join("\n")
+}
+
+/// Writes the outer doc comments of an item, on the lines before it.
+fn outer_doc_comment(text: Option<&str>) -> String {
+	text.map(|text| doc_comment("///", text) + "\n").unwrap_or_default()
+}
+
+/// Surrounds source with braces, indenting it if there is any.
+fn braced(source: &str) -> String {
+	match source.is_empty() {
+		true => "{}".into(),
+		false => format!("{{\n{}\n}}", source.lines()
+			.map(|line| match line.is_empty() {
+				true => String::new(),
+				false => format!("\t{}", line)
+			})
+			.join("\n"))
+	}
+}
+
+/// The items declared directly within a block. Nested blocks (function bodies,
+/// block expressions and modules) own their own scopes, so walking `functions`,
+/// `modules` and `variables` yields the full scope tree. Names are normalized
+/// to NFC by the tokenizer, so canonically equivalent names are the same key.
+#[derive(Debug, Default, Serialize)]
 pub struct Scope<'s> {
+	#[serde(serialize_with = "serialize::map")]
 	pub types: HashMap<IStr<'s>, Type<'s>>,
-	functions: HashMap<IStr<'s>, Function<'s>>
+	#[serde(serialize_with = "serialize::map")]
+	pub functions: HashMap<IStr<'s>, Function<'s>>,
+	#[serde(serialize_with = "serialize::map")]
+	pub modules: HashMap<IStr<'s>, Module<'s>>,
+	#[serde(serialize_with = "serialize::map")]
+	pub imports: HashMap<IStr<'s>, Import<'s>>,
+	#[serde(serialize_with = "serialize::map")]
+	pub values: HashMap<IStr<'s>, Value<'s>>,
+	/// The names of every public item in a module's scope.
+	#[serde(serialize_with = "serialize::set")]
+	pub exports: HashSet<IStr<'s>>
 }
 
 impl<'s> Scope<'s> {
 	pub fn new() -> Self {
 		Default::default()
 	}
+
+	/// The scope containing every built in type, within which all modules are
+	/// nested.
+	pub fn prelude() -> Self {
+		let types = IntegerType::ALL.into_iter()
+			.map(|(name, integer)| (name, Type::Integer(integer)))
+			.chain([("f32", Type::Float), ("f64", Type::Float)])
+			.chain([("bool", Type::Boolean), ("str", Type::String), ("char", Type::Character)])
+			.map(|(name, r#type)| (name.into(), r#type))
+			.collect();
+
+		Self {types, ..Default::default()}
+	}
 }
 
+/// A view of a scope along with every scope enclosing it. Lookups start at the
+/// innermost scope, so inner items shadow outer ones.
 #[derive(Clone, Copy, Debug)]
-// TODO: This type is outdated (and I wrote it like an hour ago lmao).
-pub struct ScopeRef<'l, 'o, 's> {
-	local: &'l Scope<'s>,
-	outer: Option<&'o ScopeRef<'o, 'o, 's>>
+pub struct ScopeRef<'r, 's> {
+	local: &'r Scope<'s>,
+	outer: Option<&'r ScopeRef<'r, 's>>,
+	/// Whether the scope holds a function's arguments, beyond which only items
+	/// and foreign globals are visible, as functions do not capture variables.
+	function: bool
 }
 
-impl<'l, 'o, 's> ScopeRef<'l, 'o, 's> {
-	pub fn new(local: &'l Scope<'s>) -> Self {
-		Self {local, outer: None}
+impl<'r, 's> ScopeRef<'r, 's> {
+	pub fn new(local: &'r Scope<'s>) -> Self {
+		Self {local, outer: None, function: false}
+	}
+
+	pub fn r#in(&'r self, local: &'r Scope<'s>) -> Self {
+		Self {local, outer: Some(self), function: false}
+	}
+
+	/// A scope holding the arguments of a function declared in this scope.
+	pub fn function(&'r self, arguments: &'r Scope<'s>) -> Self {
+		Self {local: arguments, outer: Some(self), function: true}
+	}
+
+	pub fn local(&self) -> &'r Scope<'s> {
+		self.local
+	}
+
+	pub fn outer(&self) -> Option<&'r ScopeRef<'r, 's>> {
+		self.outer
+	}
+
+	/// The outermost scope, which every module is nested directly within.
+	pub fn root(&self) -> Self {
+		match self.outer {
+			Some(outer) => outer.root(),
+			None => *self
+		}
+	}
+
+	fn has_import(&self, name: &IStr<'s>, kind: ItemKind) -> bool {
+		self.local.imports.get(name)
+			.map(|import| import.kind == kind)
+			.unwrap_or_default()
+	}
+
+	/// Looks up the import a name of the given kind refers to, if the nearest
+	/// item it could refer to is an import rather than a declaration.
+	pub fn import_ref(&self, name: &IStr<'s>, kind: ItemKind)
+			-> Option<&'r Import<'s>> {
+		let declared = match kind {
+			ItemKind::Type => self.local.types.contains_key(name),
+			ItemKind::Function => self.local.functions.contains_key(name),
+			ItemKind::Module => self.local.modules.contains_key(name)
+		};
+
+		match declared {
+			true => None,
+			false => self.local.imports.get(name)
+				.filter(|import| import.kind == kind)
+				.or_else(|| self.outer.and_then(|scope| scope.import_ref(name, kind)))
+		}
 	}
 
-	pub fn r#in(&'o self, local: &'l Scope<'s>) -> Self {
-		Self {local, outer: Some(self)}
+	/// Looks up a type declared in this scope or an enclosing one. Imported
+	/// types are not declared here, and so are not found.
+	pub fn type_ref(&self, r#type: &IStr<'s>) -> Option<&'r Type<'s>> {
+		self.local.types.get(r#type)
+			.or_else(|| self.outer.and_then(|scope| scope.type_ref(r#type)))
+	}
+
+	pub fn function_ref(&self, function: &IStr<'s>) -> Option<&'r Function<'s>> {
+		self.local.functions.get(function)
+			.or_else(|| self.outer.and_then(|scope| scope.function_ref(function)))
+	}
+
+	pub fn value_ref(&self, value: &IStr<'s>) -> Option<&'r Value<'s>> {
+		self.local.values.get(value).or_else(|| match self.function {
+			true => self.outer?.global_ref(value),
+			false => self.outer?.value_ref(value)
+		})
+	}
+
+	/// Looks up a foreign global, skipping over any variables.
+	fn global_ref(&self, value: &IStr<'s>) -> Option<&'r Value<'s>> {
+		self.local.values.get(value)
+			.filter(|value| value.foreign.is_some())
+			.or_else(|| self.outer?.global_ref(value))
+	}
+
+	/// The item a type name refers to in this scope, if it names a type.
+	/// `module` is the path of the module the scope is in.
+	pub fn type_item(&self, r#type: &IStr<'s>, module: &[IStr<'s>])
+			-> Option<TypeItem<'s>> {
+		let module_path = || module.iter().cloned();
+
+		// The outermost scope is the prelude, and every module is nested
+		// directly within it.
+		match (self.local.types.get(r#type), self.outer.map(|outer| outer.outer)) {
+			(Some(_), None) => Some(TypeItem::Builtin(r#type.clone())),
+			(Some(_), Some(None)) =>
+				Some(TypeItem::Module(module_path().chain([r#type.clone()]).collect())),
+			(Some(r#type), Some(Some(_))) => Some(TypeItem::Local {
+				module: module_path().collect(),
+				location: r#type.location().expect("local types should be declared")
+			}),
+			(None, _) => match self.local.imports.get(r#type) {
+				Some(import) if import.kind == ItemKind::Type =>
+					Some(TypeItem::Module(import.path.clone())),
+				_ => self.outer.and_then(|scope| scope.type_item(r#type, module))
+			}
+		}
 	}
 
 	pub fn has_type(&self, r#type: &IStr<'s>) -> bool {
 		self.local.types.contains_key(r#type)
+			|| self.has_import(r#type, ItemKind::Type)
 			|| self.outer.map(|scope| scope.has_type(r#type)).unwrap_or_default()
 	}
+
+	pub fn has_function(&self, function: &IStr<'s>) -> bool {
+		self.local.functions.contains_key(function)
+			|| self.has_import(function, ItemKind::Function)
+			|| self.outer.map(|scope| scope.has_function(function)).unwrap_or_default()
+	}
 }
 
-pub fn construct_main_representation(block: &Block, scope: ScopeRef) -> Code<'static> {
-	// Only used to verify that named types exist; types declared at the end of
-	// the file may be used at the beginning of the same file.
-	let type_names: HashSet<_> = block.0.iter()
-		.filter_map(Statement::data_item_ref)
-		.map(|data| data.name())
+/// Constructs the main representation of a block. `scope` is the scope the
+/// block is nested within, and `module` is the module it belongs to; blocks
+/// that are the body of a module are processed with `scope` being the root.
+pub fn construct_main_representation<'s>(block: &Block<'s>, scope: ScopeRef<'_, 's>,
+		module: ModuleRef<'_, 's>) -> Result<Code<'s>, Error> {
+	let top_level = scope.outer().is_none();
+
+	// Process imports, relative to the module this block is in.
+	let imports = block.0.iter()
+		.filter_map(|statement| Some((statement.location(), statement.use_item_ref()?)))
+		.try_fold(HashMap::new(), |mut imports, (location, r#use)| {
+			let name = r#use.name().clone();
+			let span = location..location;
+
+			// Imports become JavaScript imports, which may only be at the top level
+			// of a module.
+			if !top_level {
+				return Err(error(NestedImport,
+					format!("{:?} imported outside of a module", name)).at(span))
+			}
+
+			let resolution = module.resolve(&r#use.path).map_err(|error| error.at(span.clone()))?;
+			let import = Import {path: resolution.path, kind: resolution.kind};
+
+			// Import Duplication Checks
+			// TODO: Remove clone when IStr becomes an identifier.
+			if imports.insert(name.clone(), import).is_some() {
+				return Err(error(DuplicateImport, format!("duplicate import {:?}", name))
+					.at(span))
+			}
+
+			Ok(imports)
+		})?;
+
+	// Items declared in extern blocks, along with the binding each refers to.
+	let foreign_items: Vec<_> = block.0.iter()
+		.filter_map(parser::Statement::extern_item_ref)
+		.map(|r#extern| {
+			// ABI Checks
+			if &*r#extern.abi != "js"
+				{return Err(error(UnsupportedAbi, format!("unsupported ABI {:?}", r#extern.abi)))}
+
+			Ok(r#extern.items.iter().map(|(header, item)| (header, item, Foreign {
+				abi: (*r#extern.abi).into(),
+				name: item.link_name().into(),
+				from: r#extern.from.as_deref().map(Into::into)
+			})))
+		})
+		.collect::<Result<Vec<_>, _>>()?
+		.into_iter()
+		.flatten()
+		.collect();
+
+	// The items the types named in this block refer to, before the types are
+	// processed, as types declared at the end of the file may be used at the
+	// beginning of the same file.
+	let module_path = module.path().to_vec();
+	let declared_item = |name: &IStr<'s>, location: Location| match top_level {
+		true => TypeItem::Module(module_path.iter().cloned().chain([name.clone()]).collect()),
+		false => TypeItem::Local {module: module_path.clone(), location}
+	};
+	let declared_types: HashMap<IStr, TypeItem> = imports.iter()
+		.filter(|(_, import)| import.kind == ItemKind::Type)
+		.map(|(name, import)| (name.clone(), TypeItem::Module(import.path.clone())))
+		.chain(block.0.iter()
+			.filter_map(|statement| Some((statement.data_item_ref()?, statement.location())))
+			.map(|(data, location)| (data.name().clone(), declared_item(data.name(), location))))
+		.chain(foreign_items.iter()
+			.filter(|(_, item, _)| matches!(item, ForeignItem::Type {..}))
+			.map(|(header, item, _)|
+				(item.name().clone(), declared_item(item.name(), header.location))))
 		.collect();
+	let type_names: HashSet<_> = declared_types.keys().map(|name| &**name).collect();
+	let check_type = |r#type: &IStr<'s>, location: Location| {
+		let name = r#type.clone();
+
+		// Type Reference Checks
+		let item = declared_types.get(r#type).cloned()
+			.or_else(|| scope.type_item(&name, module.path()))
+			.ok_or_else(|| error(UnknownType, format!("unknown type {:?}", r#type))
+				.at(location..location))?;
+
+		Ok::<_, Error>(TypeName {name, item})
+	};
+
+	// Process modules, which do not see any of the items around them.
+	let modules = block.0.iter()
+		.filter_map(|statement| Some((statement, statement.mod_item_ref()?)))
+		.try_fold(HashMap::new(), |mut modules, (statement, item)| {
+			let span = statement.location()..statement.location();
+			if !top_level {
+				return Err(error(NestedModule,
+					format!("module {:?} declared outside of a module", item.name)).at(span))
+			}
+
+			let name = item.name.clone();
+			let path: Vec<_> = module.path().iter().cloned()
+				.chain([item.name.clone()])
+				.collect();
+			let body = item.body.as_ref()
+				.unwrap_or_else(|| panic!("module {:?} was not loaded", item.name));
+			let code = construct_main_representation(body, scope.root(), module.enter(&path))
+				.map_err(|error| match &item.file {
+					Some(file) => error.in_file(file),
+					None => error
+				})?;
+			let module = Module {
+				code,
+				documentation: statement.documentation().map(Into::into),
+				file: item.file.clone()
+			};
+
+			// Module Duplication Checks
+			// TODO: Remove clone when IStr becomes an identifier.
+			if modules.insert(name.clone(), module).is_some() {
+				return Err(error(DuplicateModule, format!("duplicate module {:?}", name))
+					.at(span))
+			}
+
+			Ok(modules)
+		})?;
+
+	// Public items are only meaningful at the top level of a module.
+	let exports = match top_level {
+		true => block.0.iter()
+			.filter(|statement| statement.visibility() == Visibility::Public)
+			.filter_map(|statement| match statement {
+				parser::Statement::DataItem(_, data) => Some(data.name().clone()),
+				parser::Statement::FunctionItem(_, function) => Some(function.name.clone()),
+				parser::Statement::ModItem(_, module) => Some(module.name.clone()),
+				parser::Statement::UseItem(_, r#use) => Some(r#use.name().clone()),
+				_ => None
+			})
+			.chain(foreign_items.iter()
+				.filter(|(header, item, _)| header.visibility == Visibility::Public
+					&& !matches!(item, ForeignItem::Global {..}))
+				.map(|(_, item, _)| item.name().clone()))
+			.collect(),
+		false => HashSet::new()
+	};
 
 	// Process types.
 	let types = block.0.iter()
-		.filter_map(Statement::data_item_ref)
-		.fold(HashMap::new(), |mut types, data| {
-			let name = (PhantomData, data.name().into());
+		.filter_map(|statement| Some((statement, statement.data_item_ref()?)))
+		.try_fold(HashMap::new(), |mut types, (statement, data)| {
+			let name = data.name().clone();
+			let documentation = statement.documentation().map(Into::into);
+			let location = statement.location();
+			let span = location..location;
 			let r#type = match data {
 				DataItem::Single(variant) => {
-					let (name, format) =
-						construct_data_representation(variant, scope, &type_names);
-					Type::User {format}
+					let (_, format) = construct_data_representation(variant, scope, &type_names)
+						.map_err(|error| error.at(span.clone()))?;
+					Type::User {format, documentation, location}
 				},
 
-				DataItem::Multiple {name, variants} => {
+				DataItem::Multiple {variants, ..} => {
 					let variants = variants.iter()
-						.fold(HashMap::new(), |mut variants, variant| {
+						.try_fold(HashMap::new(), |mut variants, variant| {
 							let (name, format) =
-								construct_data_representation(variant, scope, &type_names);
+								construct_data_representation(variant, scope, &type_names)?;
 
 							// Variant Duplication Checks
 							// TODO: Remove clone when IStr becomes an identifier.
-							if variants.insert(name.clone(), format).is_some()
-								{panic!("duplicate variant {:?}", name)}
+							if variants.insert(name.clone(), format).is_some() {
+								return Err(error(DuplicateVariant,
+									format!("duplicate variant {:?}", name)))
+							}
 
-							variants
-						});
+							Ok(variants)
+						})
+						.map_err(|error| error.at(span.clone()))?;
 
 					Type::User {
 						format: DataFormat::Named {
 							fields: HashMap::new(),
 							variants
-						}
+						},
+						documentation,
+						location
 					}
 				}
 			};
@@ -131,87 +877,556 @@ pub fn construct_main_representation(block: &Block, scope: ScopeRef) -> Code<'st
 			// Type Duplication Checks
 			// TODO: Remove clone when IStr becomes an identifier.
 			if types.insert(name.clone(), r#type).is_some()
-				{panic!("duplicate type {:?}", name)}
+				{return Err(error(DuplicateType, format!("duplicate type {:?}", name)).at(span))}
 
-			types
-		});
+			Ok(types)
+		})?;
+	let types = foreign_items.iter()
+		.filter(|(_, item, _)| matches!(item, ForeignItem::Type {..}))
+		.try_fold(types, |mut types, (header, item, foreign)| {
+			let name = item.name().clone();
+			let r#type = Type::Foreign {binding: foreign.clone(), location: header.location};
 
-	// Same deal as type_names.
-	// TODO: How do we compile multiple files together???
-	let function_names: HashSet<_> = block.0.iter()
-		.filter_map(Statement::function_item_ref)
-		.map(|function| &*function.name)
-		.collect();
+			// Type Duplication Checks
+			// TODO: Remove clone when IStr becomes an identifier.
+			if types.insert(name.clone(), r#type).is_some() {
+				return Err(error(DuplicateType, format!("duplicate type {:?}", name))
+					.at(header.location..header.location))
+			}
 
-	// Process functions.
+			Ok(types)
+		})?;
+
+	// Functions are declared before any body is processed, so that bodies may
+	// refer to functions declared after them (or to themselves).
 	let functions = block.0.iter()
-		.filter_map(Statement::function_item_ref)
-		.fold(HashMap::new(), |mut functions, function| {
-			let name = (PhantomData, function.name.clone());
-			// TODO: Fix scoping.
+		.filter_map(|statement| Some((statement, statement.function_item_ref()?)))
+		.map(|(statement, function)| (&function.name, &function.arguments,
+			&function.r#return, None, statement.header().cloned().unwrap_or_default()))
+		.chain(foreign_items.iter()
+			.filter_map(|(header, item, foreign)| match item {
+				ForeignItem::Function {name, arguments, r#return, ..} => Some((name,
+					arguments, r#return, Some(foreign.clone()), (*header).clone())),
+				_ => None
+			}))
+		.try_fold(HashMap::new(), |mut functions,
+				(name, arguments, r#return, foreign, header)| {
+			let name = name.clone();
+			let location = header.location;
+
+			// Return Type Checks
+			if foreign.is_none() && r#return.is_some() {
+				return Err(error(UnsupportedReturnType, format!(
+					"function {:?} cannot return a value; only foreign functions have return types",
+					name)).at(location..location))
+			}
+
 			let function = Function {
-				code: construct_main_representation(&function.body, scope)
+				arguments: arguments.iter()
+					.map(|(name, r#type)|
+						Ok((name.clone(), check_type(r#type, location)?)))
+					.collect::<Result<_, Error>>()?,
+				r#return: r#return.as_ref()
+					.map(|r#type| check_type(r#type, location))
+					.transpose()?,
+				code: Code::default(),
+				foreign,
+				documentation: header.documentation.as_deref().map(Into::into),
+				location: header.location
 			};
 
 			// Function Duplication Checks
 			// TODO: Remove clone when IStr becomes an identifier.
-			if functions.insert(name.clone(), function).is_some()
-				{panic!("duplicate type {:?}", name)}
+			if functions.insert(name.clone(), function).is_some() {
+				return Err(error(DuplicateFunction, format!("duplicate function {:?}", name))
+					.at(location..location))
+			}
+
+			Ok(functions)
+		})?;
+
+	// Foreign globals are the only values visible to functions declared here.
+	let values = foreign_items.iter()
+		.filter_map(|(header, item, foreign)| match item {
+			ForeignItem::Global {name, r#type, ..} =>
+				Some((name, r#type, header.location, foreign)),
+			_ => None
+		})
+		.try_fold(HashMap::new(), |mut values, (name, r#type, location, foreign)| {
+			let name = name.clone();
+			let r#type = check_type(r#type, location)?;
+
+			// Value Duplication Checks
+			// TODO: Remove clone when IStr becomes an identifier.
+			if values.insert(name.clone(), Value {r#type, foreign: Some(foreign.clone())})
+					.is_some() {
+				return Err(error(DuplicateGlobal, format!("duplicate global {:?}", name))
+					.at(location..location))
+			}
+
+			Ok(values)
+		})?;
+
+	let mut local = Scope {types, functions, modules, imports, values, exports};
+	let inner = scope.r#in(&local);
 
-			functions
-		});
+	// Process function bodies, each within a scope nested in this one, which
+	// is itself nested in a scope holding the function's arguments.
+	let bodies: Vec<_> = block.0.iter()
+		.filter_map(|statement| Some((statement.location(), statement.function_item_ref()?)))
+		.map(|(location, function)| {
+			let name = function.name.clone();
+			let arguments = Scope {
+				values: local.functions[&name].arguments.iter()
+					.map(|(name, r#type)| (name.clone(), Value {
+						r#type: r#type.clone(),
+						foreign: None
+					}))
+					.collect(),
+				..Default::default()
+			};
+			let code = construct_main_representation(&function.body,
+				inner.function(&arguments), module)
+				.map_err(|error| error.or_at(location..location))?;
+
+			Ok((name, code))
+		})
+		.collect::<Result<_, Error>>()?;
+
+	bodies.into_iter().for_each(|(name, code)|
+		local.functions.get_mut(&name).unwrap().code = code);
+
+	// Process variables and expressions in order. Each variable is only visible
+	// to the statements after it.
+	let statements = block.0.iter()
+		.map(|statement| Ok::<_, Error>(match statement {
+			parser::Statement::LetItem(_, variable) => {
+				let location = statement.location();
+				let span = location..location;
+				let name = variable.name.clone();
+				let r#type = check_type(&variable.r#type, location)?;
+				let mut expression = construct_expression(&variable.expression,
+					scope.r#in(&local), module)
+					.map_err(|error| error.or_at(span.clone()))?;
+
+				// Variable Type Checks
+				check_integer_literal(&mut expression, &r#type)
+					.map_err(|error| error.at(span.clone()))?;
+				if !r#type.accepts(&expression.r#type()) {
+					return Err(error(MismatchedTypes,
+						format!("mismatched types in variable {:?}; {}",
+							name, mismatch(&r#type, &expression.r#type()))).at(span))
+				}
+
+				local.values.insert(name.clone(), Value {r#type: r#type.clone(), foreign: None});
+				Some(Statement::Variable(Variable {
+					name,
+					r#type,
+					expression,
+					documentation: statement.documentation().map(Into::into),
+					location: statement.location()
+				}))
+			},
+			parser::Statement::Expression(location, expression) => Some(Statement::Expression {
+				expression: construct_expression(expression, scope.r#in(&local), module)
+					.map_err(|error| error.or_at(*location..*location))?,
+				location: *location
+			}),
+			_ => None
+		}))
+		.filter_map(Result::transpose)
+		.collect::<Result<_, _>>()?;
 
-	Code {scope: Scope {types, functions}}
+	let documentation = block.0.iter()
+		.filter_map(|statement| match statement {
+			parser::Statement::Documentation(text) => Some(&**text),
+			_ => None
+		})
+		.collect::<Vec<_>>();
+	let documentation = (!documentation.is_empty())
+		.then(|| documentation.join("\n").into());
+
+	Ok(Code {scope: local, statements, documentation})
 }
 
-pub fn construct_data_representation<V>(variant: &DataVariant,
-		scope: ScopeRef, type_names: &HashSet<&str>)
-			-> (IStr<'static>, GenericFormat<'static, V>) where V: Default {
-	match variant {
+/// Constructs the main representation of an expression. Errors in it are only
+/// placed where the parser records locations, such as at blocks' statements.
+pub fn construct_expression<'s>(expression: &parser::Expression<'s>, scope: ScopeRef<'_, 's>,
+		module: ModuleRef<'_, 's>) -> Result<Expression<'s>, Error> {
+	Ok(match expression {
+		parser::Expression::Block(block) =>
+			Expression::Block(Box::new(
+				construct_main_representation(block, scope, module)?)),
+		parser::Expression::LiteralInteger {value, suffix} => {
+			let mut literal =
+				Expression::LiteralInteger {value: *value, suffix: None, integer: None};
+			// A suffixed literal is checked as if it were used as its suffix.
+			if let Some(suffix) = suffix {
+				check_integer_literal(&mut literal, &TypeName::builtin(suffix.clone()))?;
+				if let Expression::LiteralInteger {suffix: written, ..} = &mut literal
+					{*written = Some(suffix.clone())}
+			}
+
+			literal
+		},
+		parser::Expression::LiteralFloat {value, suffix} => Expression::LiteralFloat {
+			value: value.clone(),
+			suffix: suffix.clone()
+		},
+		parser::Expression::LiteralBoolean(boolean) =>
+			Expression::LiteralBoolean(*boolean),
+		parser::Expression::LiteralString(string) =>
+			Expression::LiteralString(string.clone()),
+		parser::Expression::LiteralCharacter(character) =>
+			Expression::LiteralCharacter(*character),
+
+		parser::Expression::Identifier(name) => {
+			let name = name.clone();
+
+			// Value Reference Checks
+			let value = scope.value_ref(&name)
+				.ok_or_else(|| error(UnknownValue, format!("unknown value {:?}", name)))?;
+
+			Expression::Variable {
+				r#type: value.r#type.clone(),
+				foreign: value.foreign.clone(),
+				name
+			}
+		},
+
+		parser::Expression::FunctionCall {name, arguments} => {
+			let name = name.clone();
+
+			// Function Reference Checks
+			let (expected, r#return, foreign) = match scope.import_ref(&name, ItemKind::Function) {
+				Some(import) => {
+					let signature = module.signature(&import.path);
+
+					// The types are named as they are in the function's own module.
+					let declared_in = &import.path[..import.path.len() - 1];
+					let resolve = |r#type: &IStr<'s>| Ok::<_, Error>(TypeName {
+						name: r#type.clone(),
+						item: match module.type_path(declared_in, r#type)? {
+							Some(path) => TypeItem::Module(path),
+							None => TypeItem::Builtin(r#type.clone())
+						}
+					});
+					let arguments: Vec<_> = signature.arguments.iter()
+						.map(resolve)
+						.collect::<Result<_, _>>()?;
+					(arguments, signature.r#return.as_ref().map(resolve).transpose()?, None)
+				},
+				None => match scope.function_ref(&name) {
+					Some(function) => (
+						function.arguments.iter().map(|(_, r#type)| r#type.clone()).collect(),
+						function.r#return.clone(),
+						function.foreign.clone()
+					),
+					None => return Err(error(UnknownFunction,
+						format!("unknown function {:?}", name)))
+				}
+			};
+
+			let mut arguments = arguments.iter()
+				.map(|argument| construct_expression(argument, scope, module))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			// Argument Count & Type Checks
+			if arguments.len() != expected.len() {
+				return Err(error(ArgumentCount,
+					format!("function {:?} takes {} arguments but {} were supplied",
+						name, expected.len(), arguments.len())))
+			}
+			expected.iter().zip(&mut arguments)
+				.try_for_each(|(expected, argument)| {
+					check_integer_literal(argument, expected)?;
+					match expected.accepts(&argument.r#type()) {
+						true => Ok(()),
+						false => Err(error(MismatchedTypes,
+							format!("mismatched types in call to {:?}; {}",
+								name, mismatch(expected, &argument.r#type()))))
+					}
+				})?;
+
+			Expression::FunctionCall {name, arguments, r#return, foreign}
+		}
+	})
+}
+
+/// Checks that an integer literal without a suffix fits within the type it is
+/// used as, if that is an integer type, and records the type on the literal.
+fn check_integer_literal(expression: &mut Expression, r#type: &TypeName) -> Result<(), Error> {
+	if let (Expression::LiteralInteger {value, suffix: None, integer: slot},
+			Some(integer)) = (expression, r#type.integer()) {
+		// Integer Overflow Checks
+		if *value > integer.max() {
+			return Err(error(LiteralOutOfRange,
+				format!("literal {} out of range for {:?}; its maximum is {}",
+					value, r#type.name, integer.max())))
+		}
+		*slot = Some(integer);
+	}
+
+	Ok(())
+}
+
+/// Describes what was expected and found where types do not match, telling
+/// types apart by where they are declared when they have the same name.
+fn mismatch(expected: &TypeName, found: &ExpressionType) -> String {
+	match found {
+		ExpressionType::Named(found) if found.name == expected.name =>
+			format!("expected {:?}, found {}", expected.qualified(), found.qualified()),
+		_ => format!("expected {:?}, found {}", expected.name, found)
+	}
+}
+
+pub fn construct_data_representation<'s, V>(variant: &DataVariant<'s>,
+		scope: ScopeRef<'_, 's>, type_names: &HashSet<&str>)
+			-> Result<(IStr<'s>, GenericFormat<'s, V>), Error> where V: Default {
+	Ok(match variant {
 		DataVariant::Marker {name} => (
-			(PhantomData, name.clone()),
+			name.clone(),
 			GenericFormat::Marker
 		),
 
 		DataVariant::Tuple {name, fields} => {
-			let fields: Vec<_> = fields.iter()
+			let fields = fields.iter()
 				.map(|r#type| {
-					let r#type = (PhantomData, r#type.clone());
+					let r#type = r#type.clone();
 
 					// Type Reference Checks
-					if !scope.has_type(&r#type) && !type_names.contains(&*r#type.1)
-						{panic!("unknown type {:?}", r#type)}
+					if !scope.has_type(&r#type) && !type_names.contains(&*r#type)
+						{return Err(error(UnknownType, format!("unknown type {:?}", r#type)))}
 
-					r#type
+					Ok(r#type)
 				})
-				.collect();
+				.collect::<Result<_, _>>()?;
 
 			(
-				(PhantomData, name.clone()),
+				name.clone(),
 				GenericFormat::Unnamed {fields}
 			)
 		},
 
 		DataVariant::Struct {name, fields} => {
 			let fields = fields.iter()
-				.fold(HashMap::new(), |mut fields, (name, r#type)| {
-					let r#type = (PhantomData, r#type.clone());
-					let name = (PhantomData, name.clone());
+				.try_fold(HashMap::new(), |mut fields, (name, r#type)| {
+					let r#type = r#type.clone();
+					let name = name.clone();
 
 					// Type Reference & Field Duplication Checks
-					if !scope.has_type(&r#type) && !type_names.contains(&*r#type.1)
-						{panic!("unknown type {:?}", r#type)}
+					if !scope.has_type(&r#type) && !type_names.contains(&*r#type)
+						{return Err(error(UnknownType, format!("unknown type {:?}", r#type)))}
 					// TODO: Remove clone when IStr becomes an identifier.
 					if fields.insert(name.clone(), r#type).is_some()
-						{panic!("duplicate field {:?}", name)}
+						{return Err(error(DuplicateField, format!("duplicate field {:?}", name)))}
 
-					fields
-				});
+					Ok(fields)
+				})?;
 
 			(
-				(PhantomData, name.clone()),
+				name.clone(),
 				GenericFormat::Named {fields, variants: Default::default()}
 			)
 		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{construct_main_representation, error::{Error, ErrorCode::{self, *}},
+		module::{Declarations, ModuleRef}, parser::Parser, tokenizer::{Located, Location}, Code,
+		Foreign, Scope, ScopeRef, Statement, TypeName, Value};
+
+	/// Checks a crate root whose modules all have bodies.
+	fn check(source: &str) -> Result<Code<'_>, Error> {
+		let block = Parser::new(Located::new(source)).parse_block()?;
+		let declarations = Declarations::declare(&block)?;
+		let prelude = Scope::prelude();
+		construct_main_representation(&block, ScopeRef::new(&prelude),
+			ModuleRef::new(&declarations))
+	}
+
+	fn code(source: &str) -> Option<ErrorCode> {
+		check(source).err().map(|error| error.code)
+	}
+
+	#[test]
+	fn only_foreign_functions_return_values() {
+		assert_eq!(code("fn f() -> i32 {}"), Some(UnsupportedReturnType));
+		assert_eq!(code("fn f() {fn g() -> str {}}"), Some(UnsupportedReturnType));
+		assert_eq!(code("extern \"js\" {fn f() -> i32;} let x: i32 = f();"), None);
+	}
+
+	#[test]
+	fn variables_are_checked_against_their_types() {
+		let mismatched = [
+			"let x: i32 = true;",
+			"let y: bool = 5;",
+			"let z: str = 1.5;",
+			"data P; let p: P = 5;",
+			"extern \"js\" {fn log(message: str);} let a: i32 = log(\"x\");",
+			"let b: i32 = {};",
+			"let c: i32 = 1; let d: i64 = c;",
+			"let e: char = \"e\";"
+		];
+		mismatched.into_iter().for_each(|source|
+			assert_eq!(code(source), Some(MismatchedTypes), "{}", source));
+
+		let matched = [
+			"let x: i32 = 1; let y: i32 = x;",
+			"let z: f32 = 1.5;",
+			"let s: str = \"s\";",
+			"data P; extern \"js\" {fn p() -> P;} let q: P = p();"
+		];
+		matched.into_iter().for_each(|source| assert_eq!(code(source), None, "{}", source));
+	}
+
+	#[test]
+	fn literals_are_checked_against_their_types() {
+		let mismatched = [
+			"let x: i64 = 0xffu8;",
+			"let x: i32 = 1.5f32;",
+			"let x: i32 = 1e3;",
+			"let x: u8 = 2.;",
+			"let x: f32 = 1.5f64;",
+			"let x: f64 = 1u8;",
+			"let x: f32 = 1;",
+			"extern \"js\" {fn f(x: u16);} f(1i16);"
+		];
+		mismatched.into_iter().for_each(|source|
+			assert_eq!(code(source), Some(MismatchedTypes), "{}", source));
+
+		assert_eq!(code("let x: u8 = 256;"), Some(LiteralOutOfRange));
+		assert_eq!(code("let x: u8 = 0x1_00u8;"), Some(LiteralOutOfRange));
+		assert_eq!(code("let x: i8 = 128;"), Some(LiteralOutOfRange));
+
+		let matched = [
+			"let x: u8 = 0xffu8;",
+			"let x: i64 = 0xff;",
+			"let x: u128 = 340282366920938463463374607431768211455;",
+			"let x: f32 = 2f32;",
+			"let x: f64 = 1e3;",
+			"extern \"js\" {fn f(x: u16);} f(0b1u16);"
+		];
+		matched.into_iter().for_each(|source| assert_eq!(code(source), None, "{}", source));
+	}
+
+	#[test]
+	fn types_are_the_items_they_name() {
+		let module = "mod m {pub data P; pub fn g(p: P) {}} use crate::m::g;";
+		let root = format!("data P; extern \"js\" {{fn p() -> P;}} {} g(p());", module);
+		assert_eq!(code(&root), Some(MismatchedTypes));
+		assert!(check(&root).unwrap_err().message
+			.ends_with("expected \"crate::m::P\", found crate::P"));
+
+		let reexported = format!("mod n {{pub use crate::m::P;}} use crate::n::P; \
+			extern \"js\" {{fn p() -> P;}} {} g(p());", module);
+		assert_eq!(code(&reexported), None);
+
+		let shadowed = "data P; extern \"js\" {fn p() -> P;} fn f() {data P; let q: P = p();}";
+		assert_eq!(code(shadowed), Some(MismatchedTypes));
+		assert_eq!(code("data P; extern \"js\" {fn p() -> P;} fn f() {let q: P = p();}"), None);
+
+		// A type named like a built in one is not built in.
+		assert_eq!(code("data i32; let x: i32 = 1;"), Some(MismatchedTypes));
+		assert_eq!(code("data i32; extern \"js\" {fn p() -> i32;} let x: i32 = p();"), None);
+	}
+
+	#[test]
+	fn functions_do_not_capture_variables() {
+		assert_eq!(code("fn f(a: i32) {fn g() {let b: i32 = a;}}"), Some(UnknownValue));
+		assert_eq!(code("fn f() {let x: i32 = 1; {fn g() {let y: i32 = x;}}}"),
+			Some(UnknownValue));
+		assert_eq!(code("fn f(a: i32) {fn g(a: bool) {let b: bool = a;}}"), None);
+		assert_eq!(code("fn f(a: i32) {{let b: i32 = a;}}"), None);
+
+		// Foreign globals are not variables, even when a variable shadows them.
+		let global = "extern \"js\" {let g: i32;} fn f(g: bool) {fn h() {let x: i32 = g;}}";
+		assert_eq!(code(global), None);
+	}
+
+	#[test]
+	fn inner_scopes_shadow_outer_ones() {
+		let value = |r#type, foreign| Value {r#type: TypeName::builtin(r#type), foreign};
+		let global = || Foreign {abi: "js".into(), name: "g".into(), from: None};
+
+		let mut outer = Scope::new();
+		outer.values.insert("x".into(), value("i32", None));
+		outer.values.insert("g".into(), value("str", Some(global())));
+		let mut inner = Scope::new();
+		inner.values.insert("x".into(), value("bool", None));
+		let arguments = Scope::new();
+
+		let outer = ScopeRef::new(&outer);
+		let inner = outer.r#in(&inner);
+		let type_of = |scope: ScopeRef<'_, '_>, name: &'static str| scope.value_ref(&name.into())
+			.map(|value| value.r#type.name.to_string());
+		assert_eq!(type_of(outer, "x").as_deref(), Some("i32"));
+		assert_eq!(type_of(inner, "x").as_deref(), Some("bool"));
+		assert_eq!(type_of(inner, "g").as_deref(), Some("str"));
+
+		// Only foreign globals are seen from within a function.
+		let function = inner.function(&arguments);
+		assert_eq!(type_of(function, "x"), None);
+		assert_eq!(type_of(function, "g").as_deref(), Some("str"));
+	}
+
+	#[test]
+	fn variables_shadow_those_before_them() {
+		assert_eq!(code("let x: i32 = 1; let x: bool = true; let y: bool = x;"), None);
+		assert_eq!(code("let x: i32 = 1; let x: bool = true; let y: i32 = x;"),
+			Some(MismatchedTypes));
+		assert_eq!(code("let x: i32 = 1; {let x: bool = true;} let y: i32 = x;"), None);
+		assert_eq!(code("let x: i32 = 1; {let x: bool = true; let y: bool = x;}"), None);
+		assert_eq!(code("fn f(a: i32) {let a: bool = true; let b: bool = a;}"), None);
+		assert_eq!(code("{let x: i32 = 1;} let y: i32 = x;"), Some(UnknownValue));
+	}
+
+	#[test]
+	fn items_are_seen_across_function_boundaries() {
+		assert_eq!(code("fn f() {} fn g() {fn h() {{f();}}}"), None);
+		assert_eq!(code("data P; fn g() {fn h(p: P) {}}"), None);
+		assert_eq!(code("fn g() {h(); fn h() {}}"), None);
+		assert_eq!(code("fn g() {fn h() {}} fn i() {h();}"), Some(UnknownFunction));
+
+		// The nearest function of a name is the one called.
+		let shadowed = "fn f(a: bool) {} fn g() {fn f(a: i32) {} {f(1);}}";
+		assert_eq!(code(shadowed), None);
+		assert_eq!(code("fn f(a: bool) {} fn g() {fn f(a: i32) {} f(true);}"),
+			Some(MismatchedTypes));
+	}
+
+	#[test]
+	fn imports_are_only_at_the_top_level() {
+		let module = "mod m {pub fn g() {}}";
+		assert_eq!(code(&format!("{} fn main() {{use crate::m::g; g();}}", module)),
+			Some(NestedImport));
+		assert_eq!(code(&format!("{} fn main() {{{{use crate::m;}}}}", module)),
+			Some(NestedImport));
+		assert_eq!(code(&format!("{} use crate::m::g; fn main() {{g();}}", module)), None);
+		assert_eq!(code("mod m {mod n {pub fn f() {}} use self::n::f;}"), None);
+	}
+
+	#[test]
+	fn expression_statements_run_in_order() {
+		let code = check("fn f() {} let x: i32 = 1; f(); {} let y: i32 = x;").unwrap();
+		let kinds: Vec<_> = code.statements.iter()
+			.map(|statement| matches!(statement, Statement::Variable(_)))
+			.collect();
+		assert_eq!(kinds, [true, false, false, true]);
+		assert_eq!(code.to_string().lines().last(), Some("let y: i32 = x;"));
+	}
+
+	#[test]
+	fn errors_are_placed_at_their_statements() {
+		let at = |line, column| Some(Location {line, column}..Location {line, column});
+		let span = |source| check(source).unwrap_err().span;
+
+		assert_eq!(span("fn f() {}\n  let x: bool = 1;"), at(1, 2));
+		assert_eq!(span("fn f() {}\nfn f() {}"), at(1, 0));
+		assert_eq!(span("fn f() {\n\tg();\n}"), at(1, 1));
+		assert_eq!(span("data P;\n\npub data P;"), at(2, 0));
+		assert_eq!(span("fn f(x: Q) {}"), at(0, 0));
 	}
 }
@@ -0,0 +1,345 @@
+use super::{error::{error, Error, ErrorCode::*},
+	parser::{Block, ForeignItem, Parser, PathSegment, Statement, Visibility}, tokenizer::Located,
+	IStr};
+use itertools::Itertools;
+use serde::Serialize;
+use std::{collections::{BTreeSet, HashMap, HashSet}, fs::read_to_string,
+	path::{Path, PathBuf}};
+
+/// Parses the crate root at `path`, along with every file it declares as a
+/// module, into a single block in which every module has a body.
+///
+/// `mod name;` in a crate root (or a `mod.rsst` file) loads `name.rsst` or
+/// `name/mod.rsst` from the same directory; in any other file `a.rsst` it loads
+/// from the `a` directory next to it.
+///
+/// Every path the crate's source was looked for at, whether or not a file was
+/// found there, is added to `sources`, even if loading fails.
+pub fn load(path: &Path, sources: &mut BTreeSet<PathBuf>) -> Result<Block<'static>, Error> {
+	let directory = path.parent().unwrap_or_else(|| Path::new(""));
+	sources.insert(path.to_owned());
+	load_file(path, directory, &mut HashSet::new(), sources)
+}
+
+/// Parses a crate root given as source text, such as one read from standard
+/// input, loading the modules it declares from `directory`. Names in the crate
+/// root borrow from `source`.
+pub fn load_source<'s>(source: &'s str, directory: &Path, sources: &mut BTreeSet<PathBuf>)
+		-> Result<Block<'s>, Error> {
+	let mut block = Parser::new(Located::new(source)).parse_block()?;
+	load_declared(&mut block, directory, sources)?;
+
+	Ok(block)
+}
+
+/// Loads the modules an already parsed crate root declares from `directory`,
+/// giving each of them its body.
+pub fn load_declared(block: &mut Block<'_>, directory: &Path, sources: &mut BTreeSet<PathBuf>)
+		-> Result<(), Error> {
+	load_modules(block, directory, &mut HashSet::new(), sources)
+}
+
+/// Parses the module file at `path`. Its text does not outlive loading, so the
+/// block parsed from it owns its names.
+fn load_file(path: &Path, directory: &Path, loaded: &mut HashSet<PathBuf>,
+		sources: &mut BTreeSet<PathBuf>) -> Result<Block<'static>, Error> {
+	// Module Reloading Checks
+	let canonical = path.canonicalize()
+		.map_err(|cause| error(ModuleNotFound,
+			format!("could not load module {:?}: {}", path, cause)))?;
+	if !loaded.insert(canonical) {
+		return Err(error(ModuleReloaded,
+			format!("module file {:?} is loaded more than once", path)))
+	}
+
+	let source = read_to_string(path)
+		.map_err(|cause| error(ModuleNotFound,
+			format!("could not load module {:?}: {}", path, cause)))?;
+	let tokens = Located::new(&source)
+		.map(|token| token.map(|(span, token)| (span, token.into_owned())));
+	let mut block = Parser::new(tokens).parse_block().map_err(|error| error.in_file(path))?;
+	load_modules(&mut block, directory, loaded, sources).map_err(|error| error.in_file(path))?;
+
+	Ok(block)
+}
+
+fn load_modules(block: &mut Block<'_>, directory: &Path,
+		loaded: &mut HashSet<PathBuf>, sources: &mut BTreeSet<PathBuf>) -> Result<(), Error> {
+	block.0.iter_mut()
+		.filter_map(|statement| match statement {
+			Statement::ModItem(header, module) => Some((header.location, module)),
+			_ => None
+		})
+		.try_for_each(|(location, module)| {
+			let nested = directory.join(&*module.name);
+			match &mut module.body {
+				Some(body) => load_modules(body, &nested, loaded, sources),
+
+				None => {
+					let file = directory.join(format!("{}.rsst", module.name));
+					let index = nested.join("mod.rsst");
+					sources.extend([file.clone(), index.clone()]);
+
+					let path = match (file.is_file(), index.is_file()) {
+						(true, false) => file,
+						(false, true) => index,
+						(true, true) => return Err(error(AmbiguousModule, format!(
+							"module {:?} found at both {:?} and {:?}", module.name, file, index))
+							.at(location..location)),
+						(false, false) => return Err(error(ModuleNotFound, format!(
+							"module {:?} not found at {:?} or {:?}", module.name, file, index))
+							.at(location..location))
+					};
+
+					module.body = Some(load_file(&path, &nested, loaded, sources)?);
+					module.file = Some(path);
+					Ok(())
+				}
+			}
+		})
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ItemKind {
+	Type,
+	Function,
+	Module
+}
+
+#[derive(Debug)]
+pub enum Declaration<'s> {
+	Type,
+	Function(Signature<'s>),
+	Module(Declarations<'s>),
+	Import(Vec<PathSegment<'s>>)
+}
+
+/// The names of the argument and return types of a function.
+#[derive(Clone, Debug, Default)]
+pub struct Signature<'s> {
+	pub arguments: Vec<IStr<'s>>,
+	pub r#return: Option<IStr<'s>>
+}
+
+impl<'s> Signature<'s> {
+	fn new(arguments: &[(IStr<'s>, IStr<'s>)], r#return: &Option<IStr<'s>>) -> Self {
+		Self {
+			arguments: arguments.iter().map(|(_, r#type)| r#type.clone()).collect(),
+			r#return: r#return.clone()
+		}
+	}
+}
+
+/// The names declared at the top level of every module in a crate, gathered
+/// before any module is processed so that modules may import from each other
+/// regardless of order.
+#[derive(Debug, Default)]
+pub struct Declarations<'s> {
+	pub items: HashMap<IStr<'s>, (Visibility, Declaration<'s>)>
+}
+
+impl<'s> Declarations<'s> {
+	pub fn declare(block: &Block<'s>) -> Result<Self, Error> {
+		let items = block.0.iter()
+			.flat_map(|statement| {
+				let declaration = match statement {
+					Statement::DataItem(_, data) =>
+						(data.name().clone(), Declaration::Type),
+					Statement::FunctionItem(_, function) => (
+						function.name.clone(),
+						Declaration::Function(Signature::new(&function.arguments,
+							&function.r#return))
+					),
+					Statement::ModItem(_, module) => match module.body.as_ref().map(Self::declare) {
+						Some(Ok(body)) => (module.name.clone(), Declaration::Module(body)),
+						Some(Err(error)) => return vec![Err(match &module.file {
+							Some(file) => error.in_file(file),
+							None => error
+						})],
+						None => panic!("module {:?} was not loaded", module.name)
+					},
+					Statement::UseItem(_, r#use) =>
+						(r#use.name().clone(), Declaration::Import(r#use.path.clone())),
+
+					// Foreign globals are values rather than items, and so cannot be
+					// imported.
+					Statement::ExternItem(r#extern) => return r#extern.items.iter()
+						.filter_map(|(header, item)| match item {
+							ForeignItem::Function {name, arguments, r#return, ..} => Some((
+								(header.visibility, header.location),
+								(name.clone(),
+									Declaration::Function(Signature::new(arguments, r#return)))
+							)),
+							ForeignItem::Type {name, ..} => Some((
+								(header.visibility, header.location),
+								(name.clone(), Declaration::Type)
+							)),
+							ForeignItem::Global {..} => None
+						})
+						.map(Ok)
+						.collect(),
+
+					_ => return Vec::new()
+				};
+
+				vec![Ok(((statement.visibility(), statement.location()), declaration))]
+			})
+			.try_fold(HashMap::new(), |mut items, declaration| {
+				let ((visibility, location), (name, declaration)) = declaration?;
+
+				// Item Duplication Checks
+				if items.contains_key(&name) {
+					return Err(error(DuplicateItem, format!("duplicate item {:?} in module", name))
+						.at(location..location))
+				}
+				items.insert(name, (visibility, declaration));
+
+				Ok(items)
+			})?;
+
+		Ok(Self {items})
+	}
+
+	/// The signature of the function at `path`, from the crate root.
+	pub fn signature(&self, path: &[IStr<'s>]) -> &Signature<'s> {
+		let (name, module) = path.split_last()
+			.expect("function path should not be empty");
+		match self.module(module).items.get(name) {
+			Some((_, Declaration::Function(signature))) => signature,
+			_ => unreachable!("function path {:?} does not lead to a function", path)
+		}
+	}
+
+	fn module(&self, path: &[IStr<'s>]) -> &Declarations<'s> {
+		path.iter().fold(self, |module, name| match module.items.get(name) {
+			Some((_, Declaration::Module(module))) => module,
+			_ => unreachable!("module path {:?} does not lead to a module", path)
+		})
+	}
+}
+
+/// An import resolved to the item it ultimately names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution<'s> {
+	/// The path of the item from the crate root, including its own name.
+	pub path: Vec<IStr<'s>>,
+	pub kind: ItemKind
+}
+
+/// A module within a crate, from which paths are resolved.
+#[derive(Clone, Copy, Debug)]
+pub struct ModuleRef<'d, 's> {
+	root: &'d Declarations<'s>,
+	path: &'d [IStr<'s>]
+}
+
+impl<'d, 's> ModuleRef<'d, 's> {
+	pub fn new(root: &'d Declarations<'s>) -> Self {
+		Self {root, path: &[]}
+	}
+
+	/// A reference to the child module named by the last element of `path`,
+	/// which must extend this module's path by one.
+	pub fn enter(&self, path: &'d [IStr<'s>]) -> Self {
+		debug_assert!(path.starts_with(self.path) && path.len() == self.path.len() + 1);
+		Self {root: self.root, path}
+	}
+
+	pub fn path(&self) -> &'d [IStr<'s>] {
+		self.path
+	}
+
+	pub fn resolve(&self, path: &[PathSegment<'s>]) -> Result<Resolution<'s>, Error> {
+		resolve(self.root, self.path, path, &mut HashSet::new())
+	}
+
+	/// The signature of the function at `path`, from the crate root.
+	pub fn signature(&self, path: &[IStr<'s>]) -> &'d Signature<'s> {
+		self.root.signature(path)
+	}
+
+	/// The path from the crate root of the item a type named `name` at the top
+	/// level of the module at `module` refers to, or nothing if the module
+	/// declares nothing by that name, as built in types are not declared.
+	pub fn type_path(&self, module: &[IStr<'s>], name: &IStr<'s>)
+			-> Result<Option<Vec<IStr<'s>>>, Error> {
+		self.root.module(module).items.contains_key(name)
+			.then(|| resolve(self.root, module, &[PathSegment::Name(name.clone())],
+				&mut HashSet::new()))
+			.transpose()
+			.map(|resolution| resolution.map(|resolution| resolution.path))
+	}
+}
+
+fn resolve<'s>(root: &Declarations<'s>, from: &[IStr<'s>], path: &[PathSegment<'s>],
+		visiting: &mut HashSet<(Vec<IStr<'s>>, IStr<'s>)>) -> Result<Resolution<'s>, Error> {
+	let mut current = from.to_vec();
+	let written = || path.iter().join("::");
+
+	for (index, segment) in path.iter().enumerate() {
+		let last = index + 1 == path.len();
+
+		match segment {
+			PathSegment::Crate if index == 0 => current.clear(),
+			PathSegment::Current if index == 0 => (),
+			PathSegment::Super if path[..index].iter()
+					.all(|segment| *segment == PathSegment::Super) =>
+				if current.pop().is_none() {
+					return Err(error(TooManySupers,
+						format!("too many supers in path {:?}", written())))
+				},
+			PathSegment::Crate | PathSegment::Current | PathSegment::Super =>
+				return Err(error(MisplacedPathSegment,
+					format!("misplaced path segment `{}` in path {:?}", segment, written()))),
+
+			PathSegment::Name(name) => {
+				let (visibility, declaration) = root.module(&current).items.get(name)
+					.ok_or_else(|| error(UnresolvedName,
+						format!("unresolved name {:?} in path {:?}", name, written())))?;
+
+				// Visibility Checks
+				if *visibility == Visibility::Private && !from.starts_with(&current) {
+					return Err(error(PrivateItem,
+						format!("{:?} in path {:?} is private", name, written())))
+				}
+
+				let resolution = match declaration {
+					Declaration::Type => Resolution {
+						path: current.iter().cloned().chain([name.clone()]).collect(),
+						kind: ItemKind::Type
+					},
+					Declaration::Function(_) => Resolution {
+						path: current.iter().cloned().chain([name.clone()]).collect(),
+						kind: ItemKind::Function
+					},
+					Declaration::Module(_) => Resolution {
+						path: current.iter().cloned().chain([name.clone()]).collect(),
+						kind: ItemKind::Module
+					},
+
+					Declaration::Import(import) => {
+						// Import Cycle Checks
+						let key = (current.clone(), name.clone());
+						if !visiting.insert(key.clone()) {
+							return Err(error(CyclicImport,
+								format!("cyclic import of {:?} in path {:?}", name, written())))
+						}
+						let resolution = resolve(root, &current, import, visiting)?;
+						visiting.remove(&key);
+
+						resolution
+					}
+				};
+
+				match (last, resolution.kind) {
+					(true, _) => return Ok(resolution),
+					(false, ItemKind::Module) => current = resolution.path,
+					(false, _) => return Err(error(NotAModule,
+						format!("{:?} in path {:?} is not a module", name, written())))
+				}
+			}
+		}
+	}
+
+	Ok(Resolution {path: current, kind: ItemKind::Module})
+}
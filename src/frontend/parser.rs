@@ -1,56 +1,140 @@
-use super::tokenizer::Token;
-use std::iter::Peekable;
-
-#[derive(Debug)]
-pub struct Block(pub Vec<Statement>);
-
-#[derive(Debug)]
-pub enum Statement {
-	DataItem(DataItem),
-	FunctionItem(FunctionItem),
-	LetItem(LetItem),
-	Expression(Expression)
+use super::{braced, doc_comment, error::{error, Error, ErrorCode::*}, outer_doc_comment,
+	tokenizer::{Location, Token}, IStr};
+use itertools::Itertools;
+use serde::Serialize;
+use std::{borrow::Cow, fmt::{Debug, Display, Formatter, Result as FMTResult},
+	iter::Peekable, ops::Range, path::PathBuf};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Block<'s>(pub Vec<Statement<'s>>);
+
+#[derive(Clone, Debug, Serialize)]
+pub enum Statement<'s> {
+	DataItem(Header<'s>, DataItem<'s>),
+	FunctionItem(Header<'s>, FunctionItem<'s>),
+	ModItem(Header<'s>, ModItem<'s>),
+	UseItem(Header<'s>, UseItem<'s>),
+	ExternItem(ExternItem<'s>),
+	LetItem(Header<'s>, LetItem<'s>),
+	/// An inner doc comment, documenting the item the block belongs to.
+	Documentation(Cow<'s, str>),
+	/// An expression evaluated for its effects, such as a call to a function
+	/// that returns nothing.
+	Expression(Location, Expression<'s>)
 }
 
-impl Statement {
-	pub fn data_item_ref(&self) -> Option<&DataItem> {
+impl<'s> Statement<'s> {
+	pub fn header(&self) -> Option<&Header<'s>> {
 		match self {
-			Self::DataItem(item) => Some(item),
+			Self::DataItem(header, _) | Self::FunctionItem(header, _)
+				| Self::ModItem(header, _) | Self::UseItem(header, _)
+				| Self::LetItem(header, _) => Some(header),
 			_ => None
 		}
 	}
 
-	pub fn function_item_ref(&self) -> Option<&FunctionItem> {
+	pub fn visibility(&self) -> Visibility {
+		self.header()
+			.map(|header| header.visibility)
+			.unwrap_or_default()
+	}
+
+	pub fn documentation(&self) -> Option<&str> {
+		self.header()?.documentation.as_deref()
+	}
+
+	pub fn location(&self) -> Location {
+		match self {
+			Self::Expression(location, _) => *location,
+			_ => self.header()
+				.map(|header| header.location)
+				.unwrap_or_default()
+		}
+	}
+
+	pub fn data_item_ref(&self) -> Option<&DataItem<'s>> {
+		match self {
+			Self::DataItem(_, item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn function_item_ref(&self) -> Option<&FunctionItem<'s>> {
+		match self {
+			Self::FunctionItem(_, item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn mod_item_ref(&self) -> Option<&ModItem<'s>> {
+		match self {
+			Self::ModItem(_, item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn use_item_ref(&self) -> Option<&UseItem<'s>> {
+		match self {
+			Self::UseItem(_, item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn extern_item_ref(&self) -> Option<&ExternItem<'s>> {
 		match self {
-			Self::FunctionItem(item) => Some(item),
+			Self::ExternItem(item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn let_item_ref(&self) -> Option<&LetItem<'s>> {
+		match self {
+			Self::LetItem(_, item) => Some(item),
+			_ => None
+		}
+	}
+
+	pub fn expression_ref(&self) -> Option<&Expression<'s>> {
+		match self {
+			Self::Expression(_, expression) => Some(expression),
 			_ => None
 		}
 	}
 }
 
-#[derive(Debug)]
-pub enum Expression {
-	Block(Block),
-	LiteralInteger(Box<str>),
+#[derive(Clone, Debug, Serialize)]
+pub enum Expression<'s> {
+	Block(Block<'s>),
+	LiteralInteger {
+		value: u128,
+		suffix: Option<IStr<'s>>
+	},
+	LiteralFloat {
+		value: Cow<'s, str>,
+		suffix: Option<IStr<'s>>
+	},
 	LiteralBoolean(bool),
+	LiteralString(Cow<'s, str>),
+	LiteralCharacter(char),
+	Identifier(IStr<'s>),
 
 	FunctionCall {
-		name: Box<str>,
-		arguments: Vec<Expression>
+		name: IStr<'s>,
+		arguments: Vec<Expression<'s>>
 	}
 }
 
-#[derive(Clone, Debug)]
-pub enum DataItem {
-	Single(DataVariant),
+#[derive(Clone, Debug, Serialize)]
+pub enum DataItem<'s> {
+	Single(DataVariant<'s>),
 	Multiple {
-		name: Box<str>,
-		variants: Vec<DataVariant>
+		name: IStr<'s>,
+		variants: Vec<DataVariant<'s>>
 	}
 }
 
-impl DataItem {
-	pub fn name(&self) -> &str {
+impl<'s> DataItem<'s> {
+	pub fn name(&self) -> &IStr<'s> {
 		match self {
 			Self::Single(variant) => variant.name(),
 			Self::Multiple {name, ..} => name
@@ -58,62 +142,305 @@ impl DataItem {
 	}
 }
 
-#[derive(Clone, Debug)]
-pub enum DataVariant {
+#[derive(Clone, Debug, Serialize)]
+pub enum DataVariant<'s> {
 	Marker {
-		name: Box<str>
+		name: IStr<'s>
 	},
 	Tuple {
-		name: Box<str>,
-		fields: Vec<Box<str>>
+		name: IStr<'s>,
+		fields: Vec<IStr<'s>>
 	},
 	Struct {
-		name: Box<str>,
-		fields: Vec<(Box<str>, Box<str>)>
+		name: IStr<'s>,
+		fields: Vec<(IStr<'s>, IStr<'s>)>
+	}
+}
+
+impl<'s> DataVariant<'s> {
+	pub fn name(&self) -> &IStr<'s> {
+		match self {
+			Self::Marker {name} => name,
+			Self::Tuple {name, ..} => name,
+			Self::Struct {name, ..} => name
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionItem<'s> {
+	pub name: IStr<'s>,
+	pub arguments: Vec<(IStr<'s>, IStr<'s>)>,
+	pub r#return: Option<IStr<'s>>,
+	pub body: Block<'s>
+}
+
+/// A block of bindings to items defined outside of rustscript, such as
+/// `extern "js" from "node:fs" {fn readFileSync(path: str) -> str;}`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExternItem<'s> {
+	pub abi: Cow<'s, str>,
+	/// The module the items are imported from, if they are not globals.
+	pub from: Option<Cow<'s, str>>,
+	pub items: Vec<(Header<'s>, ForeignItem<'s>)>
+}
+
+/// An item within an extern block. Each may be given the name it has on the
+/// other side, as in `fn log(message: str) = "console.log";`, and otherwise
+/// shares its rustscript name.
+#[derive(Clone, Debug, Serialize)]
+pub enum ForeignItem<'s> {
+	Function {
+		name: IStr<'s>,
+		arguments: Vec<(IStr<'s>, IStr<'s>)>,
+		r#return: Option<IStr<'s>>,
+		link_name: Option<Cow<'s, str>>
+	},
+	Type {
+		name: IStr<'s>,
+		link_name: Option<Cow<'s, str>>
+	},
+	Global {
+		name: IStr<'s>,
+		r#type: IStr<'s>,
+		link_name: Option<Cow<'s, str>>
+	}
+}
+
+impl<'s> ForeignItem<'s> {
+	pub fn name(&self) -> &IStr<'s> {
+		match self {
+			Self::Function {name, ..} => name,
+			Self::Type {name, ..} => name,
+			Self::Global {name, ..} => name
+		}
+	}
+
+	/// The name of the item on the other side.
+	pub fn link_name(&self) -> &str {
+		match self {
+			Self::Function {name, link_name, ..} | Self::Type {name, link_name}
+				| Self::Global {name, link_name, ..} => link_name.as_deref().unwrap_or(name)
+		}
+	}
+}
+
+/// A module declaration. `mod name;` is parsed without a body, which is filled
+/// in once the module's file has been loaded.
+#[derive(Clone, Debug, Serialize)]
+pub struct ModItem<'s> {
+	pub name: IStr<'s>,
+	pub body: Option<Block<'s>>,
+	/// The file the body was loaded from, if it was not written inline.
+	pub file: Option<PathBuf>
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UseItem<'s> {
+	pub path: Vec<PathSegment<'s>>
+}
+
+impl<'s> UseItem<'s> {
+	/// The name this import is bound to.
+	pub fn name(&self) -> &IStr<'s> {
+		match self.path.last() {
+			Some(PathSegment::Name(name)) => name,
+			_ => panic!("use path {:?} does not end in a name", self.path)
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum PathSegment<'s> {
+	Crate,
+	Super,
+	Current,
+	Name(IStr<'s>)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum Visibility {
+	#[default]
+	Private,
+	Public
+}
+
+/// Everything written before an item's keyword.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Header<'s> {
+	/// The item's outer doc comments, joined by newlines.
+	pub documentation: Option<Cow<'s, str>>,
+	pub visibility: Visibility,
+	/// Where the item begins, after its doc comments.
+	pub location: Location
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LetItem<'s> {
+	pub name: IStr<'s>,
+	pub r#type: IStr<'s>,
+	pub expression: Expression<'s>
+}
+
+/// Writes a block as source. Items are separated by blank lines, except within
+/// runs of imports or variables.
+impl Display for Block<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		self.0.iter().enumerate().try_for_each(|(index, statement)| {
+			match index.checked_sub(1).map(|previous| (&self.0[previous], statement)) {
+				None => (),
+				Some((Statement::UseItem(..), Statement::UseItem(..))
+					| (Statement::LetItem(..) | Statement::Expression(..),
+						Statement::LetItem(..) | Statement::Expression(..))) => writeln!(f)?,
+				Some(_) => write!(f, "\n\n")?
+			}
+			write!(f, "{}", statement)
+		})
 	}
 }
 
-impl DataVariant {
-	pub fn name(&self) -> &str {
+impl Display for Statement<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		if let Some(header) = self.header() {
+			write!(f, "{}", outer_doc_comment(header.documentation.as_deref()))?;
+			if header.visibility == Visibility::Public {write!(f, "pub ")?}
+		}
+
 		match self {
-			Self::Marker {name} => &name,
-			Self::Tuple {name, ..} => &name,
-			Self::Struct {name, ..} => &name
+			Self::DataItem(_, data) => write!(f, "data {}", data),
+			Self::FunctionItem(_, function) => write!(f, "fn {}{} {}", function.name,
+				signature(&function.arguments, &function.r#return),
+				braced(&function.body.to_string())),
+			Self::ModItem(_, ModItem {name, body: None, ..}) => write!(f, "mod {};", name),
+			Self::ModItem(_, ModItem {name, body: Some(body), ..}) =>
+				write!(f, "mod {} {}", name, braced(&body.to_string())),
+			Self::UseItem(_, r#use) => write!(f, "use {};", r#use.path.iter().join("::")),
+			Self::ExternItem(r#extern) => {
+				write!(f, "extern {:?} ", r#extern.abi)?;
+				if let Some(from) = &r#extern.from {write!(f, "from {:?} ", from)?}
+				write!(f, "{}", braced(&r#extern.items.iter()
+					.map(|(header, item)| format!("{}{}{};",
+						outer_doc_comment(header.documentation.as_deref()),
+						if header.visibility == Visibility::Public {"pub "} else {""},
+						item))
+					.join("\n")))
+			},
+			Self::LetItem(_, variable) => write!(f, "let {}: {} = {};",
+				variable.name, variable.r#type, variable.expression),
+			Self::Documentation(text) => write!(f, "{}", doc_comment("//!", text)),
+			Self::Expression(_, expression) => write!(f, "{};", expression)
 		}
 	}
 }
 
-#[derive(Debug)]
-pub struct FunctionItem {
-	pub name: Box<str>,
-	pub arguments: Vec<(Box<str>, Box<str>)>,
-	pub body: Block
+impl Display for DataItem<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Single(variant @ DataVariant::Struct {..}) => write!(f, "{}", variant),
+			Self::Single(variant) => write!(f, "{};", variant),
+			Self::Multiple {name, variants} =>
+				write!(f, "{} {}", name, braced(&variants.iter().join(",\n")))
+		}
+	}
 }
 
-#[derive(Debug)]
-pub struct LetItem {
-	pub name: Box<str>,
-	pub r#type: Box<str>,
-	pub expression: Expression
+impl Display for DataVariant<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Marker {name} => write!(f, "{}", name),
+			Self::Tuple {name, fields} => write!(f, "{}({})", name, fields.iter().join(", ")),
+			Self::Struct {name, fields} => write!(f, "{} {}", name, braced(&fields.iter()
+				.map(|(name, r#type)| format!("{}: {}", name, r#type))
+				.join(",\n")))
+		}
+	}
 }
 
-pub struct Parser<I>(pub Peekable<I>)
-	where I: Iterator<Item = Token>;
+impl Display for ForeignItem<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Function {name, arguments, r#return, ..} =>
+				write!(f, "fn {}{}", name, signature(arguments, r#return))?,
+			Self::Type {name, ..} => write!(f, "data {}", name)?,
+			Self::Global {name, r#type, ..} => write!(f, "let {}: {}", name, r#type)?
+		}
+
+		match self {
+			Self::Function {link_name: Some(link_name), ..}
+				| Self::Type {link_name: Some(link_name), ..}
+				| Self::Global {link_name: Some(link_name), ..} =>
+				write!(f, " = {:?}", link_name),
+			_ => Ok(())
+		}
+	}
+}
+
+impl Display for PathSegment<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Crate => write!(f, "crate"),
+			Self::Super => write!(f, "super"),
+			Self::Current => write!(f, "self"),
+			Self::Name(name) => write!(f, "{}", name)
+		}
+	}
+}
+
+impl Display for Expression<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Block(block) => write!(f, "{}", braced(&block.to_string())),
+			Self::LiteralInteger {value, suffix} =>
+				write!(f, "{}{}", value, suffix.as_deref().unwrap_or_default()),
+			Self::LiteralFloat {value, suffix} =>
+				write!(f, "{}{}", value, suffix.as_deref().unwrap_or_default()),
+			Self::LiteralBoolean(boolean) => write!(f, "{}", boolean),
+			Self::LiteralString(string) => write!(f, "{:?}", string),
+			Self::LiteralCharacter(character) => write!(f, "{:?}", character),
+			Self::Identifier(name) => write!(f, "{}", name),
+			Self::FunctionCall {name, arguments} =>
+				write!(f, "{}({})", name, arguments.iter().join(", "))
+		}
+	}
+}
+
+/// Writes a function's arguments and return type.
+fn signature(arguments: &[(IStr, IStr)], r#return: &Option<IStr>) -> String {
+	format!("({}){}",
+		arguments.iter().map(|(name, r#type)| format!("{}: {}", name, r#type)).join(", "),
+		r#return.as_ref().map(|r#type| format!(" -> {}", r#type)).unwrap_or_default())
+}
 
-impl<I> Parser<I>
-		where I: Iterator<Item = Token> {
+pub struct Parser<I>
+		where I: Iterator {
+	tokens: Peekable<I>,
+	/// The span of the last token eaten, or the end of the file once every
+	/// token has been.
+	span: Range<Location>
+}
+
+impl<'s, I> Parser<I>
+		where I: Iterator<Item = Result<(Range<Location>, Token<'s>), Error>> {
 	pub fn new(iterator: I) -> Self {
-		Self(iterator.peekable())
+		Self {tokens: iterator.peekable(), span: Location::default()..Location::default()}
+	}
+
+	/// Checks that every token has been parsed.
+	pub fn parse_end(&mut self) -> Result<(), Error> {
+		match self.peek()? {
+			Some(_) => Err(self.unexpected_next("the end of the input")),
+			None => Ok(())
+		}
 	}
 }
 
-impl<I> Parser<I>
-		where I: Iterator<Item = Token> {
+impl<'s, I> Parser<I>
+		where I: Iterator<Item = Result<(Range<Location>, Token<'s>), Error>> {
 	/// Eats a token, disposing of it.
 	fn eat(&mut self) {
 		match self.next() {
-			Some(_) => (),
-			None => unreachable!("called eat when there wasn't anything next")
+			Ok(Some(_)) => (),
+			_ => unreachable!("called eat when there wasn't anything next")
 		}
 	}
 
@@ -125,82 +452,353 @@ impl<I> Parser<I>
 	}
 
 	#[must_use = "all tokens should be consumed"]
-	fn eat_identifier(&mut self) -> Box<str> {
-		match self.next() {
-			Some(Token::Identifier(name)) => name,
-			Some(_) => unreachable!("called eat_identifier when an identifier wasn't next"),
-			None => unreachable!("called eat_identifier when there wasn't anything next")
+	fn eat_identifier(&mut self) -> Result<IStr<'s>, Error> {
+		match self.next()? {
+			Some(Token::Identifier(name)) => Ok(name),
+			token => Err(self.unexpected("an identifier", token))
 		}
 	}
 
 	#[must_use = "all tokens should be consumed"]
-	fn eat_literal_number(&mut self) -> Box<str> {
+	fn eat_literal_string(&mut self) -> Result<Cow<'s, str>, Error> {
+		match self.next()? {
+			Some(Token::LiteralString(string)) => Ok(string),
+			token => Err(self.unexpected("a string", token))
+		}
+	}
+
+	/// Eats a token, which must be `expected`.
+	fn expect(&mut self, expected: Token<'s>) -> Result<(), Error> {
+		match self.next()? {
+			Some(token) if token == expected => Ok(()),
+			token => Err(self.unexpected(format_args!("`{}`", expected), token))
+		}
+	}
+
+	/// Returns the next token, if any.
+	fn next(&mut self) -> Result<Option<Token<'s>>, Error> {
+		Ok(match self.tokens.next().transpose()? {
+			Some((span, token)) => {
+				self.span = span;
+				Some(token)
+			},
+			None => {
+				self.span = self.span.end..self.span.end;
+				None
+			}
+		})
+	}
+
+	fn peek(&mut self) -> Result<Option<&Token<'s>>, Error> {
+		match self.tokens.peek() {
+			Some(Ok((_, token))) => Ok(Some(token)),
+			Some(Err(error)) => Err(error.clone()),
+			None => Ok(None)
+		}
+	}
+
+	/// The location of the next token, or the default location at the end.
+	fn location(&mut self) -> Location {
+		match self.tokens.peek() {
+			Some(Ok((span, _))) => span.start,
+			_ => Location::default()
+		}
+	}
+
+	/// Reports that `found`, the last token eaten (or the end of the file), was
+	/// found where `expected` should have been.
+	fn unexpected(&self, expected: impl Display, found: Option<Token>) -> Error {
+		let found = match found {
+			Some(found) => format!("`{}`", found),
+			None => "end of file".into()
+		};
+		error(UnexpectedToken, format!("expected {}, found {}", expected, found))
+			.at(self.span.clone())
+	}
+
+	/// Reports that the next token (or the end of the file) was found where
+	/// `expected` should have been, eating it.
+	fn unexpected_next(&mut self, expected: impl Display) -> Error {
 		match self.next() {
-			Some(Token::LiteralNumber(number)) => number,
-			Some(_) => unreachable!("called eat_identifier when an identifier wasn't next"),
-			None => unreachable!("called eat_identifier when there wasn't anything next")
+			Ok(found) => self.unexpected(expected, found),
+			Err(error) => error
 		}
 	}
 
-	/// Returns the next character, if any.
-	fn next(&mut self) -> Option<Token> {
-		self.0.next()
+	pub fn parse_block(&mut self) -> Result<Block<'s>, Error> {
+		match self.parse_statements(false)? {
+			(block, None) => Ok(block),
+			(_, Some(_)) => unreachable!("blocks are parsed without a final expression")
+		}
 	}
 
-	fn peek(&mut self) -> Option<&Token> {
-		self.0.peek()
+	/// Parses a block whose last statement may be an expression without its
+	/// semicolon, which is returned separately, as the REPL reads its input.
+	pub fn parse_block_with_expression(&mut self)
+			-> Result<(Block<'s>, Option<Expression<'s>>), Error> {
+		self.parse_statements(true)
 	}
 
-	pub fn parse_block(&mut self) -> Block {
+	fn parse_statements(&mut self, last: bool)
+			-> Result<(Block<'s>, Option<Expression<'s>>), Error> {
 		let mut statements = Vec::new();
 
 		loop {
-			statements.push(match self.peek() {
-				Some(Token::KeywordFn) =>
-					Statement::FunctionItem(self.parse_function()),
-				Some(Token::KeywordData) =>
-					Statement::DataItem(self.parse_data()),
-				Some(Token::KeywordLet) =>
-					Statement::LetItem(self.parse_let()),
-				_ => break Block(statements),
+			let documentation = self.parse_documentation()?;
+			let location = self.location();
+			statements.push(match self.peek()? {
+				Some(Token::InnerDocComment(_)) if documentation.is_none() =>
+					match self.next()? {
+						Some(Token::InnerDocComment(text)) => Statement::Documentation(text),
+						_ => unreachable!()
+					},
+				Some(Token::KeywordPub) => {
+					self.eat();
+					self.parse_item(Header {
+						documentation,
+						visibility: Visibility::Public,
+						location
+					})?
+				},
+				Some(Token::KeywordFn | Token::KeywordData | Token::KeywordMod
+					| Token::KeywordUse) =>
+					self.parse_item(Header {documentation, location, ..Default::default()})?,
+				// Doc comments on extern blocks themselves document nothing.
+				Some(Token::KeywordExtern) =>
+					Statement::ExternItem(self.parse_extern()?),
+				Some(Token::KeywordLet) => Statement::LetItem(
+					Header {documentation, location, ..Default::default()}, self.parse_let()?),
+				_ if documentation.is_some() =>
+					return Err(self.unexpected_next("an item after doc comment")),
+				Some(Token::BraceLeft | Token::Identifier(_) | Token::LiteralInteger {..}
+					| Token::LiteralFloat {..} | Token::LiteralTrue | Token::LiteralFalse
+					| Token::LiteralString(_) | Token::LiteralCharacter(_)) => {
+					let expression = self.parse_expression()?;
+					match (self.peek()?, &expression) {
+						(Some(Token::SemiColon), _) => self.eat(),
+						// Like items, blocks need no semicolon after them.
+						(_, Expression::Block(_)) => (),
+						(None, _) if last => break Ok((Block(statements), Some(expression))),
+						_ => self.expect(Token::SemiColon)?
+					}
+
+					Statement::Expression(location, expression)
+				},
+				_ => break Ok((Block(statements), None)),
 			})
 		}
 	}
 
-	pub fn parse_function(&mut self) -> FunctionItem {
-		assert_eq!(self.next(), Some(Token::KeywordFn));
-		let name = self.eat_identifier(); // CHECKS WHERE?
-		assert_eq!(self.next(), Some(Token::ParenLeft));
-		assert_eq!(self.next(), Some(Token::ParenRight));
+	/// Parses the outer doc comments before an item, if there are any.
+	fn parse_documentation(&mut self) -> Result<Option<Cow<'s, str>>, Error> {
+		let mut lines = Vec::new();
+		while let Some(Token::DocComment(_)) = self.peek()? {
+			match self.next()? {
+				Some(Token::DocComment(text)) => lines.push(text),
+				_ => unreachable!()
+			}
+		}
 
-		assert_eq!(self.next(), Some(Token::BraceLeft));
-		let body = self.parse_block();
-		assert_eq!(self.next(), Some(Token::BraceRight));
+		Ok(match lines.len() {
+			0 | 1 => lines.pop(),
+			_ => Some(lines.join("\n").into())
+		})
+	}
 
-		FunctionItem {name, arguments: Vec::new(), body}
+	pub fn parse_item(&mut self, header: Header<'s>) -> Result<Statement<'s>, Error> {
+		Ok(match self.peek()? {
+			Some(Token::KeywordFn) =>
+				Statement::FunctionItem(header, self.parse_function()?),
+			Some(Token::KeywordData) =>
+				Statement::DataItem(header, self.parse_data()?),
+			Some(Token::KeywordMod) =>
+				Statement::ModItem(header, self.parse_mod()?),
+			Some(Token::KeywordUse) =>
+				Statement::UseItem(header, self.parse_use()?),
+			_ => return Err(self.unexpected_next("an item"))
+		})
 	}
 
-	pub fn parse_data(&mut self) -> DataItem {
-		assert_eq!(self.next(), Some(Token::KeywordData));
-		let name = self.eat_identifier();
+	pub fn parse_mod(&mut self) -> Result<ModItem<'s>, Error> {
+		self.expect(Token::KeywordMod)?;
+		let name = self.eat_identifier()?;
 
-		match self.next() {
+		Ok(match self.next()? {
+			// External Module
+			Some(Token::SemiColon) => ModItem {name, body: None, file: None},
+
+			// Inline Module
+			Some(Token::BraceLeft) => {
+				let body = self.parse_block()?;
+				self.expect(Token::BraceRight)?;
+
+				ModItem {name, body: Some(body), file: None}
+			},
+
+			token => return Err(self.unexpected("`;` or `{`", token))
+		})
+	}
+
+	pub fn parse_use(&mut self) -> Result<UseItem<'s>, Error> {
+		self.expect(Token::KeywordUse)?;
+		let mut path = Vec::new();
+
+		loop {
+			path.push(match self.next()? {
+				Some(Token::Identifier(name)) => PathSegment::Name(name),
+				Some(Token::KeywordCrate) => PathSegment::Crate,
+				Some(Token::KeywordSuper) => PathSegment::Super,
+				Some(Token::KeywordSelf) => PathSegment::Current,
+				token => return Err(self.unexpected("a path segment", token))
+			});
+
+			match self.next()? {
+				Some(Token::DoubleColon) => (),
+				Some(Token::SemiColon) => match path.last() {
+					Some(PathSegment::Name(_)) => break Ok(UseItem {path}),
+					_ => return Err(self.unexpected("a name", Some(Token::SemiColon)))
+				},
+				token => return Err(self.unexpected("`::` or `;`", token))
+			}
+		}
+	}
+
+	pub fn parse_function(&mut self) -> Result<FunctionItem<'s>, Error> {
+		let (name, arguments, r#return) = self.parse_function_signature()?;
+
+		self.expect(Token::BraceLeft)?;
+		let body = self.parse_block()?;
+		self.expect(Token::BraceRight)?;
+
+		Ok(FunctionItem {name, arguments, r#return, body})
+	}
+
+	/// Parses everything in a function up to its body.
+	#[allow(clippy::type_complexity)]
+	fn parse_function_signature(&mut self)
+			-> Result<(IStr<'s>, Vec<(IStr<'s>, IStr<'s>)>, Option<IStr<'s>>), Error> {
+		self.expect(Token::KeywordFn)?;
+		let name = self.eat_identifier()?; // CHECKS WHERE?
+		self.expect(Token::ParenLeft)?;
+
+		let mut arguments = Vec::new();
+		loop {
+			if let Some(Token::ParenRight) = self.peek()? {
+				self.eat();
+				break
+			}
+
+			let name = self.eat_identifier()?;
+			self.expect(Token::Colon)?;
+			let r#type = self.eat_identifier()?;
+			arguments.push((name, r#type));
+
+			match self.next()? {
+				Some(Token::Comma) => (),
+				Some(Token::ParenRight) => break,
+				token => return Err(self.unexpected("`,` or `)`", token))
+			}
+		}
+
+		let r#return = match self.peek()? {
+			Some(Token::ThinArrow) => {
+				self.eat();
+				Some(self.eat_identifier()?)
+			},
+			_ => None
+		};
+
+		Ok((name, arguments, r#return))
+	}
+
+	pub fn parse_extern(&mut self) -> Result<ExternItem<'s>, Error> {
+		self.expect(Token::KeywordExtern)?;
+		let abi = self.eat_literal_string()?;
+		let from = match self.peek()? {
+			Some(Token::Identifier(from)) if &**from == "from" => {
+				self.eat();
+				Some(self.eat_literal_string()?)
+			},
+			_ => None
+		};
+		self.expect(Token::BraceLeft)?;
+
+		let mut items = Vec::new();
+		loop {
+			let documentation = self.parse_documentation()?;
+			let location = self.location();
+			let visibility = match self.peek()? {
+				Some(Token::BraceRight) if documentation.is_none() => {
+					self.eat();
+					break Ok(ExternItem {abi, from, items})
+				},
+				Some(Token::KeywordPub) => self.eat_return(Visibility::Public),
+				_ => Visibility::Private
+			};
+
+			let item = match self.peek()? {
+				Some(Token::KeywordFn) => {
+					let (name, arguments, r#return) = self.parse_function_signature()?;
+					let link_name = self.parse_link_name()?;
+					ForeignItem::Function {name, arguments, r#return, link_name}
+				},
+
+				Some(Token::KeywordData) => {
+					self.eat();
+					let name = self.eat_identifier()?;
+					let link_name = self.parse_link_name()?;
+					ForeignItem::Type {name, link_name}
+				},
+
+				Some(Token::KeywordLet) => {
+					self.eat();
+					let name = self.eat_identifier()?;
+					self.expect(Token::Colon)?;
+					let r#type = self.eat_identifier()?;
+					let link_name = self.parse_link_name()?;
+					ForeignItem::Global {name, r#type, link_name}
+				},
+
+				_ => return Err(self.unexpected_next("a foreign item"))
+			};
+
+			self.expect(Token::SemiColon)?;
+			items.push((Header {documentation, visibility, location}, item));
+		}
+	}
+
+	/// Parses the `= "name"` that may follow a foreign item.
+	fn parse_link_name(&mut self) -> Result<Option<Cow<'s, str>>, Error> {
+		Ok(match self.peek()? {
+			Some(Token::Equals) => {
+				self.eat();
+				Some(self.eat_literal_string()?)
+			},
+			_ => None
+		})
+	}
+
+	pub fn parse_data(&mut self) -> Result<DataItem<'s>, Error> {
+		self.expect(Token::KeywordData)?;
+		let name = self.eat_identifier()?;
+
+		Ok(match self.next()? {
 			// Struct or Enum
-			Some(Token::BraceLeft) => match self.next() {
-				Some(Token::Identifier(variant)) => match self.peek() {
+			Some(Token::BraceLeft) => match self.next()? {
+				Some(Token::Identifier(variant)) => match self.peek()? {
 					// Definitely a Struct
 					Some(Token::Colon) => {
 						self.eat();
-						let r#type = self.eat_identifier();
+						let r#type = self.eat_identifier()?;
 						let mut fields = vec![(variant, r#type)];
 
 						loop {
-							match self.next() {
+							match self.next()? {
 								// Field
 								Some(Token::Comma) => {
-									let name = self.eat_identifier();
-									assert_eq!(self.next(), Some(Token::Colon));
-									let r#type = self.eat_identifier();
+									let name = self.eat_identifier()?;
+									self.expect(Token::Colon)?;
+									let r#type = self.eat_identifier()?;
 
 									fields.push((name, r#type))
 								},
@@ -209,33 +807,33 @@ impl<I> Parser<I>
 								Some(Token::BraceRight) =>
 									break DataItem::Single(DataVariant::Struct {name, fields}),
 
-								_ => unimplemented!()
+								token => return Err(self.unexpected("`,` or `}`", token))
 							}
 						}
 					},
 
 					// Definitely an Enum
 					_ => { // TODO: Fix this whole branch, it's crazy.
-						let variant = match self.next() {
+						let variant = match self.next()? {
 							// Struct
 							Some(Token::BraceLeft) => {
 								let mut fields = Vec::new();
 								loop {
-									if let Some(Token::BraceRight) = self.peek() {
+									if let Some(Token::BraceRight) = self.peek()? {
 										self.eat();
 										break DataVariant::Struct {name: variant, fields}
 									}
 
-									let name = self.eat_identifier();
-									assert_eq!(self.next(), Some(Token::Colon));
-									let r#type = self.eat_identifier();
+									let name = self.eat_identifier()?;
+									self.expect(Token::Colon)?;
+									let r#type = self.eat_identifier()?;
 									fields.push((name, r#type));
 
-									match self.next() {
+									match self.next()? {
 										Some(Token::Comma) => (),
 										Some(Token::BraceRight) =>
 											break DataVariant::Struct {name: variant, fields},
-										_ => unimplemented!()
+										token => return Err(self.unexpected("`,` or `}`", token))
 									}
 								}
 							},
@@ -244,18 +842,18 @@ impl<I> Parser<I>
 							Some(Token::ParenLeft) => {
 								let mut fields = Vec::new();
 								loop {
-									if let Some(Token::ParenRight) = self.peek() {
+									if let Some(Token::ParenRight) = self.peek()? {
 										self.eat();
 										break DataVariant::Tuple {name: variant, fields}
 									}
 
-									fields.push(self.eat_identifier());
+									fields.push(self.eat_identifier()?);
 
-									match self.next() {
+									match self.next()? {
 										Some(Token::Comma) => (),
 										Some(Token::ParenRight) =>
 											break DataVariant::Tuple {name: variant, fields},
-										_ => unimplemented!()
+										token => return Err(self.unexpected("`,` or `)`", token))
 									}
 								}
 							},
@@ -263,38 +861,39 @@ impl<I> Parser<I>
 							// Marker
 							Some(Token::Colon) => DataVariant::Marker {name: variant},
 
-							_ => unimplemented!()
+							token => return Err(self.unexpected("a variant body", token))
 						};
 						let mut variants = vec![variant];
 
-						match self.next() {
+						match self.next()? {
 							Some(Token::Comma) => (),
 							Some(Token::BraceRight) =>
-								return DataItem::Multiple {name, variants}, // Ew!
-							_ => unimplemented!()
+								return Ok(DataItem::Multiple {name, variants}), // Ew!
+							token => return Err(self.unexpected("`,` or `}`", token))
 						}
 						loop {
-							let variant = self.eat_identifier();
-							variants.push(match self.next() {
+							let variant = self.eat_identifier()?;
+							variants.push(match self.next()? {
 								// Struct
 								Some(Token::BraceLeft) => {
 									let mut fields = Vec::new();
 									loop {
-										if let Some(Token::BraceRight) = self.peek() {
+										if let Some(Token::BraceRight) = self.peek()? {
 											self.eat();
 											break DataVariant::Struct {name: variant, fields}
 										}
 
-										let name = self.eat_identifier();
-										assert_eq!(self.next(), Some(Token::Colon));
-										let r#type = self.eat_identifier();
+										let name = self.eat_identifier()?;
+										self.expect(Token::Colon)?;
+										let r#type = self.eat_identifier()?;
 										fields.push((name, r#type));
 
-										match self.next() {
+										match self.next()? {
 											Some(Token::Comma) => (),
 											Some(Token::ParenRight) =>
 												break DataVariant::Struct {name: variant, fields},
-											_ => unimplemented!()
+											token =>
+												return Err(self.unexpected("`,` or `}`", token))
 										}
 									}
 								},
@@ -303,18 +902,19 @@ impl<I> Parser<I>
 								Some(Token::ParenLeft) => {
 									let mut fields = Vec::new();
 									loop {
-										if let Some(Token::ParenRight) = self.peek() {
+										if let Some(Token::ParenRight) = self.peek()? {
 											self.eat();
 											break DataVariant::Tuple {name: variant, fields}
 										}
 
-										fields.push(self.eat_identifier());
+										fields.push(self.eat_identifier()?);
 
-										match self.next() {
+										match self.next()? {
 											Some(Token::Comma) => (),
 											Some(Token::ParenRight) =>
 												break DataVariant::Tuple {name: variant, fields},
-											_ => unimplemented!()
+											token =>
+												return Err(self.unexpected("`,` or `)`", token))
 										}
 									}
 								},
@@ -322,14 +922,14 @@ impl<I> Parser<I>
 								// Marker
 								Some(Token::Colon) => DataVariant::Marker {name: variant},
 
-								s => unimplemented!("{:?}", s)
+								token => return Err(self.unexpected("a variant body", token))
 							});
 
-							match self.next() {
+							match self.next()? {
 								Some(Token::Comma) => (),
 								Some(Token::BraceRight) =>
 									break DataItem::Multiple {name, variants},
-								_ => unimplemented!()
+								token => return Err(self.unexpected("`,` or `}`", token))
 							}
 						}
 					}
@@ -340,27 +940,27 @@ impl<I> Parser<I>
 				Some(Token::BraceRight) =>
 					DataItem::Multiple {name, variants: Vec::new()},
 
-				_ => unimplemented!()
+				token => return Err(self.unexpected("a field or variant", token))
 			},
 
 			// Tuple Struct
 			Some(Token::ParenLeft) => {
 				let mut fields = Vec::new();
 				loop {
-					if let Some(Token::ParenRight) = self.peek() {
+					if let Some(Token::ParenRight) = self.peek()? {
 						self.eat();
 						break DataItem::Single(DataVariant::Tuple {name, fields})
 					}
 
-					fields.push(self.eat_identifier());
+					fields.push(self.eat_identifier()?);
 
-					match self.next() {
+					match self.next()? {
 						Some(Token::Comma) => (),
 						Some(Token::ParenRight) => {
-							assert_eq!(self.next(), Some(Token::SemiColon));
+							self.expect(Token::SemiColon)?;
 							break DataItem::Single(DataVariant::Tuple {name, fields})
 						},
-						_ => unimplemented!()
+						token => return Err(self.unexpected("`,` or `)`", token))
 					}
 				}
 			},
@@ -369,57 +969,146 @@ impl<I> Parser<I>
 			Some(Token::SemiColon) =>
 				DataItem::Single(DataVariant::Marker {name}),
 
-			_ => unimplemented!()
-		}
+			token => return Err(self.unexpected("`{`, `(` or `;`", token))
+		})
 	}
 
-	pub fn parse_let(&mut self) -> LetItem {
-		assert_eq!(self.next(), Some(Token::KeywordLet));
-		let name = self.eat_identifier();
-		assert_eq!(self.next(), Some(Token::Colon));
-		let r#type = self.eat_identifier();
-		assert_eq!(self.next(), Some(Token::Equals));
-		let expression = self.parse_expression();
-		assert_eq!(self.next(), Some(Token::SemiColon));
+	pub fn parse_let(&mut self) -> Result<LetItem<'s>, Error> {
+		self.expect(Token::KeywordLet)?;
+		let name = self.eat_identifier()?;
+		self.expect(Token::Colon)?;
+		let r#type = self.eat_identifier()?;
+		self.expect(Token::Equals)?;
+		let expression = self.parse_expression()?;
+		self.expect(Token::SemiColon)?;
 
-		LetItem {name, r#type, expression}
+		Ok(LetItem {name, r#type, expression})
 	}
 
-	pub fn parse_expression(&mut self) -> Expression {
-		match self.peek().unwrap() {
-			Token::BraceLeft => {
+	pub fn parse_expression(&mut self) -> Result<Expression<'s>, Error> {
+		Ok(match self.peek()? {
+			Some(Token::BraceLeft) => {
 				self.eat();
-				let block = self.parse_block();
-				assert_eq!(self.next(), Some(Token::BraceRight));
+				let block = self.parse_block()?;
+				self.expect(Token::BraceRight)?;
 
 				Expression::Block(block)
 			},
 
-			Token::LiteralNumber(_) =>
-				Expression::LiteralInteger(self.eat_literal_number()),
-			Token::LiteralTrue =>
+			Some(Token::LiteralInteger {..}) => match self.next()? {
+				Some(Token::LiteralInteger {value, suffix}) =>
+					Expression::LiteralInteger {value, suffix},
+				_ => unreachable!()
+			},
+			Some(Token::LiteralFloat {..}) => match self.next()? {
+				Some(Token::LiteralFloat {value, suffix}) =>
+					Expression::LiteralFloat {value, suffix},
+				_ => unreachable!()
+			},
+			Some(Token::LiteralTrue) =>
 				self.eat_return(Expression::LiteralBoolean(true)),
-			Token::LiteralFalse =>
+			Some(Token::LiteralFalse) =>
 				self.eat_return(Expression::LiteralBoolean(false)),
+			Some(Token::LiteralString(_)) =>
+				Expression::LiteralString(self.eat_literal_string()?),
+			Some(&Token::LiteralCharacter(character)) =>
+				self.eat_return(Expression::LiteralCharacter(character)),
+
+			Some(Token::Identifier(_)) => {
+				let actor = self.eat_identifier()?;
+				match self.peek()? {
+					Some(Token::ParenLeft) => {
+						self.eat();
 
-			Token::Identifier(_) => {
-				let actor = self.eat_identifier();
-				match self.peek().unwrap() {
-					Token::ParenLeft => {
-						// TODO: Arguments.
-						assert_eq!(self.next(), Some(Token::ParenRight));
+						let mut arguments = Vec::new();
+						loop {
+							if let Some(Token::ParenRight) = self.peek()? {
+								self.eat();
+								break
+							}
+
+							arguments.push(self.parse_expression()?);
 
-						Expression::FunctionCall {
-							name: actor,
-							arguments: Vec::new()
+							match self.next()? {
+								Some(Token::Comma) => (),
+								Some(Token::ParenRight) => break,
+								token => return Err(self.unexpected("`,` or `)`", token))
+							}
 						}
+
+						Expression::FunctionCall {name: actor, arguments}
 					},
 
-					_ => todo!("add identifier reference")
+					_ => Expression::Identifier(actor)
 				}
 			},
 
-			_ => unimplemented!()
-		}
+			_ => return Err(self.unexpected_next("an expression"))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::{error::ErrorCode, tokenizer::{Located, Location}}, Expression, Parser,
+		Statement};
+
+	#[test]
+	fn expression_statements_end_with_semicolons() {
+		let block = Parser::new(Located::new("f(); {} g(1, x);")).parse_block().unwrap();
+		assert!(block.0.iter().all(|statement| matches!(statement, Statement::Expression(..))));
+		assert_eq!(block.to_string(), "f();\n{};\ng(1, x);");
+
+		let error = Parser::new(Located::new("f()")).parse_block().unwrap_err();
+		assert_eq!(error.code, ErrorCode::UnexpectedToken);
+	}
+
+	#[test]
+	fn final_expression_may_omit_semicolon() {
+		let (block, expression) =
+			Parser::new(Located::new("let x: i32 = 1; f(x)")).parse_block_with_expression()
+				.unwrap();
+		assert_eq!(block.0.len(), 1);
+		assert!(matches!(expression, Some(Expression::FunctionCall {..})));
+	}
+
+	#[test]
+	fn errors_name_tokens_where_they_are() {
+		let at = |line, column| Location {line, column};
+		let error = |source| Parser::new(Located::new(source)).parse_block().unwrap_err();
+
+		let found = error("f();\nlet x: i32 = f.g;");
+		assert_eq!(found.message, "expected `;`, found `.`");
+		assert_eq!(found.span, Some(at(1, 14)..at(1, 15)));
+
+		let found = error("fn f(x: i32 {}");
+		assert_eq!(found.message, "expected `,` or `)`, found `{`");
+		assert_eq!(found.span, Some(at(0, 12)..at(0, 13)));
+
+		let found = error("let x: i32 =");
+		assert_eq!(found.message, "expected an expression, found end of file");
+		assert_eq!(found.span, Some(at(0, 12)..at(0, 12)));
+
+		// Errors in tokens are reported as they are.
+		assert_eq!(error("f(\"\\q\");").code, ErrorCode::InvalidEscape);
+	}
+
+	#[test]
+	fn doc_comments_document_what_follows_them() {
+		let block = Parser::new(Located::new("//! The module.\n\
+			/// First.\n/// Second.\npub fn f() {}\n/** A variable. */ let x: i32 = 1;"))
+			.parse_block().unwrap();
+		let documentation: Vec<_> = block.0.iter()
+			.map(|statement| match statement {
+				Statement::Documentation(text) => Some(&**text),
+				statement => statement.documentation()
+			})
+			.collect();
+		assert_eq!(documentation,
+			[Some(" The module."), Some(" First.\n Second."), Some(" A variable.")]);
+
+		// Only items may be documented.
+		let error = Parser::new(Located::new("/// Nothing.\nf();")).parse_block().unwrap_err();
+		assert_eq!(error.message, "expected an item after doc comment, found `f`");
 	}
 }
@@ -0,0 +1,54 @@
+//! Serializes the parts of the main representation that `serde` cannot derive:
+//! maps and sets keyed by name are sorted, so that the output is the same
+//! between runs.
+
+use super::{EnumVariantFormat, IStr};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+pub fn map<S, V>(map: &HashMap<IStr, V>, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer, V: Serialize {
+	serializer.collect_map(map.iter()
+		.map(|(name, value)| (&**name, value))
+		.collect::<BTreeMap<_, _>>())
+}
+
+/// Serializes a map from names to names, such as a struct's fields.
+pub fn name_map<S>(map: &HashMap<IStr, IStr>, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer {
+	serializer.collect_map(map.iter()
+		.map(|(name, value)| (&**name, &**value))
+		.collect::<BTreeMap<_, _>>())
+}
+
+pub fn set<S>(set: &HashSet<IStr>, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer {
+	serializer.collect_seq(set.iter()
+		.map(|name| &**name)
+		.collect::<BTreeSet<_>>())
+}
+
+/// The variants of a data format, which the variants themselves do not have.
+pub trait Variants {
+	fn serialize_variants<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer;
+}
+
+impl Variants for () {
+	fn serialize_variants<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+			where S: Serializer {
+		serializer.serialize_unit()
+	}
+}
+
+impl Variants for HashMap<IStr<'_>, EnumVariantFormat<'_>> {
+	fn serialize_variants<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+			where S: Serializer {
+		map(self, serializer)
+	}
+}
+
+pub fn variants<S, V>(variants: &V, serializer: S) -> Result<S::Ok, S::Error>
+		where S: Serializer, V: Variants {
+	variants.serialize_variants(serializer)
+}
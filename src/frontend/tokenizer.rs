@@ -1,17 +1,45 @@
-use std::iter::Peekable;
+use super::{error::{error, Error, ErrorCode::*}, IntegerType};
+use serde::Serialize;
+use std::{borrow::Cow, fmt::{Display, Formatter, Result as FMTResult}, ops::Range};
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Token {
-	Identifier(Box<str>),
+/// A token, borrowing its text from the source wherever the text appears there
+/// verbatim.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub enum Token<'s> {
+	Identifier(Cow<'s, str>),
 
 	KeywordFn,
 	KeywordData,
 	KeywordLet,
+	KeywordMod,
+	KeywordUse,
+	KeywordPub,
+	KeywordCrate,
+	KeywordSuper,
+	KeywordSelf,
+	KeywordExtern,
 
-	LiteralNumber(Box<str>),
+	LiteralInteger {
+		value: u128,
+		suffix: Option<Cow<'s, str>>
+	},
+	LiteralFloat {
+		/// The literal in decimal, without any `_` separators.
+		value: Cow<'s, str>,
+		suffix: Option<Cow<'s, str>>
+	},
+	LiteralString(Cow<'s, str>),
+	LiteralCharacter(char),
 	LiteralTrue,
 	LiteralFalse,
 
+	/// An outer doc comment (`///` or `/** */`), documenting the item after it.
+	DocComment(Cow<'s, str>),
+	/// An inner doc comment (`//!` or `/*! */`), documenting the item it is in.
+	InnerDocComment(Cow<'s, str>),
+
 	ParenLeft,
 	ParenRight,
 	BraceLeft,
@@ -24,23 +52,284 @@ pub enum Token {
 	Period,
 	Comma,
 	Colon,
+	DoubleColon,
 	SemiColon,
+	ThinArrow,
 
 	Equals
 }
 
-pub struct Tokenizer<I>(pub Peekable<I>)
-	where I: Iterator<Item = char>;
+impl Token<'_> {
+	/// A copy of the token that does not borrow from its source, for sources
+	/// that do not outlive what is parsed from them.
+	pub fn into_owned(self) -> Token<'static> {
+		let owned = |text: Cow<str>| Cow::Owned(text.into_owned());
+		match self {
+			Self::Identifier(name) => Token::Identifier(owned(name)),
+			Self::KeywordFn => Token::KeywordFn,
+			Self::KeywordData => Token::KeywordData,
+			Self::KeywordLet => Token::KeywordLet,
+			Self::KeywordMod => Token::KeywordMod,
+			Self::KeywordUse => Token::KeywordUse,
+			Self::KeywordPub => Token::KeywordPub,
+			Self::KeywordCrate => Token::KeywordCrate,
+			Self::KeywordSuper => Token::KeywordSuper,
+			Self::KeywordSelf => Token::KeywordSelf,
+			Self::KeywordExtern => Token::KeywordExtern,
+			Self::LiteralInteger {value, suffix} =>
+				Token::LiteralInteger {value, suffix: suffix.map(owned)},
+			Self::LiteralFloat {value, suffix} =>
+				Token::LiteralFloat {value: owned(value), suffix: suffix.map(owned)},
+			Self::LiteralString(string) => Token::LiteralString(owned(string)),
+			Self::LiteralCharacter(character) => Token::LiteralCharacter(character),
+			Self::LiteralTrue => Token::LiteralTrue,
+			Self::LiteralFalse => Token::LiteralFalse,
+			Self::DocComment(text) => Token::DocComment(owned(text)),
+			Self::InnerDocComment(text) => Token::InnerDocComment(owned(text)),
+			Self::ParenLeft => Token::ParenLeft,
+			Self::ParenRight => Token::ParenRight,
+			Self::BraceLeft => Token::BraceLeft,
+			Self::BraceRight => Token::BraceRight,
+			Self::BracketLeft => Token::BracketLeft,
+			Self::BracketRight => Token::BracketRight,
+			Self::ArrowLeft => Token::ArrowLeft,
+			Self::ArrowRight => Token::ArrowRight,
+			Self::Period => Token::Period,
+			Self::Comma => Token::Comma,
+			Self::Colon => Token::Colon,
+			Self::DoubleColon => Token::DoubleColon,
+			Self::SemiColon => Token::SemiColon,
+			Self::ThinArrow => Token::ThinArrow,
+			Self::Equals => Token::Equals
+		}
+	}
+}
+
+/// Writes a token as it could appear in source.
+impl Display for Token<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Identifier(name) => write!(f, "{}", name),
+
+			Self::KeywordFn => write!(f, "fn"),
+			Self::KeywordData => write!(f, "data"),
+			Self::KeywordLet => write!(f, "let"),
+			Self::KeywordMod => write!(f, "mod"),
+			Self::KeywordUse => write!(f, "use"),
+			Self::KeywordPub => write!(f, "pub"),
+			Self::KeywordCrate => write!(f, "crate"),
+			Self::KeywordSuper => write!(f, "super"),
+			Self::KeywordSelf => write!(f, "self"),
+			Self::KeywordExtern => write!(f, "extern"),
+
+			Self::LiteralInteger {value, suffix} =>
+				write!(f, "{}{}", value, suffix.as_deref().unwrap_or_default()),
+			Self::LiteralFloat {value, suffix} =>
+				write!(f, "{}{}", value, suffix.as_deref().unwrap_or_default()),
+			// Rust's escapes are also rustscript's.
+			Self::LiteralString(string) => write!(f, "{:?}", string),
+			Self::LiteralCharacter(character) => write!(f, "{:?}", character),
+			Self::LiteralTrue => write!(f, "true"),
+			Self::LiteralFalse => write!(f, "false"),
+
+			Self::DocComment(text) => text.split('\n')
+				.enumerate()
+				.try_for_each(|(index, line)| match index {
+					0 => write!(f, "///{}", line),
+					_ => write!(f, "\n///{}", line)
+				}),
+			Self::InnerDocComment(text) => text.split('\n')
+				.enumerate()
+				.try_for_each(|(index, line)| match index {
+					0 => write!(f, "//!{}", line),
+					_ => write!(f, "\n//!{}", line)
+				}),
+
+			Self::ParenLeft => write!(f, "("),
+			Self::ParenRight => write!(f, ")"),
+			Self::BraceLeft => write!(f, "{{"),
+			Self::BraceRight => write!(f, "}}"),
+			Self::BracketLeft => write!(f, "["),
+			Self::BracketRight => write!(f, "]"),
+			Self::ArrowLeft => write!(f, "<"),
+			Self::ArrowRight => write!(f, ">"),
+
+			Self::Period => write!(f, "."),
+			Self::Comma => write!(f, ","),
+			Self::Colon => write!(f, ":"),
+			Self::DoubleColon => write!(f, "::"),
+			Self::SemiColon => write!(f, ";"),
+			Self::ThinArrow => write!(f, "->"),
+
+			Self::Equals => write!(f, "=")
+		}
+	}
+}
+
+/// Splits source text into tokens, working in byte offsets into the source.
+pub struct Tokenizer<'s> {
+	source: &'s str,
+	offset: usize
+}
+
+impl<'s> Tokenizer<'s> {
+	pub fn new(source: &'s str) -> Self {
+		Self {source, offset: 0}
+	}
+
+	/// The byte offset of the next character in the source.
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the next token along with the range of bytes it was written in.
+	/// After an error, there are no more tokens.
+	pub fn next_spanned(&mut self) -> Result<Option<(Range<usize>, Token<'s>)>, Error> {
+		loop {
+			let next = match self.parse_whitespace() {
+				Some(next) => next,
+				None => break Ok(None)
+			};
+			let start = self.offset;
+			break match self.parse_token(next) {
+				Ok(Some(token)) => Ok(Some((start..self.offset, token))),
+				Ok(None) => continue,
+				Err(error) => {
+					let span = self.location(start)..self.location(self.offset);
+					self.offset = self.source.len();
+					Err(error.at(span))
+				}
+			}
+		}
+	}
+
+	/// Parses the token beginning with `next`, or `None` if it is an ordinary
+	/// comment.
+	fn parse_token(&mut self, next: char) -> Result<Option<Token<'s>>, Error> {
+		Ok(Some(match next {
+			'0'..='9' => self.parse_number()?,
+			character if character == '_' || is_xid_start(character) =>
+				self.parse_identifier()?,
+			'"' => self.parse_string()?,
+			'\'' => self.parse_character()?,
+			'/' => return self.parse_comment(),
+
+			'(' => self.eat_return(Token::ParenLeft),
+			')' => self.eat_return(Token::ParenRight),
+			'{' => self.eat_return(Token::BraceLeft),
+			'}' => self.eat_return(Token::BraceRight),
+			'[' => self.eat_return(Token::BracketLeft),
+			']' => self.eat_return(Token::BracketRight),
+			'<' => self.eat_return(Token::ArrowLeft),
+			'>' => self.eat_return(Token::ArrowRight),
+
+			'.' => self.eat_return(Token::Period),
+			',' => self.eat_return(Token::Comma),
+			':' => {
+				self.eat();
+				match self.peek() {
+					Some(':') => self.eat_return(Token::DoubleColon),
+					_ => Token::Colon
+				}
+			},
+			';' => self.eat_return(Token::SemiColon),
+
+			'=' => self.eat_return(Token::Equals),
+			'-' => {
+				self.eat();
+				match self.peek() {
+					Some('>') => self.eat_return(Token::ThinArrow),
+					character => return Err(self.unexpected(character))
+				}
+			},
+
+			character => return Err(self.unexpected(Some(character)))
+		}))
+	}
+
+	/// The location of the byte at `offset` in the source.
+	fn location(&self, offset: usize) -> Location {
+		Location::default().advance(&self.source[..offset])
+	}
+
+	/// Reports a character (or the end of the file) that cannot begin a token,
+	/// eating it so that the error spans it.
+	fn unexpected(&mut self, character: Option<char>) -> Error {
+		match character {
+			Some(character) => {
+				self.eat();
+				error(UnexpectedCharacter, format!("unexpected character {:?}", character))
+			},
+			None => error(UnexpectedCharacter, "unexpected end of file")
+		}
+	}
+}
+
+/// A position in source text. Lines and columns both count from zero, and
+/// columns are counted in UTF-16 code units, as source maps and editors count
+/// them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Location {
+	pub line: u32,
+	pub column: u32
+}
 
-impl<I> Tokenizer<I>
-		where I: Iterator<Item = char> {
-	pub fn new(iterator: I) -> Self {
-		Self(iterator.peekable())
+impl Location {
+	/// The location after `text` is written from this one.
+	pub fn advance(self, text: &str) -> Self {
+		match text.rfind('\n') {
+			Some(index) => Self {
+				line: self.line + text.matches('\n').count() as u32,
+				column: text[index + 1..].encode_utf16().count() as u32
+			},
+			None => Self {column: self.column + text.encode_utf16().count() as u32, ..self}
+		}
 	}
 }
 
-impl<I> Tokenizer<I>
-		where I: Iterator<Item = char> {
+/// Finds the locations of ranges of bytes in source text, which must be given
+/// in order.
+pub struct Locator<'s> {
+	source: &'s str,
+	/// The offset and location of the start of the last range located.
+	offset: usize,
+	location: Location
+}
+
+impl<'s> Locator<'s> {
+	pub fn new(source: &'s str) -> Self {
+		Self {source, offset: 0, location: Location::default()}
+	}
+
+	pub fn locate(&mut self, range: Range<usize>) -> Range<Location> {
+		self.location = self.location.advance(&self.source[self.offset..range.start]);
+		self.offset = range.start;
+		self.location..self.location.advance(&self.source[range])
+	}
+}
+
+/// Yields each token along with the locations it begins and ends at.
+pub struct Located<'s> {
+	tokenizer: Tokenizer<'s>,
+	locator: Locator<'s>
+}
+
+impl<'s> Located<'s> {
+	pub fn new(source: &'s str) -> Self {
+		Self {tokenizer: Tokenizer::new(source), locator: Locator::new(source)}
+	}
+}
+
+impl<'s> Iterator for Located<'s> {
+	type Item = Result<(Range<Location>, Token<'s>), Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		Some(self.tokenizer.next_spanned().transpose()?
+			.map(|(range, token)| (self.locator.locate(range), token)))
+	}
+}
+
+impl<'s> Tokenizer<'s> {
 	/// Eats a character, disposing of it.
 	#[allow(unused_must_use)] // Rationale: This is eat.
 	fn eat(&mut self) {
@@ -57,7 +346,9 @@ impl<I> Tokenizer<I>
 	/// Returns the next character, if any.
 	#[must_use = "all characters should be consumed, if you already peeked this, you should use `eat`"]
 	fn next(&mut self) -> Option<char> {
-		self.0.next()
+		let next = self.peek()?;
+		self.offset += next.len_utf8();
+		Some(next)
 	}
 
 	/// Returns the next character, assuming that the character was already
@@ -71,8 +362,16 @@ impl<I> Tokenizer<I>
 	}
 
 	/// Peeks the next character, if any.
-	fn peek(&mut self) -> Option<char> {
-		self.0.peek().map(Clone::clone)
+	fn peek(&self) -> Option<char> {
+		match self.source.as_bytes().get(self.offset)? {
+			byte if byte.is_ascii() => Some(*byte as char),
+			_ => self.source[self.offset..].chars().next()
+		}
+	}
+
+	/// The source from `start` up to the next character.
+	fn slice(&self, start: usize) -> &'s str {
+		&self.source[start..self.offset]
 	}
 
 	/// Parses and discards all whitespace, and returns the last peeked non
@@ -86,56 +385,545 @@ impl<I> Tokenizer<I>
 		}
 	}
 
-	fn parse_identifier(&mut self) -> Token {
-		let mut name = String::new();
-		while let Some('a'..='z' | 'A'..='Z' | '_') = self.peek()
-			{name.push(self.peeked_next())}
+	/// Parses a comment, starting at its first `/`. Returns the doc comment
+	/// token it is, or `None` for an ordinary comment, which is discarded.
+	fn parse_comment(&mut self) -> Result<Option<Token<'s>>, Error> {
+		self.eat();
+		match self.peek() {
+			// Line Comments
+			Some('/') => {
+				self.eat();
+				let kind: Option<fn(Cow<'s, str>) -> Token<'s>> = match self.peek() {
+					Some('/') => {
+						self.eat();
+						// `////` and beyond are ordinary comments.
+						match self.peek() {
+							Some('/') => None,
+							_ => Some(Token::DocComment)
+						}
+					},
+					Some('!') => self.eat_return(Some(Token::InnerDocComment)),
+					_ => None
+				};
+
+				let start = self.offset;
+				while !matches!(self.peek(), Some('\n') | None) {self.eat()}
+
+				Ok(kind.map(|kind| kind(self.slice(start).trim_end_matches('\r').into())))
+			},
+
+			// Block Comments
+			Some('*') => {
+				self.eat();
+				let kind: Option<fn(Cow<'s, str>) -> Token<'s>> = match self.peek() {
+					Some('*') => {
+						self.eat();
+						match self.peek() {
+							// `/**/` is an empty ordinary comment.
+							Some('/') => return self.eat_return(Ok(None)),
+							// `/***` and beyond are ordinary comments.
+							Some('*') => None,
+							_ => Some(Token::DocComment)
+						}
+					},
+					Some('!') => self.eat_return(Some(Token::InnerDocComment)),
+					_ => None
+				};
+
+				// Block comments nest, as they do in Rust.
+				let start = self.offset;
+				let mut depth = 1;
+				loop {
+					match self.next() {
+						Some('/') if self.peek() == Some('*') => {
+							self.eat();
+							depth += 1;
+						},
+						Some('*') if self.peek() == Some('/') => {
+							self.eat();
+							depth -= 1;
+							if depth == 0 {break}
+						},
+						Some(_) => (),
+						None => return Err(error(Unterminated, "unterminated block comment"))
+					}
+				}
+				let text = self.source[start..self.offset - 2].trim_end();
+
+				// Continuation lines commonly begin with a decorative `*`.
+				let text = match text.contains('\n') {
+					true => text.lines().enumerate()
+						.map(|(index, line)| match line.trim_start().strip_prefix('*') {
+							Some(line) if index > 0 => line,
+							_ => line
+						})
+						.collect::<Vec<_>>()
+						.join("\n")
+						.into(),
+					false => text.into()
+				};
+				Ok(kind.map(|kind| kind(text)))
+			},
+
+			character => Err(self.unexpected(character))
+		}
+	}
+
+	/// Parses an identifier, as defined by UAX #31 (with `_` allowed to begin
+	/// one). Identifiers are normalized to NFC, so that names which look the
+	/// same because they are canonically equivalent are the same name.
+	fn parse_identifier(&mut self) -> Result<Token<'s>, Error> {
+		let start = self.offset;
+		while self.peek().map(is_xid_continue).unwrap_or_default() {self.eat()}
+		let name = self.slice(start);
 
-		let name = Box::<str>::from(name);
-		match &*name {
+		// Raw Strings
+		if name == "r" && matches!(self.peek(), Some('"' | '#'))
+			{return self.parse_raw_string()}
+
+		let name: Cow<str> = match is_nfc_quick(name.chars()) {
+			IsNormalized::Yes => name.into(),
+			_ => name.nfc().collect::<String>().into()
+		};
+		Ok(match &*name {
 			"fn" => Token::KeywordFn,
 			"data" => Token::KeywordData,
 			"let" => Token::KeywordLet,
+			"mod" => Token::KeywordMod,
+			"use" => Token::KeywordUse,
+			"pub" => Token::KeywordPub,
+			"crate" => Token::KeywordCrate,
+			"super" => Token::KeywordSuper,
+			"self" => Token::KeywordSelf,
+			"extern" => Token::KeywordExtern,
 			"true" => Token::LiteralTrue,
 			"false" => Token::LiteralFalse,
 			_ => Token::Identifier(name)
+		})
+	}
+
+	fn parse_number(&mut self) -> Result<Token<'s>, Error> {
+		let start = self.offset;
+
+		// Base Prefixes
+		let mut radix = 10;
+		let mut digits = false;
+		if self.peek() == Some('0') {
+			self.eat();
+			match self.peek() {
+				Some('x') => radix = self.eat_return(16),
+				Some('o') => radix = self.eat_return(8),
+				Some('b') => radix = self.eat_return(2),
+				_ => digits = true
+			}
+		}
+
+		// Hexadecimal digits include letters, so only they may be followed by
+		// letters which are still part of the number rather than its suffix.
+		let mut value = Some(0u128);
+		while let Some(character) = self.peek() {
+			let digit = match character {
+				'_' => {
+					self.eat();
+					continue
+				},
+				'0'..='9' => self.eat_return(character),
+				'a'..='f' | 'A'..='F' if radix == 16 => self.eat_return(character),
+				_ => break
+			};
+
+			let digit = digit.to_digit(radix).ok_or_else(|| error(InvalidNumber,
+				format!("invalid digit {:?} in base {} literal", digit, radix)))?;
+			value = value
+				.and_then(|value| value.checked_mul(radix.into()))
+				.and_then(|value| value.checked_add(digit.into()));
+			digits = true;
+		}
+
+		if !digits
+			{return Err(error(InvalidNumber, format!("base {} literal has no digits", radix)))}
+
+		// Floating Point Literals
+		let mut float = false;
+		if radix == 10 && self.peek() == Some('.') {
+			self.eat();
+			float = true;
+
+			// As in Rust, a literal ending in a point may not be followed by an
+			// exponent or suffix, such as in `2.e3`.
+			if let Some(character) = self.peek().filter(|character| is_xid_continue(*character)
+					&& !character.is_ascii_digit()) {
+				return Err(error(InvalidNumber, format!(
+					"expected a digit after the point in `{}`, found {:?}",
+					self.slice(start), character)))
+			}
+			self.parse_decimal_digits();
+		}
+		if radix == 10 && matches!(self.peek(), Some('e' | 'E')) {
+			self.eat();
+			float = true;
+			if let Some('+' | '-') = self.peek() {self.eat()}
+
+			if !self.parse_decimal_digits()
+				{return Err(error(InvalidNumber,
+					format!("exponent of literal `{}` has no digits", self.slice(start))))}
+		}
+		let text = self.slice(start);
+
+		// Type Suffixes
+		let suffix = self.offset;
+		while let Some('a'..='z' | 'A'..='Z' | '0'..='9' | '_') = self.peek() {self.eat()}
+		let suffix = Some(self.slice(suffix)).filter(|suffix| !suffix.is_empty());
+
+		let literal_float = move || Token::LiteralFloat {
+			value: match text.contains('_') {
+				true => text.replace('_', "").into(),
+				false => text.into()
+			},
+			suffix: suffix.map(Into::into)
+		};
+		match suffix {
+			Some("f32" | "f64") if radix != 10 => Err(error(InvalidNumber,
+				format!("base {} float literals are not supported", radix))),
+			Some("f32" | "f64") => Ok(literal_float()),
+			None if float => Ok(literal_float()),
+
+			Some(suffix) if float => Err(error(InvalidNumber,
+				format!("invalid suffix `{}` for float literal", suffix))),
+			Some(suffix) if IntegerType::from_name(suffix).is_none() => Err(error(InvalidNumber,
+				format!("invalid suffix `{}` for number literal", suffix))),
+			_ => Ok(Token::LiteralInteger {
+				value: value.ok_or_else(||
+					error(IntegerTooLarge, format!("integer literal `{}` is too large", text)))?,
+				suffix: suffix.map(Into::into)
+			})
 		}
 	}
 
-	fn parse_number(&mut self) -> Token {
-		let mut number = String::new();
-		while let Some('0'..='9') = self.peek()
-			{number.push(self.peeked_next())}
-		Token::LiteralNumber(Box::from(number))
+	/// Parses decimal digits and `_` separators, returning whether there were
+	/// any digits.
+	fn parse_decimal_digits(&mut self) -> bool {
+		let mut any = false;
+		while let Some(character) = self.peek() {
+			match character {
+				'_' => self.eat(),
+				'0'..='9' => any = self.eat_return(true),
+				_ => break
+			}
+		}
+		any
 	}
-}
 
-impl<I> Iterator for Tokenizer<I>
-		where I: Iterator<Item = char> {
-	type Item = Token;
+	fn parse_string(&mut self) -> Result<Token<'s>, Error> {
+		self.eat();
+		let start = self.offset;
+		// Only strings containing escapes differ from the source, and so need
+		// text of their own.
+		let mut string: Option<String> = None;
+		loop {
+			let end = self.offset;
+			match self.next() {
+				Some('"') => break Ok(Token::LiteralString(match string {
+					Some(string) => string.into(),
+					None => self.source[start..end].into()
+				})),
+				Some('\\') => {
+					let source = self.source;
+					let string = string.get_or_insert_with(|| source[start..end].into());
+					match self.peek() {
+						// A backslash at the end of a line skips the line break, along
+						// with any whitespace at the start of the next line.
+						Some('\n' | '\r') => while let Some(' ' | '\n' | '\r' | '\t') = self.peek()
+							{self.eat()},
+						_ => string.push(self.parse_escape()?)
+					}
+				},
+				Some(character) => if let Some(string) = &mut string {string.push(character)},
+				None => break Err(error(Unterminated, "unterminated string"))
+			}
+		}
+	}
 
-	fn next(&mut self) -> Option<Token> {
-		Some(match self.parse_whitespace()? {
-			'a'..='z' | 'A'..='Z' | '_' => self.parse_identifier(),
-			'0'..='9' => self.parse_number(),
+	/// Parses a raw string such as `r#"..."#`, starting after its `r`. Nothing
+	/// within a raw string is escaped; it ends at the first `"` followed by as
+	/// many `#`s as it began with.
+	fn parse_raw_string(&mut self) -> Result<Token<'s>, Error> {
+		let mut hashes = 0;
+		while let Some('#') = self.peek() {
+			self.eat();
+			hashes += 1;
+		}
 
-			'(' => self.eat_return(Token::ParenLeft),
-			')' => self.eat_return(Token::ParenRight),
-			'{' => self.eat_return(Token::BraceLeft),
-			'}' => self.eat_return(Token::BraceRight),
-			'[' => self.eat_return(Token::BracketLeft),
-			']' => self.eat_return(Token::BracketRight),
-			'<' => self.eat_return(Token::ArrowLeft),
-			'>' => self.eat_return(Token::ArrowRight),
+		match self.next() {
+			Some('"') => (),
+			character => return Err(error(UnexpectedCharacter,
+				format!("expected '\"' to begin raw string, found {}", describe(character))))
+		}
 
-			'.' => self.eat_return(Token::Period),
-			',' => self.eat_return(Token::Comma),
-			':' => self.eat_return(Token::Colon),
-			';' => self.eat_return(Token::SemiColon),
+		let start = self.offset;
+		loop {
+			let end = self.offset;
+			match self.next() {
+				Some('"') => {
+					let mut closing = 0;
+					while closing < hashes && self.peek() == Some('#') {
+						self.eat();
+						closing += 1;
+					}
 
-			'=' => self.eat_return(Token::Equals),
+					if closing == hashes
+						{break Ok(Token::LiteralString(self.source[start..end].into()))}
+				},
+				Some(_) => (),
+				None => break Err(error(Unterminated, "unterminated raw string"))
+			}
+		}
+	}
+
+	fn parse_character(&mut self) -> Result<Token<'s>, Error> {
+		self.eat();
+		let character = match self.next() {
+			Some('\\') => self.parse_escape()?,
+			Some('\'') => return Err(error(InvalidCharacter, "empty character literal")),
+			Some(character) => character,
+			None => return Err(error(Unterminated, "unterminated character literal"))
+		};
+
+		match self.next() {
+			Some('\'') => Ok(Token::LiteralCharacter(character)),
+			_ => Err(error(InvalidCharacter, "character literal may only contain one character"))
+		}
+	}
+
+	/// Parses an escape sequence in a string or character literal, starting
+	/// after its backslash.
+	fn parse_escape(&mut self) -> Result<char, Error> {
+		Ok(match self.next() {
+			Some('n') => '\n',
+			Some('r') => '\r',
+			Some('t') => '\t',
+			Some('0') => '\0',
+			Some('\\') => '\\',
+			Some('\'') => '\'',
+			Some('"') => '"',
+
+			// ASCII Escapes
+			Some('x') => {
+				let digits: String = (0..2).filter_map(|_| self.next()).collect();
+				match u8::from_str_radix(&digits, 16) {
+					Ok(code @ 0..=0x7F) => code.into(),
+					Ok(_) => return Err(error(InvalidEscape,
+						format!("escape \\x{} is out of range; must be at most \\x7F", digits))),
+					Err(_) =>
+						return Err(error(InvalidEscape, format!("invalid escape \\x{}", digits)))
+				}
+			},
+
+			// Unicode Escapes
+			Some('u') => {
+				if self.next() != Some('{') {
+					return Err(error(InvalidEscape, "unicode escape must be of the form \\u{...}"))
+				}
+
+				let mut digits = String::new();
+				loop {
+					match self.next() {
+						Some('}') => break,
+						Some('_') => (),
+						Some(digit) if digit.is_ascii_hexdigit() && digits.len() < 6 =>
+							digits.push(digit),
+						character => return Err(error(InvalidEscape,
+							format!("expected a hexadecimal digit or `}}` in unicode escape, \
+								found {}", describe(character))))
+					}
+				}
 
-			token => todo!("add failiure code; failed on token {:?}", token)
+				// Surrogates are not characters, and so may not be escaped.
+				u32::from_str_radix(&digits, 16).ok()
+					.and_then(char::from_u32)
+					.ok_or_else(|| error(InvalidEscape,
+						format!("invalid unicode escape \\u{{{}}}", digits)))?
+			},
+
+			Some(character) => return Err(error(InvalidEscape,
+				format!("unknown character escape `\\{}`", character.escape_debug()))),
+			None => return Err(error(Unterminated, "unterminated escape"))
 		})
 	}
 }
+
+impl<'s> Iterator for Tokenizer<'s> {
+	type Item = Result<Token<'s>, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_spanned().map(|next| next.map(|(_, token)| token)).transpose()
+	}
+}
+
+/// Describes a character (or the end of the file) for an error message.
+fn describe(character: Option<char>) -> String {
+	match character {
+		Some(character) => format!("{:?}", character),
+		None => "end of file".into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Located, Location, Token, Tokenizer};
+	use crate::frontend::error::{Error, ErrorCode::{self, *}};
+	use std::borrow::Cow;
+
+	fn tokens(source: &str) -> Result<Vec<Token<'_>>, ErrorCode> {
+		Tokenizer::new(source).collect::<Result<_, _>>().map_err(|error| error.code)
+	}
+
+	/// The error tokenizing `source` reports.
+	fn error(source: &str) -> Error {
+		Located::new(source).find_map(Result::err).expect("the source should not tokenize")
+	}
+
+	fn integer(value: u128, suffix: Option<&str>) -> Result<Vec<Token<'_>>, ErrorCode> {
+		Ok(vec![Token::LiteralInteger {value, suffix: suffix.map(Into::into)}])
+	}
+
+	fn float<'s>(value: &'s str, suffix: Option<&'s str>) -> Result<Vec<Token<'s>>, ErrorCode> {
+		Ok(vec![Token::LiteralFloat {value: value.into(), suffix: suffix.map(Into::into)}])
+	}
+
+	#[test]
+	fn comments_are_skipped() {
+		let names = Ok(vec![Token::Identifier("a".into()), Token::Identifier("b".into())]);
+		assert_eq!(tokens("a // line\nb"), names);
+		assert_eq!(tokens("a /* block /* nested */ still */ b"), names);
+		assert_eq!(tokens("a //// four\n/**/ /*** three */ b"), names);
+		assert_eq!(tokens("a /* unterminated /* nested */"), Err(Unterminated));
+	}
+
+	#[test]
+	fn doc_comments_are_tokens() {
+		assert_eq!(tokens("/// outer\n//! inner\r\n"), Ok(vec![
+			Token::DocComment(" outer".into()),
+			Token::InnerDocComment(" inner".into())
+		]));
+		assert_eq!(tokens("/** first\n  * second */"),
+			Ok(vec![Token::DocComment(" first\n second".into())]));
+		assert_eq!(tokens("/*! inner */"), Ok(vec![Token::InnerDocComment(" inner".into())]));
+	}
+
+	#[test]
+	fn strings_are_unescaped() {
+		let string = |string: &'static str| Ok(vec![Token::LiteralString(string.into())]);
+		assert_eq!(tokens(r#""a\tb\n\"q\" \\ \x41\u{e9}\u{1F6_00}\0""#),
+			string("a\tb\n\"q\" \\ A\u{e9}\u{1F600}\0"));
+		assert_eq!(tokens("\"one \\\n\t  line\""), string("one line"));
+		assert_eq!(tokens(r###"r#"no \escapes "here""# r"x" r##"a"#b"##"###),
+			Ok(vec![Token::LiteralString(r#"no \escapes "here""#.into()),
+				Token::LiteralString("x".into()), Token::LiteralString("a\"#b".into())]));
+
+		// Strings without escapes are borrowed from the source.
+		let borrowed = |source| matches!(&tokens(source).unwrap()[..],
+			[Token::LiteralString(Cow::Borrowed(_))]);
+		assert!(borrowed("\"plain\"") && borrowed("r#\"raw\\n\"#") && !borrowed("\"\\n\""));
+
+		assert_eq!(tokens(r#""\x80""#), Err(InvalidEscape));
+		assert_eq!(tokens(r#""\u{D800}""#), Err(InvalidEscape));
+		assert_eq!(tokens(r#""\u{1234567}""#), Err(InvalidEscape));
+		assert_eq!(tokens(r##"r#"a"##), Err(Unterminated));
+	}
+
+	#[test]
+	fn characters_hold_one_character() {
+		let character = |character| Ok(vec![Token::LiteralCharacter(character)]);
+		assert_eq!(tokens("'a'"), character('a'));
+		assert_eq!(tokens(r"'\''"), character('\''));
+		assert_eq!(tokens(r"'\u{1F600}'"), character('\u{1F600}'));
+
+		assert_eq!(tokens("''"), Err(InvalidCharacter));
+		assert_eq!(tokens("'ab'"), Err(InvalidCharacter));
+		assert_eq!(tokens("'a"), Err(InvalidCharacter));
+		assert_eq!(tokens("'"), Err(Unterminated));
+	}
+
+	#[test]
+	fn integer_literals() {
+		assert_eq!(tokens("1_000_000"), integer(1_000_000, None));
+		assert_eq!(tokens("0xff"), integer(0xff, None));
+		assert_eq!(tokens("0xFFu8"), integer(0xff, Some("u8")));
+		assert_eq!(tokens("0o17"), integer(0o17, None));
+		assert_eq!(tokens("0b1010_1010"), integer(0b1010_1010, None));
+		assert_eq!(tokens("7u8"), integer(7, Some("u8")));
+		assert_eq!(tokens("3_i64"), integer(3, Some("i64")));
+		// Hexadecimal digits take precedence over suffixes.
+		assert_eq!(tokens("0x1f32"), integer(0x1f32, None));
+
+		assert_eq!(tokens("0x"), Err(InvalidNumber));
+		assert_eq!(tokens("0b102"), Err(InvalidNumber));
+		assert_eq!(tokens("0o8"), Err(InvalidNumber));
+		assert_eq!(tokens("7u7"), Err(InvalidNumber));
+		assert_eq!(tokens("0x10f64"), integer(0x10f64, None));
+		assert_eq!(tokens("0b1f32"), Err(InvalidNumber));
+		assert_eq!(tokens("340282366920938463463374607431768211456"), Err(IntegerTooLarge));
+	}
+
+	#[test]
+	fn float_literals() {
+		assert_eq!(tokens("1.5"), float("1.5", None));
+		assert_eq!(tokens("1.5f32"), float("1.5", Some("f32")));
+		assert_eq!(tokens("2f64"), float("2", Some("f64")));
+		assert_eq!(tokens("2."), float("2.", None));
+		assert_eq!(tokens("1e3"), float("1e3", None));
+		assert_eq!(tokens("1_000.5E-3"), float("1000.5E-3", None));
+		assert_eq!(tokens("2.5e+3_f32"), float("2.5e+3", Some("f32")));
+
+		assert_eq!(tokens("2.e3"), Err(InvalidNumber));
+		assert_eq!(tokens("2.f32"), Err(InvalidNumber));
+		assert_eq!(tokens("2._5"), Err(InvalidNumber));
+		assert_eq!(tokens("1e"), Err(InvalidNumber));
+		assert_eq!(tokens("1.5u8"), Err(InvalidNumber));
+		assert_eq!(tokens("1e3i32"), Err(InvalidNumber));
+	}
+
+	#[test]
+	fn identifiers_are_unicode_and_normalized() {
+		let name = |name: &'static str| Token::Identifier(name.into());
+		assert_eq!(tokens("caf\u{e9} _x \u{65e5}\u{672c} \u{3b1}\u{3b2}"),
+			Ok(vec![name("caf\u{e9}"), name("_x"), name("\u{65e5}\u{672c}"),
+				name("\u{3b1}\u{3b2}")]));
+		// A decomposed é is the same name as a composed one.
+		assert_eq!(tokens("cafe\u{301}"), Ok(vec![name("caf\u{e9}")]));
+
+		assert_eq!(tokens("\u{1F600}"), Err(UnexpectedCharacter));
+		assert_eq!(tokens("\u{301}x"), Err(UnexpectedCharacter));
+	}
+
+	#[test]
+	fn identifiers_may_contain_digits() {
+		assert_eq!(tokens("x1 vec2"), Ok(vec![Token::Identifier("x1".into()),
+			Token::Identifier("vec2".into())]));
+		assert_eq!(tokens("1x"), Err(InvalidNumber));
+	}
+
+	#[test]
+	fn errors_describe_characters() {
+		assert_eq!(error(r#""\q""#).message, "unknown character escape `\\q`");
+		assert_eq!(error("r#x").message, "expected '\"' to begin raw string, found 'x'");
+		assert_eq!(error("'\\u{12").message,
+			"expected a hexadecimal digit or `}` in unicode escape, found end of file");
+		assert_eq!(error("1e").message, "exponent of literal `1e` has no digits");
+	}
+
+	#[test]
+	fn errors_span_their_tokens() {
+		let at = |line, column| Location {line, column};
+		// Errors span from the start of their token to where they were found.
+		assert_eq!(error("x\n  $").span, Some(at(1, 2)..at(1, 3)));
+		assert_eq!(error("a \"b\\q\" c").span, Some(at(0, 2)..at(0, 6)));
+		assert_eq!(error("\"abc").span, Some(at(0, 0)..at(0, 4)));
+
+		// Nothing follows an error.
+		let mut tokens = Located::new("$ x");
+		assert!(matches!(tokens.next(), Some(Err(_))));
+		assert!(tokens.next().is_none());
+	}
+}
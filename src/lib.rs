@@ -0,0 +1,6 @@
+pub mod backend;
+pub mod compiler;
+pub mod format;
+pub mod frontend;
+pub mod lsp;
+pub mod repl;
@@ -0,0 +1,960 @@
+//! What the language server knows about an open document: the items its tokens
+//! declare and refer to, and the main representation it was last checked into.
+//!
+//! Items and names are found from the tokens alone, so that they are known even
+//! while the document does not parse. Errors are placed at the spans they were
+//! found at.
+//!
+//! A document that another file loads as a module is checked as part of the
+//! crate it belongs to, with its text in place of what is saved in its file.
+
+use crate::frontend::{construct_main_representation,
+	error::{error, Error, ErrorCode::{self, *}}, lint::confusable_identifiers,
+	module::{load_declared, Declarations, ItemKind, ModuleRef}, parser::{self, Block, Parser},
+	tokenizer::{Located, Location, Locator, Token, Tokenizer}, Code, Expression, IStr, Scope,
+	ScopeRef, Statement, Type, Value};
+use std::{cmp::Reverse, collections::{BTreeSet, HashSet}, fs::{read_dir, read_to_string},
+	ops::Range, path::{Path, PathBuf}, ptr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+	Type,
+	Function,
+	Variable,
+	Module
+}
+
+/// An item declared in a document.
+#[derive(Clone, Debug)]
+pub struct Definition {
+	pub name: Box<str>,
+	pub kind: Kind,
+	/// Where the item's name is written.
+	pub selection: Range<usize>,
+	/// The whole item, from its keyword to its end.
+	pub range: Range<usize>,
+	/// Inside the item's braces, if it has any.
+	body: Option<Range<usize>>
+}
+
+/// A name used somewhere other than where it is declared.
+#[derive(Clone, Debug)]
+struct Reference {
+	name: Box<str>,
+	kind: Kind,
+	range: Range<usize>
+}
+
+/// Every item a document declares, and every name it refers to an item by.
+#[derive(Clone, Debug, Default)]
+pub struct Index {
+	pub definitions: Vec<Definition>,
+	references: Vec<Reference>
+}
+
+impl Index {
+	/// Indexes the tokens of a document, each with the range of bytes it spans.
+	pub fn new(tokens: &[(Range<usize>, Token)]) -> Self {
+		let mut index = Self::default();
+		// The definitions whose items have not ended yet, each with the brace
+		// depth it was declared at.
+		let mut open: Vec<(usize, usize)> = Vec::new();
+		let (mut depth, mut parentheses) = (0usize, 0usize);
+		let mut in_use = false;
+
+		for (position, (range, token)) in tokens.iter().enumerate() {
+			let previous = position.checked_sub(1).map(|position| &tokens[position].1);
+			let next = tokens.get(position + 1).map(|(_, token)| token);
+
+			match token {
+				Token::BraceLeft => {
+					if let Some(&(definition, at)) = open.last() {
+						let definition = &mut index.definitions[definition];
+						if at == depth && definition.body.is_none()
+							{definition.body = Some(range.end..range.end)}
+					}
+					depth += 1;
+				},
+				Token::BraceRight => {
+					depth = depth.saturating_sub(1);
+					while let Some(&(definition, at)) = open.last() {
+						let definition = &mut index.definitions[definition];
+						if at == depth {
+							if let Some(body) = &mut definition.body
+								{body.end = body.end.max(range.start)}
+						}
+						// A variable may continue after a block expression.
+						match at > depth || (at == depth && definition.kind != Kind::Variable) {
+							true => {
+								definition.range.end = range.end;
+								open.pop();
+							},
+							false => break
+						}
+					}
+				},
+				Token::ParenLeft => parentheses += 1,
+				Token::ParenRight => parentheses = parentheses.saturating_sub(1),
+				Token::SemiColon => {
+					in_use = false;
+					while let Some(&(definition, at)) = open.last() {
+						match at >= depth {
+							true => {
+								index.definitions[definition].range.end = range.end;
+								open.pop();
+							},
+							false => break
+						}
+					}
+				},
+				Token::KeywordUse => in_use = true,
+
+				Token::Identifier(name) => {
+					let kind = match previous {
+						Some(Token::KeywordData) => Some(Kind::Type),
+						Some(Token::KeywordFn) => Some(Kind::Function),
+						Some(Token::KeywordLet) => Some(Kind::Variable),
+						Some(Token::KeywordMod) => Some(Kind::Module),
+						_ => None
+					};
+					if let Some(kind) = kind {
+						let keyword = &tokens[position - 1].0;
+						let start = match position.checked_sub(2).map(|before| &tokens[before]) {
+							Some((range, Token::KeywordPub)) => range.start,
+							_ => keyword.start
+						};
+
+						open.push((index.definitions.len(), depth));
+						index.definitions.push(Definition {
+							name: (**name).into(),
+							kind,
+							selection: range.clone(),
+							range: start..range.end,
+							body: None
+						});
+						continue
+					}
+
+					// Within a data item, only the names after `:` and within
+					// parentheses are types; the others name fields and variants.
+					let data = open.last().is_some_and(|&(definition, _)|
+						index.definitions[definition].kind == Kind::Type);
+					let kind = match (previous, next) {
+						_ if in_use => None,
+						(Some(Token::LiteralString(_)), _) if &**name == "from" => None,
+						(Some(Token::Colon | Token::ThinArrow), _) => Some(Kind::Type),
+						_ if data => (parentheses > 0).then_some(Kind::Type),
+						// Names before `:` are arguments and fields.
+						(_, Some(Token::Colon)) => None,
+						(_, Some(Token::ParenLeft)) => Some(Kind::Function),
+						_ => Some(Kind::Variable)
+					};
+					if let Some(kind) = kind {
+						index.references.push(Reference {
+							name: (**name).into(),
+							kind,
+							range: range.clone()
+						})
+					}
+				},
+				_ => ()
+			}
+		}
+
+		let end = tokens.last().map(|(range, _)| range.end).unwrap_or_default();
+		open.into_iter().for_each(|(definition, _)| {
+			let definition = &mut index.definitions[definition];
+			definition.range.end = end;
+			if let Some(body) = &mut definition.body {body.end = body.end.max(end)}
+		});
+
+		index
+	}
+
+	/// The name at `offset`, along with what it names and where it is written.
+	fn name_at(&self, offset: usize) -> Option<(&str, Kind, Range<usize>)> {
+		let contains = |range: &Range<usize>| range.start <= offset && offset <= range.end;
+		self.definitions.iter()
+			.find(|definition| contains(&definition.selection))
+			.map(|definition| (&*definition.name, definition.kind, definition.selection.clone()))
+			.or_else(|| self.references.iter()
+				.find(|reference| contains(&reference.range))
+				.map(|reference| (&*reference.name, reference.kind, reference.range.clone())))
+	}
+
+	/// The items whose bodies `offset` is within, outermost first.
+	fn parents(&self, offset: usize) -> Vec<&Definition> {
+		self.definitions.iter()
+			.filter(|definition| definition.kind != Kind::Type)
+			.filter(|definition| definition.body.as_ref()
+				.is_some_and(|body| body.start <= offset && offset <= body.end))
+			.collect()
+	}
+
+	/// The definition of the item named at `offset`, along with whether it is
+	/// visible there. Of the items with its name, the one declared in the
+	/// innermost item enclosing the name is chosen.
+	fn definition(&self, offset: usize) -> Option<(&Definition, bool)> {
+		let (name, kind, range) = self.name_at(offset)?;
+		let parents = self.parents(range.start);
+		let visible = |definition: &Definition| {
+			let enclosing = self.parents(definition.selection.start);
+			enclosing.iter().zip(&parents).all(|(outer, inner)| ptr::eq(*outer, *inner))
+				&& enclosing.len() <= parents.len()
+		};
+
+		self.definitions.iter()
+			.filter(|definition| &*definition.name == name && definition.kind == kind)
+			.max_by_key(|definition| {
+				let visible = visible(definition);
+				let depth = self.parents(definition.selection.start).len();
+				// Earlier items win ties.
+				(visible, visible.then_some(depth), Reverse(definition.range.start))
+			})
+			.map(|definition| (definition, visible(definition)))
+	}
+
+	/// Where the item of the given kind named `name` is declared at the top level
+	/// of the inline module at `modules`, which is empty for the document itself.
+	fn declaration(&self, modules: &[Box<str>], name: &str, kind: Kind) -> Option<Range<usize>> {
+		self.definitions.iter()
+			.filter(|definition| &*definition.name == name && definition.kind == kind)
+			.find(|definition| {
+				let parents = self.parents(definition.selection.start);
+				parents.len() == modules.len() && parents.iter().zip(modules)
+					.all(|(parent, module)| parent.kind == Kind::Module && parent.name == *module)
+			})
+			.map(|definition| definition.selection.clone())
+	}
+}
+
+/// Where the item of the given kind named `name` is declared in `text`, at the
+/// top level of the inline module at `modules` within it.
+pub fn declaration(text: &str, modules: &[Box<str>], name: &str, kind: Kind)
+		-> Option<Range<usize>> {
+	Index::new(&tokens(text).0).declaration(modules, name, kind)
+}
+
+/// The tokens of a text up to the first that cannot be read, each with the
+/// range of bytes it spans, and the error reading that one, if there is one.
+#[allow(clippy::type_complexity)]
+fn tokens(text: &str) -> (Vec<(Range<usize>, Token<'_>)>, Result<(), Error>) {
+	let mut tokenizer = Tokenizer::new(text);
+	let mut tokens = Vec::new();
+	let tokenized = loop {
+		match tokenizer.next_spanned() {
+			Ok(Some(token)) => tokens.push(token),
+			Ok(None) => break Ok(()),
+			Err(error) => break Err(error)
+		}
+	};
+	(tokens, tokenized)
+}
+
+/// Where go to definition leads from a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+	/// The name of an item declared in the document.
+	Here(Range<usize>),
+	/// An item declared in another file, within the inline modules `modules` of
+	/// it, or the file itself if there is no item.
+	File {
+		file: PathBuf,
+		modules: Vec<Box<str>>,
+		item: Option<(Box<str>, Kind)>
+	}
+}
+
+/// A problem found when checking a document.
+#[derive(Clone, Debug)]
+pub struct Problem {
+	pub range: Range<usize>,
+	/// Errors stop the document from being compiled; warnings do not.
+	pub error: bool,
+	pub code: Option<ErrorCode>,
+	pub message: String
+}
+
+/// An open document, as last changed by the client.
+#[derive(Debug, Default)]
+pub struct Document {
+	pub text: String,
+	/// The file the document is, or an empty path if it is not a file.
+	path: PathBuf,
+	/// The crate root that loads the document as a module, and the path of the
+	/// module from it, if the document is a module file.
+	root: Option<(PathBuf, Vec<Box<str>>)>,
+	/// The directory the modules the document declares are loaded from.
+	directory: PathBuf,
+	pub index: Index,
+	/// The document when it last had no errors.
+	checked: Option<Checked>
+}
+
+/// A document that had no errors, along with the main representation of the
+/// crate it is in and the path of its module within it.
+#[derive(Debug)]
+struct Checked {
+	text: String,
+	index: Index,
+	code: Code<'static>,
+	module: Vec<Box<str>>
+}
+
+impl Checked {
+	/// The code of the document's module.
+	fn module(&self) -> Option<&Code<'static>> {
+		self.module.iter().try_fold(&self.code, |code, name|
+			code.scope.modules.get(&**name).map(|module| &module.code))
+	}
+}
+
+impl Document {
+	pub fn new(text: String, path: PathBuf) -> Self {
+		Self {text, path, ..Self::default()}
+	}
+
+	/// Replaces the document's text, returning every problem found in it.
+	pub fn change(&mut self, text: String) -> Vec<Problem> {
+		self.text = text;
+		self.check()
+	}
+
+	/// Indexes and checks the document as a crate root, or as part of the crate
+	/// that loads it as a module, keeping the crate's main representation if it
+	/// has no errors.
+	pub fn check(&mut self) -> Vec<Problem> {
+		self.root = crate_root(&self.path);
+		self.directory = match (&self.root, self.path.file_name()) {
+			(Some(_), Some(name)) if name != "mod.rsst" => self.path.with_extension(""),
+			_ => self.path.parent().map(Path::to_path_buf).unwrap_or_default()
+		};
+
+		// The tokens up to the first that cannot be read are indexed, and then
+		// parsed, which fails with the tokenizer's error if there is one. Their
+		// names are owned, as what is checked is kept once the text changes.
+		let (tokens, tokenized) = tokens(&self.text);
+		self.index = Index::new(&tokens);
+
+		let mut locator = Locator::new(&self.text);
+		let located = tokens.into_iter()
+			.map(|(range, token)| Ok((locator.locate(range), token.into_owned())))
+			.chain(tokenized.err().map(Err));
+		let mut sources = BTreeSet::new();
+		let block = Parser::new(located).parse_block().and_then(|mut block|
+			load_declared(&mut block, &self.directory, &mut sources).map(|_| block));
+		let block = match block {
+			Ok(block) => block,
+			Err(found) => return vec![self.problem(found)]
+		};
+		let warnings = confusable_identifiers(&block).into_iter()
+			.map(|(first, second)| Problem {
+				range: self.occurrence(&second),
+				error: false,
+				code: None,
+				message: format!("identifiers {:?} and {:?} could be confused", first, second)
+			})
+			.collect();
+
+		let block = match &self.root {
+			Some((root, modules)) => in_crate(root, modules, block, &self.path),
+			None => Ok(block)
+		};
+		let scope = Scope::prelude();
+		let code = block.and_then(|block| Declarations::declare(&block).and_then(|items|
+			construct_main_representation(&block, ScopeRef::new(&scope), ModuleRef::new(&items))));
+		match code {
+			Ok(code) => {
+				self.checked = Some(Checked {
+					text: self.text.clone(),
+					index: self.index.clone(),
+					code,
+					module: self.root.iter().flat_map(|(_, modules)| modules.clone()).collect()
+				});
+				warnings
+			},
+			// Errors outside of any module file are in the crate root.
+			Err(found) => [match &self.root {
+				Some((root, _)) => self.problem(found.in_file(root)),
+				None => self.problem(found)
+			}].into_iter().chain(warnings).collect()
+		}
+	}
+
+	/// The problem an error is, placed in the document. Errors in other files
+	/// say where they are.
+	fn problem(&self, error: Error) -> Problem {
+		let range = self.range(&error);
+		let message = match (&error.file, &error.span) {
+			(Some(file), _) if *file == self.path => error.message,
+			(Some(file), Some(span)) => format!("{} (in {}:{}:{})", error.message,
+				file.display(), span.start.line + 1, span.start.column + 1),
+			(Some(file), None) => format!("{} (in {})", error.message, file.display()),
+			(None, _) => error.message
+		};
+		Problem {range, error: true, code: Some(error.code), message}
+	}
+
+	/// The byte offset of a location in the document.
+	fn offset(&self, location: Location) -> usize {
+		super::offset(&self.text, location.line as usize, location.column as usize)
+	}
+
+	/// Where in the document an error is. Errors in module files are placed at
+	/// the module loading the file, or at the start of the document if it does
+	/// not load the file, and errors found at the start of an item at its name.
+	fn range(&self, error: &Error) -> Range<usize> {
+		if let Some(file) = error.file.as_ref().filter(|file| **file != self.path) {
+			return self.index.definitions.iter()
+				.filter(|definition| definition.kind == Kind::Module
+					&& self.index.parents(definition.selection.start).is_empty())
+				.find(|definition| {
+					let file_name = format!("{}.rsst", definition.name);
+					*file == self.directory.join(file_name)
+						|| file.starts_with(self.directory.join(&*definition.name))
+				})
+				.map(|definition| definition.selection.clone())
+				.unwrap_or_default()
+		}
+
+		let Some(span) = &error.span else {return 0..0};
+		let (start, end) = (self.offset(span.start), self.offset(span.end));
+		if start < end {return start..end}
+
+		let rest = &self.text[start..];
+		let word = match rest.find(|character| !is_name(character)) {
+			Some(0) => rest.chars().next().map_or(0, char::len_utf8),
+			Some(length) => length,
+			None => rest.len()
+		};
+		self.index.definitions.iter()
+			.find(|definition| definition.range.start == start)
+			.map(|definition| definition.selection.clone())
+			.unwrap_or(start..start + word)
+	}
+
+	/// Where `name` is first written: its first use, or failing that its first
+	/// declaration.
+	fn occurrence(&self, name: &str) -> Range<usize> {
+		self.index.references.iter()
+			.find(|reference| &*reference.name == name)
+			.map(|reference| &reference.range)
+			.or_else(|| self.index.definitions.iter()
+				.find(|definition| &*definition.name == name)
+				.map(|definition| &definition.selection))
+			.cloned()
+			.unwrap_or(0..0)
+	}
+
+	/// Describes the item named at `offset`, as it was last checked. Names
+	/// within text changed since are not described.
+	pub fn hover(&self, offset: usize) -> Option<(Range<usize>, String)> {
+		let checked @ Checked {text, index, code, ..} = self.checked.as_ref()?;
+		let edit = Edit::new(text, &self.text);
+		let (name, kind, range) = index.name_at(edit.to_old(offset)?)?;
+		let parents = index.parents(range.start);
+		let range = edit.to_new(range.start)?..edit.to_new(range.end)?;
+
+		let prelude = Scope::prelude();
+		let (declaration, documentation) =
+			within(ScopeRef::new(&prelude), checked.module()?, &parents,
+				|scope| describe(scope, code, name, kind))?;
+		let documentation = documentation
+			.map(|text| text.split('\n')
+				.map(|line| line.strip_prefix(' ').unwrap_or(line))
+				.fold(String::from("\n"), |text, line| text + "\n" + line))
+			.unwrap_or_default();
+
+		Some((range, format!("```rustscript\n{}\n```{}", declaration, documentation)))
+	}
+
+	/// Every type and function visible at `offset`, as it was last checked,
+	/// along with how each is declared. Only types are offered after `:` or `->`.
+	pub fn completions(&self, offset: usize) -> Vec<(Kind, String, Option<String>)> {
+		let Some((checked, module)) = self.checked.as_ref()
+			.and_then(|checked| Some((checked, checked.module()?))) else {return Vec::new()};
+		let code = &checked.code;
+		let types_only = matches!(self.text[..offset].trim_end_matches(is_name).trim_end()
+			.chars().last(), Some(':' | '>'));
+		let parents = self.index.parents(offset);
+
+		let prelude = Scope::prelude();
+		within(ScopeRef::new(&prelude), module, &parents, |scope| {
+			let mut seen = HashSet::new();
+			let mut items = Vec::new();
+			let mut current = Some(&scope);
+			while let Some(scope) = current {
+				let local = scope.local();
+				let types = local.types.keys()
+					.chain(local.imports.iter()
+						.filter(|(_, import)| import.kind == ItemKind::Type)
+						.map(|(name, _)| name))
+					.map(|name| (Kind::Type, name));
+				let functions = local.functions.keys()
+					.chain(local.imports.iter()
+						.filter(|(_, import)| import.kind == ItemKind::Function)
+						.map(|(name, _)| name))
+					.map(|name| (Kind::Function, name))
+					.filter(|_| !types_only);
+
+				for (kind, name) in types.chain(functions) {
+					if seen.insert((kind, name.clone())) {
+						let detail = describe(*scope, code, name, kind)
+							.map(|(declaration, _)| declaration);
+						items.push((kind, name.to_string(), detail));
+					}
+				}
+				current = scope.outer();
+			}
+
+			items.sort();
+			items
+		})
+	}
+
+	/// Where the item named at `offset` is declared. Names that are not declared
+	/// in the document are followed to the file declaring them, as the document
+	/// was last checked, and modules loaded from files lead to their files.
+	pub fn definition(&self, offset: usize) -> Option<Target> {
+		let local = self.index.definition(offset);
+		let here = |definition: &Definition| Target::Here(definition.selection.clone());
+		if let Some((definition, true)) = local
+			{if definition.kind != Kind::Module {return Some(here(definition))}}
+
+		let followed = self.checked.as_ref().and_then(|checked| {
+			let edit = Edit::new(&checked.text, &self.text);
+			let (name, kind, range) = checked.index.name_at(edit.to_old(offset)?)?;
+			let parents = checked.index.parents(range.start);
+			let prelude = Scope::prelude();
+			let (file, modules, item) = within(ScopeRef::new(&prelude), checked.module()?,
+				&parents, |scope| self.target(scope, &checked.code, name, kind))?;
+
+			match file == self.path {
+				true => declaration(&self.text, &modules, &item?.0, kind).map(Target::Here),
+				false => Some(Target::File {file, modules, item})
+			}
+		});
+		followed.or_else(|| local.map(|(definition, _)| here(definition)))
+	}
+
+	/// The file an item named `name` in `scope` is declared in, the inline
+	/// modules it is within in that file, and the item itself, for an imported
+	/// item or a module loaded from a file. `code` is the crate root.
+	#[allow(clippy::type_complexity)]
+	fn target(&self, scope: ScopeRef, code: &Code, name: &str, kind: Kind)
+			-> Option<(PathBuf, Vec<Box<str>>, Option<(Box<str>, Kind)>)> {
+		let name = IStr::from(name);
+		if kind == Kind::Module {
+			let file = scope.local().modules.get(&name)?.file.clone()?;
+			return Some((file, Vec::new(), None))
+		}
+
+		let item_kind = match kind {
+			Kind::Type => ItemKind::Type,
+			Kind::Function => ItemKind::Function,
+			Kind::Variable | Kind::Module => return None
+		};
+		let import = scope.import_ref(&name, item_kind)?;
+		let (last, path) = import.path.split_last()?;
+		let root = self.root.as_ref().map_or(&self.path, |(root, _)| root).clone();
+		let (file, modules, _) = path.iter().try_fold((root, Vec::new(), code),
+			|(file, mut modules, code), name| {
+				let module = code.scope.modules.get(name)?;
+				Some(match &module.file {
+					Some(file) => (file.clone(), Vec::new(), &module.code),
+					None => {
+						modules.push(Box::from(&**name));
+						(file, modules, &module.code)
+					}
+				})
+			})?;
+		Some((file, modules, Some((Box::from(&**last), kind))))
+	}
+}
+
+/// The crate root that loads `file` as a module, and the path of the module
+/// from it, if `file` is declared as a module by a file that would load it: a
+/// crate root or `mod.rsst` next to it, or the module file its directory is
+/// named after.
+pub fn crate_root(file: &Path) -> Option<(PathBuf, Vec<Box<str>>)> {
+	fn find(file: &Path, visiting: &mut Vec<PathBuf>) -> Option<(PathBuf, Vec<Box<str>>)> {
+		// The directory a file declaring the module would load it from.
+		let (name, directory) = match file.file_name()?.to_str()? {
+			"mod.rsst" => {
+				let directory = file.parent()?;
+				(directory.file_name()?.to_str()?, directory.parent()?)
+			},
+			name => (name.strip_suffix(".rsst")?, file.parent()?)
+		};
+		if visiting.iter().any(|visited| visited == file) {return None}
+		visiting.push(file.to_owned());
+
+		let parent = directory.file_name().zip(directory.parent())
+			.map(|(parent, outer)| outer.join(parent).with_extension("rsst"));
+		let index = directory.join("mod.rsst");
+		let mut siblings = read_dir(if directory == Path::new("") {Path::new(".")} else {directory})
+			.into_iter().flatten()
+			.filter_map(|entry| Some(directory.join(entry.ok()?.file_name())))
+			.filter(|sibling| sibling.extension().is_some_and(|extension| extension == "rsst")
+				&& *sibling != file && *sibling != index)
+			.collect::<Vec<_>>();
+		siblings.sort();
+
+		// A module file or `mod.rsst` may itself be a module, but any other file
+		// that loads modules from the same directory as it is in is a crate root.
+		let found = parent.into_iter().map(|parent| (parent, true))
+			.chain([(index, false)])
+			.chain(siblings.into_iter().map(|sibling| (sibling, false)))
+			.filter(|(declaring, _)| declares(declaring, name))
+			.find_map(|(declaring, module)| match find(&declaring, visiting) {
+				Some((root, mut path)) => {
+					path.push(name.into());
+					Some((root, path))
+				},
+				None if module => None,
+				None => Some((declaring, vec![name.into()]))
+			});
+		visiting.pop();
+		found
+	}
+
+	find(file, &mut Vec::new())
+}
+
+/// Whether the file at `path` declares a module named `name` to be loaded from
+/// a file, outside of any braces.
+fn declares(path: &Path, name: &str) -> bool {
+	let Ok(text) = read_to_string(path) else {return false};
+	let tokens = tokens(&text).0;
+	let mut depth = 0usize;
+	tokens.iter().enumerate().any(|(index, (_, token))| {
+		match token {
+			Token::BraceLeft => depth += 1,
+			Token::BraceRight => depth = depth.saturating_sub(1),
+			_ => ()
+		}
+		depth == 0 && matches!(token, Token::KeywordMod)
+			&& matches!(tokens.get(index + 1), Some((_, Token::Identifier(found))) if found == name)
+			&& matches!(tokens.get(index + 2), Some((_, Token::SemiColon)))
+	})
+}
+
+/// The crate root at `root`, with its modules loaded and `module`, parsed from
+/// the text of the module file at `file`, as the module at `modules`.
+fn in_crate(root: &Path, modules: &[Box<str>], module: Block<'static>, file: &Path)
+		-> Result<Block<'static>, Error> {
+	/// Gives the module at `modules` within `parent` the body `block`, loading
+	/// the module files around it so that its body is loaded from no file.
+	fn insert(parent: &mut Block<'static>, directory: &Path, modules: &[Box<str>],
+			block: Block<'static>, file: &Path) -> Result<(), Error> {
+		let (name, rest) = modules.split_first().expect("modules are within the crate root");
+		let module = parent.0.iter_mut()
+			.filter_map(|statement| match statement {
+				parser::Statement::ModItem(_, module) => Some(module),
+				_ => None
+			})
+			.find(|module| module.name == **name && module.body.is_none())
+			.ok_or_else(|| error(ModuleNotFound, format!("module {:?} is not declared", name)))?;
+
+		if rest.is_empty() {
+			module.body = Some(block);
+			module.file = Some(file.to_owned());
+			return Ok(())
+		}
+		let nested = directory.join(&**name);
+		let path = match directory.join(format!("{}.rsst", name)) {
+			path if path.is_file() => path,
+			_ => nested.join("mod.rsst")
+		};
+		let mut body = parse(&path)?;
+		insert(&mut body, &nested, rest, block, file).map_err(|error| error.in_file(&path))?;
+		module.body = Some(body);
+		module.file = Some(path);
+		Ok(())
+	}
+
+	fn parse(path: &Path) -> Result<Block<'static>, Error> {
+		let source = read_to_string(path).map_err(|cause| error(ModuleNotFound,
+			format!("could not load module {:?}: {}", path, cause)).in_file(path))?;
+		let tokens = Located::new(&source)
+			.map(|token| token.map(|(span, token)| (span, token.into_owned())));
+		Parser::new(tokens).parse_block().map_err(|error| error.in_file(path))
+	}
+
+	let directory = root.parent().unwrap_or_else(|| Path::new(""));
+	let mut block = parse(root)?;
+	insert(&mut block, directory, modules, module, file)?;
+	load_declared(&mut block, directory, &mut BTreeSet::new())?;
+	Ok(block)
+}
+
+fn is_name(character: char) -> bool {
+	character == '_' || character.is_alphanumeric()
+}
+
+/// How a text was changed into another: everything between the bytes they
+/// begin with and the bytes they end with in common was replaced.
+struct Edit {
+	prefix: usize,
+	/// Where the bytes in common at the end begin, in the old and new text.
+	old: usize,
+	new: usize
+}
+
+impl Edit {
+	fn new(old: &str, new: &str) -> Self {
+		let prefix = old.bytes().zip(new.bytes()).take_while(|(old, new)| old == new).count();
+		let suffix = old[prefix..].bytes().rev().zip(new[prefix..].bytes().rev())
+			.take_while(|(old, new)| old == new)
+			.count();
+		Self {prefix, old: old.len() - suffix, new: new.len() - suffix}
+	}
+
+	/// The offset in the old text of an offset in the new one, unless it is
+	/// within the replaced bytes.
+	fn to_old(&self, offset: usize) -> Option<usize> {
+		match offset {
+			offset if offset <= self.prefix => Some(offset),
+			offset if offset >= self.new => Some(offset - self.new + self.old),
+			_ => None
+		}
+	}
+
+	/// The offset in the new text of an offset in the old one, unless it is
+	/// within the replaced bytes.
+	fn to_new(&self, offset: usize) -> Option<usize> {
+		match offset {
+			offset if offset <= self.prefix => Some(offset),
+			offset if offset >= self.old => Some(offset - self.old + self.new),
+			_ => None
+		}
+	}
+}
+
+/// Runs `f` on the scope of the innermost of `parents`, each of which is an
+/// item declared in the one before it, and the first in `code`. Items that were
+/// not checked, such as those added since, are skipped.
+fn within<R>(outer: ScopeRef, code: &Code, parents: &[&Definition],
+		f: impl FnOnce(ScopeRef) -> R) -> R {
+	let scope = outer.r#in(&code.scope);
+	let Some((parent, parents)) = parents.split_first() else {return f(scope)};
+	let name = &*parent.name;
+
+	match parent.kind {
+		// Modules do not see any of the items around them.
+		Kind::Module => match code.scope.modules.get(name) {
+			Some(module) => within(scope.root(), &module.code, parents, f),
+			None => f(scope)
+		},
+		Kind::Function => match code.scope.functions.get(name) {
+			Some(function) => {
+				let arguments = Scope {
+					values: function.arguments.iter()
+						.map(|(name, r#type)|
+							(name.clone(), Value {r#type: r#type.clone(), foreign: None}))
+						.collect(),
+					..Default::default()
+				};
+				within(scope.function(&arguments), &function.code, parents, f)
+			},
+			None => f(scope)
+		},
+		Kind::Variable => {
+			let variable = code.statements.iter()
+				.filter_map(Statement::variable_ref)
+				.find(|variable| variable.name == name);
+			match variable.map(|variable| &variable.expression) {
+				Some(Expression::Block(block)) => within(scope, block, parents, f),
+				_ => f(scope)
+			}
+		},
+		Kind::Type => within(outer, code, parents, f)
+	}
+}
+
+/// How the item of the given kind named `name` in `scope` is declared, along
+/// with its documentation. Imports are followed from the crate root, `code`.
+/// Variables are described without their documentation, which belongs to the
+/// block they are in rather than its scope.
+fn describe(scope: ScopeRef, code: &Code, name: &str, kind: Kind)
+		-> Option<(String, Option<String>)> {
+	let name = IStr::from(name);
+	let item_kind = match kind {
+		Kind::Type => Some(ItemKind::Type),
+		Kind::Function => Some(ItemKind::Function),
+		Kind::Variable | Kind::Module => None
+	};
+	if let Some(import) = item_kind.and_then(|item_kind| scope.import_ref(&name, item_kind)) {
+		let (last, path) = import.path.split_last()?;
+		let module = path.iter().try_fold(code, |code, name|
+			code.scope.modules.get(name).map(|module| &module.code))?;
+		let prelude = Scope::prelude();
+		return describe(ScopeRef::new(&prelude).r#in(&module.scope), code, last, kind)
+	}
+
+	match kind {
+		Kind::Type => scope.type_ref(&name).map(|r#type| (r#type.declaration(&name),
+			match r#type {
+				Type::User {documentation, ..} => documentation.as_deref().map(Into::into),
+				_ => None
+			})),
+		Kind::Function => scope.function_ref(&name).map(|function|
+			(function.declaration(&name), function.documentation.as_deref().map(Into::into))),
+		Kind::Variable => scope.value_ref(&name)
+			.map(|value| (format!("let {}: {}", name, value.r#type.name), None)),
+		Kind::Module => None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{crate_root, Document, Kind, Target};
+	use crate::frontend::error::ErrorCode::{self, *};
+	use std::{env::temp_dir, fs::{create_dir_all, read_to_string, remove_dir_all, write},
+		path::PathBuf, process};
+
+	/// The code of each problem found in `text`, the document at `path`, with
+	/// the text it is placed at.
+	fn problems(text: &str, path: PathBuf) -> Vec<(Option<ErrorCode>, String)> {
+		let mut document = Document::new(text.into(), path);
+		document.check().into_iter()
+			.map(|problem| (problem.code, text[problem.range].to_owned()))
+			.collect()
+	}
+
+	#[test]
+	fn problems_are_placed_at_their_spans() {
+		let problems = |text| problems(text, PathBuf::new());
+		assert_eq!(problems("data A;\nlet x: i32 = ;"),
+			[(Some(UnexpectedToken), ";".into())]);
+		assert_eq!(problems("let s: str = \"A\";\nlet t: str = \"unterminated;"),
+			[(Some(Unterminated), "\"unterminated;".into())]);
+		// The second declaration is the duplicate, even though the name is quoted
+		// first in a comment.
+		assert_eq!(problems("// \"f\"\nfn f() {}\n\nfn f() {}"),
+			[(Some(DuplicateItem), "f".into())]);
+		let text = "fn f() {}\n\nfn f() {}";
+		let mut document = Document::new(text.into(), PathBuf::new());
+		assert_eq!(document.check()[0].range, 14..15);
+		assert_eq!(problems("let x: Missing = 1;"), [(Some(UnknownType), "x".into())]);
+	}
+
+	#[test]
+	fn problems_in_module_files_are_placed_at_their_modules() {
+		let directory = temp_dir().join(format!("rustscript-lsp-{}", process::id()));
+		create_dir_all(directory.join("m")).unwrap();
+		write(directory.join("m.rsst"), "mod n;").unwrap();
+		write(directory.join("m/n.rsst"), "data A;\ndata A;").unwrap();
+		write(directory.join("broken.rsst"), "fn (").unwrap();
+
+		let root = directory.join("main.rsst");
+		assert_eq!(problems("data B;\nmod m;", root.clone()),
+			[(Some(DuplicateItem), "m".into())]);
+		assert_eq!(problems("mod broken;", root.clone()),
+			[(Some(UnexpectedToken), "broken".into())]);
+		assert_eq!(problems("mod missing;", root.clone()),
+			[(Some(ModuleNotFound), "missing".into())]);
+
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn hover_describes_the_last_document_without_errors() {
+		let text = "/// Doubles a number.\nfn double(x: i32) {}\n\nfn main() {double(1);}";
+		let mut document = Document::new(text.into(), PathBuf::new());
+		assert!(document.check().is_empty());
+		let call = text.rfind("double").unwrap();
+		let (range, description) = document.hover(call).unwrap();
+		assert_eq!(range, call..call + 6);
+		assert!(description.contains("fn double(x: i32)"), "{}", description);
+		assert!(description.contains("Doubles a number."), "{}", description);
+
+		// Names before and after a broken edit are still described where they
+		// now are, but the edited text is not.
+		let broken = format!("let z: i32 = ;\n{}", text);
+		assert!(!document.change(broken.clone()).is_empty());
+		let call = broken.rfind("double").unwrap();
+		assert_eq!(document.hover(call).unwrap().0, call..call + 6);
+		assert_eq!(document.hover(4), None);
+
+		let broken = text.replace("double(1)", "double(");
+		assert!(!document.change(broken.clone()).is_empty());
+		assert!(document.hover(broken.find("double").unwrap()).is_some());
+		assert_eq!(document.hover(broken.rfind("double").unwrap() + 7), None);
+	}
+
+	#[test]
+	fn module_files_are_checked_in_their_crates() {
+		let directory = temp_dir().join(format!("rustscript-lsp-crate-{}", process::id()));
+		create_dir_all(directory.join("m")).unwrap();
+		let (root, module) = (directory.join("main.rsst"), directory.join("m.rsst"));
+		write(&root, "mod m;\npub fn helper() {}\nfn main() {}").unwrap();
+		write(&module, "mod n;").unwrap();
+		write(directory.join("m/n.rsst"), "pub fn g() {}").unwrap();
+
+		assert_eq!(crate_root(&root), None);
+		assert_eq!(crate_root(&module), Some((root.clone(), vec!["m".into()])));
+		assert_eq!(crate_root(&directory.join("m/n.rsst")),
+			Some((root.clone(), vec!["m".into(), "n".into()])));
+
+		// The open text is checked rather than what is saved, and sees the crate.
+		let text = "mod n;\nuse crate::helper;\nuse n::g;\npub fn f() {helper(); g();}";
+		assert!(problems(text, module.clone()).is_empty());
+		assert_eq!(problems(&format!("{}\nlet x: bool = 1;", text), module.clone()),
+			[(Some(MismatchedTypes), "x".into())]);
+
+		// Errors elsewhere in the crate say where they are.
+		write(&root, "mod m;\npub fn helper() {}\nfn main() {missing();}").unwrap();
+		let mut document = Document::new(text.into(), module.clone());
+		let problems = document.check();
+		assert_eq!((problems[0].code, problems[0].range.clone()), (Some(UnknownFunction), 0..0));
+		assert!(problems[0].message.ends_with(&format!("(in {}:3:12)", root.display())),
+			"{}", problems[0].message);
+
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn definitions_are_found_in_the_files_declaring_them() {
+		let directory = temp_dir().join(format!("rustscript-lsp-definition-{}", process::id()));
+		create_dir_all(&directory).unwrap();
+		let (root, module) = (directory.join("main.rsst"), directory.join("m.rsst"));
+		let text = "mod m;\nmod inner {pub fn h() {}}\nuse m::f;\nuse inner::h;\n\
+			fn main() {f(); h(); main();}";
+		write(&root, text).unwrap();
+		write(&module, "pub fn f() {}").unwrap();
+
+		let mut document = Document::new(text.into(), root.clone());
+		assert!(document.check().is_empty());
+		let at = |name: &str| text.rfind(name).unwrap();
+		assert_eq!(document.definition(at("f()")), Some(Target::File {
+			file: module.clone(),
+			modules: Vec::new(),
+			item: Some(("f".into(), Kind::Function))
+		}));
+		// Items imported from inline modules, and items declared in the document,
+		// are where they are in its text.
+		let declared = |name: &str| {
+			let start = text.find(&format!("fn {}(", name)).unwrap() + 3;
+			Some(Target::Here(start..start + name.len()))
+		};
+		assert_eq!(document.definition(at("h()")), declared("h"));
+		assert_eq!(document.definition(at("main()")), declared("main"));
+		assert_eq!(document.definition(at("m;")),
+			Some(Target::File {file: module.clone(), modules: Vec::new(), item: None}));
+
+		// From a module file, items in the crate root are found in its file.
+		let text = "use crate::inner::h;\npub fn f() {h();}";
+		let mut document = Document::new(text.into(), module);
+		assert!(document.check().is_empty());
+		assert_eq!(document.definition(text.rfind("h()").unwrap()), Some(Target::File {
+			file: root.clone(),
+			modules: vec!["inner".into()],
+			item: Some(("h".into(), Kind::Function))
+		}));
+		assert_eq!(super::declaration(&read_to_string(&root).unwrap(), &["inner".into()], "h",
+			Kind::Function), Some(25..26));
+
+		remove_dir_all(directory).unwrap();
+	}
+}
@@ -0,0 +1,380 @@
+//! A language server, speaking the Language Server Protocol over standard input
+//! and output. Each open document is checked whenever it changes, as a crate
+//! root or as part of the crate that loads it as a module, and its tokens are
+//! indexed to answer questions about positions in it.
+
+mod document;
+
+use document::{declaration, Document, Kind, Problem, Target};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs::read_to_string, io::{self, BufRead, Write}, ops::Range,
+	path::{Path, PathBuf}};
+
+// Error codes defined by JSON-RPC and the protocol.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A server, along with every document the client has open.
+pub struct Server<W: Write> {
+	output: W,
+	documents: HashMap<String, Document>,
+	/// Whether the client has asked the server to shut down.
+	shutdown: bool
+}
+
+impl<W: Write> Server<W> {
+	pub fn new(output: W) -> Self {
+		Self {output, documents: HashMap::new(), shutdown: false}
+	}
+
+	/// Handles messages from `input` until the client asks the server to exit,
+	/// returning whether it was shut down first, as it should be.
+	pub fn serve(&mut self, mut input: impl BufRead) -> io::Result<bool> {
+		loop {
+			let Some(message) = read_message(&mut input)? else {return Ok(false)};
+			let message = match serde_json::from_slice::<Value>(&message) {
+				Ok(message) => message,
+				Err(error) => {
+					self.send(json!({"jsonrpc": "2.0", "id": null,
+						"error": {"code": PARSE_ERROR, "message": error.to_string()}}))?;
+					continue
+				}
+			};
+
+			let method = message["method"].as_str().unwrap_or_default();
+			if method == "exit" {return Ok(self.shutdown)}
+			let params = &message["params"];
+
+			match message.get("id") {
+				// Requests
+				Some(id) => {
+					let response = match self.request(method, params) {
+						Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+						Err((code, message)) => json!({"jsonrpc": "2.0", "id": id,
+							"error": {"code": code, "message": message}})
+					};
+					self.send(response)?;
+				},
+
+				// Notifications
+				None => self.notify(method, params)?
+			}
+		}
+	}
+
+	fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+		match method {
+			"initialize" => Ok(json!({
+				"capabilities": {
+					// The whole text is sent on every change.
+					"textDocumentSync": 1,
+					"hoverProvider": true,
+					"definitionProvider": true,
+					"documentSymbolProvider": true,
+					"completionProvider": {}
+				},
+				"serverInfo": {"name": "rustscript", "version": env!("CARGO_PKG_VERSION")}
+			})),
+			"shutdown" => {
+				self.shutdown = true;
+				Ok(Value::Null)
+			},
+
+			"textDocument/hover" => {
+				let (document, offset) = self.position(params)?;
+				Ok(document.hover(offset)
+					.map(|(range, contents)| json!({
+						"contents": {"kind": "markdown", "value": contents},
+						"range": range_json(&document.text, &range)
+					}))
+					.unwrap_or(Value::Null))
+			},
+			"textDocument/definition" => {
+				let (document, offset) = self.position(params)?;
+				Ok(match document.definition(offset) {
+					Some(Target::Here(range)) => json!({
+						"uri": params["textDocument"]["uri"],
+						"range": range_json(&document.text, &range)
+					}),
+					// Other files are read as they are open, or else as they are saved.
+					Some(Target::File {file, modules, item}) => {
+						let uri = file_uri(&file);
+						let text = match self.documents.get(&uri) {
+							Some(document) => document.text.clone(),
+							None => read_to_string(&file).unwrap_or_default()
+						};
+						let range = item
+							.and_then(|(name, kind)| declaration(&text, &modules, &name, kind))
+							.unwrap_or_default();
+						json!({"uri": uri, "range": range_json(&text, &range)})
+					},
+					None => Value::Null
+				})
+			},
+			"textDocument/documentSymbol" => {
+				let document = self.document(params)?;
+				Ok(symbols(document, &document.index.definitions))
+			},
+			"textDocument/completion" => {
+				let (document, offset) = self.position(params)?;
+				Ok(document.completions(offset).into_iter()
+					.map(|(kind, name, detail)| json!({
+						"label": name,
+						// Struct and Function
+						"kind": match kind {
+							Kind::Type => 22,
+							_ => 3
+						},
+						"detail": detail
+					}))
+					.collect())
+			},
+
+			method => Err((METHOD_NOT_FOUND, format!("unsupported method {:?}", method)))
+		}
+	}
+
+	fn notify(&mut self, method: &str, params: &Value) -> io::Result<()> {
+		let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+		match method {
+			"textDocument/didOpen" => {
+				let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+				let path = file_path(uri).unwrap_or_default();
+				let mut document = Document::new(text.into(), path);
+				let problems = document.check();
+				self.documents.insert(uri.into(), document);
+				self.publish(uri, &problems)
+			},
+			"textDocument/didChange" => {
+				let text = params["contentChanges"].as_array()
+					.and_then(|changes| changes.last())
+					.and_then(|change| change["text"].as_str());
+				match (self.documents.get_mut(uri), text) {
+					(Some(document), Some(text)) => {
+						let problems = document.change(text.into());
+						self.publish(uri, &problems)
+					},
+					_ => Ok(())
+				}
+			},
+			"textDocument/didClose" => {
+				self.documents.remove(uri);
+				self.publish(uri, &[])
+			},
+			_ => Ok(())
+		}
+	}
+
+	/// Sends the problems found in a document to the client, replacing those
+	/// sent before.
+	fn publish(&mut self, uri: &str, problems: &[Problem]) -> io::Result<()> {
+		let text = self.documents.get(uri).map(|document| &*document.text).unwrap_or_default();
+		let diagnostics: Vec<_> = problems.iter()
+			.map(|problem| json!({
+				"range": range_json(text, &problem.range),
+				// Error and Warning
+				"severity": if problem.error {1} else {2},
+				"code": problem.code.map(|code| code.name()),
+				"source": "rustscript",
+				"message": problem.message
+			}))
+			.collect();
+
+		self.send(json!({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics",
+			"params": {"uri": uri, "diagnostics": diagnostics}}))
+	}
+
+	/// The document a request is about.
+	fn document(&self, params: &Value) -> Result<&Document, (i64, String)> {
+		let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+		self.documents.get(uri)
+			.ok_or_else(|| (INVALID_PARAMS, format!("{:?} is not open", uri)))
+	}
+
+	/// The document a request is about, along with the offset of the position
+	/// it is about.
+	fn position(&self, params: &Value) -> Result<(&Document, usize), (i64, String)> {
+		let document = self.document(params)?;
+		let position = &params["position"];
+		match (position["line"].as_u64(), position["character"].as_u64()) {
+			(Some(line), Some(character)) =>
+				Ok((document, offset(&document.text, line as usize, character as usize))),
+			_ => Err((INVALID_PARAMS, "expected a position".into()))
+		}
+	}
+
+	fn send(&mut self, message: Value) -> io::Result<()> {
+		let message = message.to_string();
+		write!(self.output, "Content-Length: {}\r\n\r\n{}", message.len(), message)?;
+		self.output.flush()
+	}
+}
+
+/// Runs a server on standard input and output until the client asks it to
+/// exit, returning whether it was shut down first.
+pub fn run() -> io::Result<bool> {
+	Server::new(io::stdout().lock()).serve(io::stdin().lock())
+}
+
+/// Reads the content of the next message, or `None` at the end of the input.
+/// Messages without a valid Content-Length header are skipped, as there is no
+/// telling where their content ends.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+	const CONTENT_LENGTH: &str = "content-length";
+
+	let mut length = None;
+	loop {
+		let mut header = Vec::new();
+		if input.read_until(b'\n', &mut header)? == 0 {return Ok(None)}
+
+		let header = String::from_utf8_lossy(&header);
+		let header = header.trim_end();
+		match (header.is_empty(), length) {
+			(true, Some(length)) => {
+				let mut content = vec![0; length];
+				input.read_exact(&mut content)?;
+				return Ok(Some(content))
+			},
+			(true, None) => continue,
+			(false, _) => ()
+		}
+
+		// The content of a skipped message does not end its line, so the next
+		// message's first header may follow it on the same line.
+		if let Some((name, value)) = header.rsplit_once(':') {
+			let name = name.to_ascii_lowercase();
+			let before = name.strip_suffix(CONTENT_LENGTH);
+			if before.is_some_and(|before| before.is_empty() || before.ends_with(['}', ']']))
+				{length = value.trim().parse().ok()}
+		}
+	}
+}
+
+/// Nests the symbols of items within the items they are declared in.
+fn symbols(document: &Document, definitions: &[document::Definition]) -> Value {
+	let mut remaining = definitions;
+	let mut nested = Vec::new();
+	while let Some((definition, rest)) = remaining.split_first() {
+		let inner = rest.iter()
+			.take_while(|inner| inner.range.start < definition.range.end)
+			.count();
+		nested.push(json!({
+			"name": definition.name,
+			// Struct, Function, Variable and Module
+			"kind": match definition.kind {
+				Kind::Type => 23,
+				Kind::Function => 12,
+				Kind::Variable => 13,
+				Kind::Module => 2
+			},
+			"range": range_json(&document.text, &definition.range),
+			"selectionRange": range_json(&document.text, &definition.selection),
+			"children": symbols(document, &rest[..inner])
+		}));
+		remaining = &rest[inner..];
+	}
+
+	Value::Array(nested)
+}
+
+/// Converts a range of bytes to a range of positions, whose characters are
+/// counted in UTF-16 code units.
+fn range_json(text: &str, range: &Range<usize>) -> Value {
+	json!({"start": position(text, range.start), "end": position(text, range.end)})
+}
+
+fn position(text: &str, offset: usize) -> Value {
+	let before = &text[..offset.min(text.len())];
+	let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or_default();
+	json!({
+		"line": before.matches('\n').count(),
+		"character": before[line_start..].encode_utf16().count()
+	})
+}
+
+/// Converts a position to a byte offset, clamped to the end of its line.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+	let line_start = match line {
+		0 => 0,
+		line => match text.match_indices('\n').nth(line - 1) {
+			Some((index, _)) => index + 1,
+			None => return text.len()
+		}
+	};
+
+	let mut units = 0;
+	for (index, current) in text[line_start..].char_indices() {
+		if units >= character || current == '\n' {return line_start + index}
+		units += current.len_utf16();
+	}
+	text.len()
+}
+
+/// The path of a `file:` URI.
+fn file_path(uri: &str) -> Option<PathBuf> {
+	let path = uri.strip_prefix("file://")?;
+	let mut bytes = Vec::new();
+	let mut rest = path.as_bytes();
+	while let Some((&byte, tail)) = rest.split_first() {
+		match (byte, tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok())) {
+			(b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+				bytes.push(u8::from_str_radix(hex, 16).unwrap());
+				rest = &tail[2..];
+			},
+			_ => {
+				bytes.push(byte);
+				rest = tail;
+			}
+		}
+	}
+
+	String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// The `file:` URI of a path, with every byte that may not be written in one
+/// as is escaped.
+fn file_uri(path: &Path) -> String {
+	path.to_string_lossy().bytes().fold(String::from("file://"), |mut uri, byte| {
+		match byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+			true => uri.push(byte as char),
+			false => uri += &format!("%{:02X}", byte)
+		}
+		uri
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::read_message;
+	use std::io::BufReader;
+
+	fn messages(input: &str) -> Vec<String> {
+		let mut input = BufReader::new(input.as_bytes());
+		let mut messages = Vec::new();
+		while let Some(message) = read_message(&mut input).unwrap()
+			{messages.push(String::from_utf8(message).unwrap())}
+		messages
+	}
+
+	#[test]
+	fn messages_are_framed_by_their_length() {
+		assert_eq!(messages("Content-Length: 2\r\n\r\n{}Content-Length: 7\r\n\r\n{\"a\":1}"),
+			["{}", "{\"a\":1}"]);
+		assert_eq!(messages("content-length:2\r\nContent-Type: application/json\r\n\r\n[]"),
+			["[]"]);
+		// Content is counted in bytes.
+		assert_eq!(messages("Content-Length: 4\r\n\r\n\"\u{e9}\""), ["\"\u{e9}\""]);
+		assert!(messages("").is_empty());
+		assert!(read_message(&mut BufReader::new(&b"Content-Length: 9\r\n\r\n{}"[..])).is_err());
+	}
+
+	#[test]
+	fn messages_without_lengths_are_skipped() {
+		assert_eq!(messages("Content-Type: x\r\n\r\n{\"skipped\":true}\
+			Content-Length: 2\r\n\r\n{}"), ["{}"]);
+		assert_eq!(messages("Content-Length: many\r\n\r\n{}Content-Length: 2\r\n\r\n[]"),
+			["[]"]);
+		assert_eq!(messages("\r\n\r\nContent-Length: 2\r\n\r\n{}"), ["{}"]);
+	}
+}
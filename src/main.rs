@@ -1,28 +1,710 @@
-pub mod backend;
-pub mod frontend;
+use rustscript::{backend::{interpreter::Interpreter, javascript::{integer::Overflow,
+		printer::Layout, ModuleSystem}},
+	compiler::{self, Compiler, Diagnostic, Dump, DumpFormat, Emit, Failure, Output, Severity,
+		SourceMaps},
+	format::{self, Indent, Style}, frontend::error::ErrorCode, lsp, repl};
+use std::{collections::BTreeSet, env::{args, temp_dir}, fmt::Display,
+	fs::{create_dir_all, metadata, read_to_string, remove_dir_all, write}, io::{self, stdin},
+	path::{Path, PathBuf}, process::{self, exit}, thread::sleep, time::{Duration, SystemTime}};
 
-use crate::{frontend::{ScopeRef, Scope}, backend::javascript::from_main_representation};
+const USAGE: &str = "\
+Usage: rustscript <COMMAND> [OPTIONS] <INPUT> [-- <ARGUMENTS>...]
+       rustscript repl [--target <TARGET>]
+       rustscript lsp
 
-use self::frontend::{parser::Parser, tokenizer::Tokenizer, construct_main_representation};
-use std::{env::args, fs::{read_to_string, write}};
+Commands:
+  build  Compile a crate to JavaScript
+  check  Check a crate for errors without writing anything
+  run    Run a crate's `main` function with the interpreter, or with Node.js
+  fmt    Format the crate root and every module file it loads, in place
+  emit   Print the crate's JavaScript to standard output
+  repl   Declare items and evaluate expressions interactively
+  lsp    Run a language server over standard input and output
+
+Arguments:
+  <INPUT>         The crate root, or `-` to read it from standard input
+  <ARGUMENTS>...  Arguments passed on to the program by `run --node`
+
+Options:
+  -o, --output <FILE>  Write the crate root's JavaScript to FILE, or to standard
+                       output if FILE is `-`; other modules are written next to
+                       it [default: INPUT with a .js extension]
+      --out-dir <DIR>  Write the crate root's JavaScript into DIR
+      --target <TARGET>
+                       The module system to emit: js (ECMAScript modules) or
+                       commonjs [default: js]
+      --emit <KINDS>   A comma separated list of outputs: js and declarations
+                       (TypeScript .d.ts files) [default: js]
+      --readable       Lay JavaScript out to be read, rather than compactly
+                       (build and emit only)
+      --source-map <WHERE>
+                       Map the JavaScript back to its source, writing each map
+                       to a .js.map file or inline into the JavaScript it maps
+                       (build and emit only)
+      --overflow-checks
+                       Panic when an integer from JavaScript is out of range
+                       for its type, rather than wrapping it around as release
+                       builds of Rust do (build, emit and run --node only)
+      --dump <STAGES>  A comma separated list of compilation stages to dump:
+                       tokens, ast, main-ir and js-ir (once given to --emit,
+                       which still accepts them but is deprecated for them)
+      --dump-format <FORMAT>
+                       How stages are dumped: pretty (source-like where
+                       possible) or json [default: pretty]
+      --dump-dir <DIR> Write each dump to DIR/<INPUT>.<STAGE>.<txt|json>
+                       instead of standard output
+  -w, --watch          Keep running after building, and rebuild whenever a
+                       file the crate is loaded from changes (build only)
+      --node           Run the crate's JavaScript with Node.js instead of
+                       interpreting it (run only)
+      --check          List the files that are not formatted instead of
+                       formatting them (fmt only)
+      --indent <INDENT>
+                       How to indent formatted source: tab, or a number of
+                       spaces (fmt only) [default: tab]
+      --width <COLUMNS>
+                       The width formatted lines are kept within where
+                       possible (fmt only) [default: 100]
+  -q, --quiet          Do not print warnings
+  -v, --verbose        Describe each stage of compilation as it happens
+      --explain <CODE> Describe an error code, such as RS0001, in detail
+  -h, --help           Print this help
+  -V, --version        Print the compiler's version
+
+Exit status:
+  0  Success
+  1  The crate has errors (or, for `run`, the status of the program, and for
+     `fmt --check`, a file is not formatted)
+  2  The command line is invalid
+  3  A file could not be read or written
+";
+
+/// The program being compiled has errors.
+const FAILURE: i32 = 1;
+const USAGE_ERROR: i32 = 2;
+const IO_ERROR: i32 = 3;
+
+/// How often `--watch` checks the crate's files for changes. A rebuild waits
+/// until the files have not changed for this long.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+	Build,
+	Check,
+	Run,
+	Fmt,
+	Emit,
+	Repl,
+	Lsp
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Verbosity {
+	Quiet,
+	Normal,
+	Verbose
+}
+
+#[derive(Debug)]
+struct Options {
+	command: Command,
+	/// The crate root's path, or `None` to read it from standard input.
+	input: Option<PathBuf>,
+	/// Where the crate root's JavaScript is written, or `None` for standard
+	/// output.
+	output: Option<PathBuf>,
+	target: ModuleSystem,
+	emit: Vec<Emit>,
+	layout: Layout,
+	source_maps: Option<SourceMaps>,
+	overflow: Overflow,
+	dump: Vec<Dump>,
+	dump_format: DumpFormat,
+	/// The directory dumps are written to, or `None` for standard output.
+	dump_dir: Option<PathBuf>,
+	watch: bool,
+	/// Whether `run` uses Node.js rather than the interpreter.
+	node: bool,
+	/// Whether `fmt` only lists the files it would change.
+	check: bool,
+	style: Style,
+	verbosity: Verbosity,
+	/// Arguments passed on to the program by `run`.
+	arguments: Vec<String>,
+	/// Warnings about the arguments themselves.
+	warnings: Vec<Diagnostic>
+}
+
+impl Options {
+	fn parse() -> Self {
+		let options = Self::from_arguments(args().skip(1));
+		options.warnings.iter().for_each(|warning| options.report(warning));
+		options
+	}
+
+	fn from_arguments(arguments: impl IntoIterator<Item = String>) -> Self {
+		let mut arguments = arguments.into_iter();
+		let mut command = None;
+		let mut input = None;
+		let (mut output, mut out_dir) = (None, None);
+		let mut target = ModuleSystem::default();
+		let mut emit = None;
+		let mut layout = Layout::Compact;
+		let mut source_maps = None;
+		let mut overflow = Overflow::Wrap;
+		let mut dump = Vec::new();
+		let mut dump_format = DumpFormat::Pretty;
+		let mut dump_dir = None;
+		let mut watch = false;
+		let mut node = false;
+		let mut check = false;
+		let (mut indent, mut width) = (None, None);
+		let mut verbosity = Verbosity::Normal;
+		let mut program = Vec::new();
+		let mut warnings = Vec::new();
+
+		while let Some(argument) = arguments.next() {
+			// `--flag=value` is the same as `--flag value`.
+			let (flag, inline) = match argument.split_once('=') {
+				Some((flag, value)) if argument.starts_with("--") =>
+					(flag.to_owned(), Some(value.to_owned())),
+				_ => (argument.clone(), None)
+			};
+			let mut value = || inline.clone().or_else(|| arguments.next())
+				.unwrap_or_else(|| usage(format!("{} requires a value", flag)));
+
+			match &*flag {
+				"-h" | "--help" => {
+					print!("{}", USAGE);
+					exit(0)
+				},
+				"-V" | "--version" => {
+					println!("rustscript {}", env!("CARGO_PKG_VERSION"));
+					exit(0)
+				},
+				"--explain" => explain(&value()),
+
+				"-o" | "--output" => output = Some(value()),
+				"--out-dir" => out_dir = Some(value()),
+				"--target" => target = match &*value() {
+					"js" => ModuleSystem::EcmaScript,
+					"commonjs" => ModuleSystem::CommonJS,
+					target => usage(format!("unknown target {:?}", target))
+				},
+				"--emit" => emit = Some(value().split(',')
+					.filter_map(|kind| match (Emit::from_name(kind), Dump::from_name(kind)) {
+						(Some(kind), _) => Some(kind),
+						// Stages were once emitted rather than dumped.
+						(None, Some(stage)) => {
+							warnings.push(Diagnostic {
+								severity: Severity::Warning,
+								message: format!(
+									"`--emit {0}` is deprecated; use `--dump {0}` instead", kind)
+							});
+							dump.push(stage);
+							None
+						},
+						(None, None) => usage(format!("unknown output {:?}", kind))
+					})
+					.collect::<Vec<_>>()),
+				"--readable" => layout = Layout::Readable,
+				"--source-map" => {
+					let kind = value();
+					source_maps = Some(SourceMaps::from_name(&kind)
+						.unwrap_or_else(|| usage(format!("unknown source map kind {:?}", kind))))
+				},
+				"--overflow-checks" => overflow = Overflow::Check,
+				"--dump" => dump.extend(value().split(',')
+					.map(|stage| Dump::from_name(stage)
+						.unwrap_or_else(|| usage(format!("unknown stage {:?}", stage))))),
+				"--dump-format" => dump_format = match &*value() {
+					"pretty" => DumpFormat::Pretty,
+					"json" => DumpFormat::Json,
+					format => usage(format!("unknown dump format {:?}", format))
+				},
+				"--dump-dir" => dump_dir = Some(PathBuf::from(value())),
+				"-w" | "--watch" => watch = true,
+				"--node" => node = true,
+				"--check" => check = true,
+				"--indent" => indent = Some(match &*value() {
+					"tab" => Indent::Tabs,
+					spaces => Indent::Spaces(spaces.parse()
+						.unwrap_or_else(|_| usage(format!("invalid indentation {:?}", spaces))))
+				}),
+				"--width" => width = Some(value().parse::<usize>()
+					.unwrap_or_else(|error| usage(format!("invalid width: {}", error)))),
+				"-q" | "--quiet" => verbosity = Verbosity::Quiet,
+				"-v" | "--verbose" => verbosity = Verbosity::Verbose,
+
+				"--" => {
+					program.extend(arguments.by_ref());
+					break
+				},
+				flag if flag.starts_with('-') && flag != "-" =>
+					usage(format!("unknown option {:?}", flag)),
+
+				_ if command.is_none() => command = Some(match &*argument {
+					"build" => Command::Build,
+					"check" => Command::Check,
+					"run" => Command::Run,
+					"fmt" => Command::Fmt,
+					"emit" => Command::Emit,
+					"repl" => Command::Repl,
+					"lsp" => Command::Lsp,
+					command => usage(format!("unknown command {:?}", command))
+				}),
+				_ if input.is_none() => input = Some(argument),
+				_ => usage(format!("unexpected argument {:?}", argument))
+			}
+		}
+
+		let command = command.unwrap_or_else(|| {
+			eprint!("{}", USAGE);
+			exit(USAGE_ERROR)
+		});
+		let input = match (input, command) {
+			(Some(input), Command::Repl | Command::Lsp) =>
+				usage(format!("unexpected argument {:?}", input)),
+			(None, Command::Repl | Command::Lsp) => None,
+			(Some(input), _) => (input != "-").then(|| PathBuf::from(input)),
+			(None, _) => usage("no input was given")
+		};
+
+		let emit = emit.unwrap_or_else(|| match command {
+			Command::Build | Command::Emit => vec![Emit::Js],
+			Command::Check | Command::Run | Command::Fmt | Command::Repl | Command::Lsp =>
+				Vec::new()
+		});
+		if matches!(command, Command::Check | Command::Run)
+				&& !emit.is_empty()
+			{usage(format!("{:?} does not write JavaScript", command).to_lowercase())}
+		if layout == Layout::Readable && !matches!(command, Command::Build | Command::Emit)
+			{usage("only `build` and `emit` can write --readable JavaScript")}
+		if source_maps.is_some() && !matches!(command, Command::Build | Command::Emit)
+			{usage("only `build` and `emit` can write a --source-map")}
+		if overflow == Overflow::Check
+				&& !matches!(command, Command::Build | Command::Emit)
+				&& !(command == Command::Run && node)
+			{usage("only `build`, `emit` and `run --node` can use --overflow-checks")}
+		if watch && command != Command::Build {usage("only `build` can --watch")}
+		if watch && input.is_none() {usage("standard input cannot be watched")}
+		if node && command != Command::Run {usage("only `run` can use --node")}
+		if (check || indent.is_some() || width.is_some()) && command != Command::Fmt
+			{usage("only `fmt` can use --check, --indent and --width")}
+		let style = Style {
+			indent: indent.unwrap_or(Style::default().indent),
+			width: width.unwrap_or(Style::default().width)
+		};
+
+		// Without an output, the crate root's JavaScript is written next to it.
+		let stem = input.as_deref()
+			.and_then(Path::file_stem)
+			.unwrap_or_else(|| "main".as_ref());
+		let output = match (output, out_dir) {
+			(Some(_), Some(_)) => usage("--output and --out-dir cannot both be used"),
+			(Some(output), None) => (output != "-").then(|| PathBuf::from(output)),
+			(None, Some(directory)) =>
+				Some(Path::new(&directory).join(stem).with_extension("js")),
+			(None, None) if command == Command::Emit => None,
+			(None, None) => input.as_ref().map(|input| input.with_extension("js"))
+		};
+
+		Self {command, input, output, target, emit, layout, source_maps, overflow, dump,
+			dump_format, dump_dir, watch, node, check, style, verbosity, arguments: program,
+			warnings}
+	}
+
+	/// Writes a dumped stage of compilation to the dump directory, if there is
+	/// one, or to standard output.
+	fn dump(&self, stage: Dump, contents: &str) {
+		match &self.dump_dir {
+			Some(directory) => {
+				let stem = self.input.as_deref()
+					.and_then(Path::file_stem)
+					.map(|stem| stem.to_string_lossy().into_owned())
+					.unwrap_or_else(|| "main".into());
+				let file = directory.join(format!("{}.{}.{}", stem, stage.name(),
+					self.dump_format.extension()));
+				self.log(format_args!("writing {}", file.display()));
+				write_file(&file, contents);
+			},
+			None => println!("{}", contents.trim_end())
+		}
+	}
+
+	/// Prints a diagnostic, if the verbosity allows it.
+	fn report(&self, diagnostic: &Diagnostic) {
+		match (diagnostic.severity, self.verbosity) {
+			(Severity::Note, Verbosity::Verbose)
+				| (Severity::Warning, Verbosity::Normal | Verbosity::Verbose) =>
+				eprintln!("{}", diagnostic),
+			_ => ()
+		}
+	}
+
+	/// Describes a stage of compilation, if asked to.
+	fn log(&self, message: impl Display) {
+		if self.verbosity == Verbosity::Verbose {eprintln!("{}", message)}
+	}
+
+	/// Describes what `--watch` is doing, unless asked to be quiet.
+	fn status(&self, message: impl Display) {
+		if self.verbosity != Verbosity::Quiet {eprintln!("{}", message)}
+	}
+
+	/// Writes the crate's dumps and files.
+	fn write(&self, output: &Output) {
+		output.dumps.iter().for_each(|(stage, contents)| self.dump(*stage, contents));
+
+		match &self.output {
+			// Every module is written next to the crate root's output.
+			Some(file) => {
+				let directory = file.parent().unwrap_or_else(|| Path::new(""));
+				output.files.iter().for_each(|file| {
+					let path = directory.join(&file.path);
+					self.log(format_args!("writing {}", path.display()));
+					write_file(&path, &file.contents);
+				});
+			},
+
+			None => {
+				let several = output.files.len() > 1;
+				output.files.iter().for_each(|file| {
+					let contents = file.contents.strip_suffix('\n').unwrap_or(&file.contents);
+					match several {
+						true => println!("// {}\n{}", file.path, contents),
+						false => println!("{}", contents)
+					}
+				});
+			}
+		}
+	}
+}
 
 fn main() {
-	let mut args = args();
-	args.next();
-	let input = args.next().unwrap();
-	let output = args.next().unwrap();
+	let options = Options::parse();
+	if options.command == Command::Fmt {format(&options)}
+	if options.command == Command::Repl {
+		repl::run(options.target)
+			.unwrap_or_else(|error| io_error("could not read standard input", error));
+		exit(0)
+	}
+	if options.command == Command::Lsp {
+		// A server that exits without being shut down first has failed.
+		match lsp::run() {
+			Ok(shut_down) => exit(if shut_down {0} else {FAILURE}),
+			Err(error) => io_error("could not communicate with the client", error)
+		}
+	}
+
+	// Programs are run as CommonJS, which Node.js runs without configuration.
+	let (target, root, emit) = match options.command {
+		Command::Run if options.node =>
+			(ModuleSystem::CommonJS, Some("main.js".into()), vec![Emit::Js]),
+		Command::Run => (options.target, None, Vec::new()),
+		_ => (options.target, options.output.as_deref().map(file_name), options.emit.clone())
+	};
+	let mut compiler = Compiler::new(compiler::Options {
+		target,
+		root,
+		emit,
+		layout: options.layout,
+		source_maps: options.source_maps,
+		directory: options.output.as_deref().and_then(Path::parent).map(Path::to_path_buf),
+		overflow: options.overflow,
+		dump: options.dump.clone(),
+		dump_format: options.dump_format,
+		call_main: options.command == Command::Run
+	}).with_diagnostics(|diagnostic| options.report(diagnostic));
+	if options.watch {watch(&options, &mut compiler)}
+
+	// The input is the crate root; every module it declares is loaded with it.
+	// Standard input is kept for as long as the output, which borrows from it.
+	let source;
+	let output = match &options.input {
+		Some(input) => compiler.compile_file(input),
+		None => {
+			source = io::read_to_string(stdin())
+				.unwrap_or_else(|error| io_error("could not read standard input", error));
+			compiler.compile_str(&source, Path::new(""))
+		}
+	}.unwrap_or_else(|failure| fail(failure));
+	drop(compiler);
+
+	match options.command {
+		Command::Check => {
+			output.dumps.iter().for_each(|(stage, contents)| options.dump(*stage, contents));
+			options.log("no errors found")
+		},
+
+		Command::Build | Command::Emit => options.write(&output),
+
+		Command::Run if !options.node => {
+			output.dumps.iter().for_each(|(stage, contents)| options.dump(*stage, contents));
+
+			options.log("running with the interpreter");
+			let mut interpreter = Interpreter::new(&output.code);
+			interpreter.load()
+				.and_then(|_| interpreter.call("main", Vec::new()))
+				.unwrap_or_else(|error| {
+					eprintln!("{}", error);
+					exit(FAILURE)
+				});
+		},
+
+		Command::Run => {
+			output.dumps.iter().for_each(|(stage, contents)| options.dump(*stage, contents));
+
+			let directory = temp_dir().join(format!("rustscript-{}", process::id()));
+			output.files.iter().for_each(|file|
+				write_file(&directory.join(&file.path), &file.contents));
+
+			options.log("running with node");
+			let status = process::Command::new("node")
+				.arg(directory.join(&output.root))
+				.args(&options.arguments)
+				.status();
+			remove_dir_all(&directory).ok();
+
+			match status {
+				Ok(status) => exit(status.code().unwrap_or(FAILURE)),
+				Err(error) => io_error("could not run node", error)
+			}
+		},
+
+		Command::Fmt | Command::Repl | Command::Lsp => unreachable!()
+	}
+}
+
+/// Formats the crate root and every module file it loads in place, or standard
+/// input to standard output, and exits. With `--check`, the files that are not
+/// formatted are listed instead, and the exit status is a failure if there are
+/// any.
+fn format(options: &Options) -> ! {
+	let formatted = |source: &str| format::format(source, &options.style)
+		.unwrap_or_else(|error| fail(Failure::Error(error)));
+
+	let unformatted = match &options.input {
+		Some(input) => {
+			let files = format::files(input).unwrap_or_else(|error| match metadata(input) {
+				Err(cause) => fail(Failure::Io(input.clone(), cause)),
+				Ok(_) => fail(Failure::Error(error))
+			});
+
+			files.iter().filter(|file| {
+				let source = read_to_string(file)
+					.unwrap_or_else(|cause| fail(Failure::Io(file.to_path_buf(), cause)));
+				let formatted = formatted(&source);
+				if formatted == source {return false}
+
+				match options.check {
+					true => println!("{}", file.display()),
+					false => {
+						options.log(format_args!("formatting {}", file.display()));
+						write_file(file, &formatted);
+					}
+				}
+				true
+			}).count()
+		},
+
+		None => {
+			let source = io::read_to_string(stdin())
+				.unwrap_or_else(|error| io_error("could not read standard input", error));
+			let formatted = formatted(&source);
+			match options.check {
+				true if formatted != source => println!("<stdin>"),
+				true => (),
+				false => print!("{}", formatted)
+			}
+			(formatted != source) as usize
+		}
+	};
+
+	exit(if options.check && unformatted > 0 {FAILURE} else {0})
+}
+
+/// Builds the crate whenever a file it is loaded from changes, reporting errors
+/// without stopping, until interrupted.
+fn watch(options: &Options, compiler: &mut Compiler) -> ! {
+	let input = options.input.as_deref().expect("watched crates are read from files");
+	loop {
+		match compiler.compile_file(input) {
+			Ok(output) => {
+				options.write(&output);
+				options.status(format_args!("built {}", input.display()));
+			},
+			Err(failure) => report(&failure)
+		}
+
+		let sources = compiler.sources();
+		options.status(format_args!("watching {} for changes",
+			match sources.len() {
+				1 => "1 file".into(),
+				count => format!("{} files", count)
+			}));
+		wait_for_change(sources);
+		options.status("\nchange detected, rebuilding");
+	}
+}
+
+/// Waits for any of `files` to change, and then for them to stop changing, so
+/// that a burst of writes (such as an editor saving several files) causes a
+/// single rebuild.
+fn wait_for_change(files: &BTreeSet<PathBuf>) {
+	let mut changes = Changes::new(modification_times(files));
+	loop {
+		sleep(POLL_INTERVAL);
+		if changes.settled(modification_times(files)) {return}
+	}
+}
+
+/// When each of `files` was last modified, or `None` for those that do not
+/// exist, so that a file being created or deleted is a change.
+fn modification_times(files: &BTreeSet<PathBuf>) -> Vec<Option<SystemTime>> {
+	files.iter()
+		.map(|file| metadata(file).and_then(|metadata| metadata.modified()).ok())
+		.collect()
+}
+
+/// Follows the modification times of a set of files from one poll to the next.
+struct Changes {
+	last: Vec<Option<SystemTime>>,
+	changed: bool
+}
+
+impl Changes {
+	fn new(times: Vec<Option<SystemTime>>) -> Self {
+		Self {last: times, changed: false}
+	}
+
+	/// Records the times found by another poll. Returns whether the files have
+	/// changed since the first poll, and have then not changed since the last.
+	fn settled(&mut self, times: Vec<Option<SystemTime>>) -> bool {
+		let same = times == self.last;
+		self.last = times;
+		match same {
+			true => self.changed,
+			false => {
+				self.changed = true;
+				false
+			}
+		}
+	}
+}
+
+fn file_name(path: &Path) -> String {
+	path.file_name()
+		.map(|name| name.to_string_lossy().into_owned())
+		.unwrap_or_else(|| usage(format!("{:?} is not a file", path)))
+}
+
+fn write_file(file: &Path, contents: &str) {
+	file.parent()
+		.map(create_dir_all).transpose()
+		.and_then(|_| write(file, contents))
+		.unwrap_or_else(|error| io_error(format!("could not write {:?}", file), error))
+}
+
+/// Prints the explanation of an error code, and exits.
+fn explain(code: &str) -> ! {
+	match ErrorCode::from_name(&code.to_uppercase()) {
+		Some(code) => {
+			print!("{}", code.explanation());
+			exit(0)
+		},
+		None => usage(format!("{:?} is not an error code", code))
+	}
+}
+
+/// Reports why the crate could not be compiled, and exits.
+fn fail(failure: Failure) -> ! {
+	report(&failure);
+	exit(match failure {
+		Failure::Io(..) => IO_ERROR,
+		Failure::Error(_) => FAILURE
+	})
+}
+
+/// Reports why the crate could not be compiled.
+fn report(failure: &Failure) {
+	eprintln!("{}", failure);
+	if let Failure::Error(error) = failure {
+		eprintln!("For more information about this error, try `rustscript --explain {}`.",
+			error.code);
+	}
+}
+
+fn usage(message: impl Display) -> ! {
+	eprintln!("error: {}", message);
+	eprintln!("For more information, try `rustscript --help`.");
+	exit(USAGE_ERROR)
+}
+
+fn io_error(message: impl Display, error: io::Error) -> ! {
+	eprintln!("error: {}: {}", message, error);
+	exit(IO_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{modification_times, Changes, Command, Options};
+	use rustscript::compiler::{Dump, Emit};
+	use std::{collections::BTreeSet, env::temp_dir, fs::{remove_file, write}, process,
+		time::{Duration, SystemTime}};
+
+	fn parse(arguments: &str) -> Options {
+		Options::from_arguments(arguments.split_whitespace().map(Into::into))
+	}
+
+	#[test]
+	fn stages_may_still_be_emitted() {
+		let options = parse("emit --emit tokens,js,main-ir main.rsst");
+		assert_eq!(options.command, Command::Emit);
+		assert_eq!(options.emit, [Emit::Js]);
+		assert_eq!(options.dump, [Dump::Tokens, Dump::MainIr]);
+		assert_eq!(options.warnings.len(), 2);
+		assert!(options.warnings[0].message.contains("--dump tokens"));
+
+		let options = parse("check --emit ast main.rsst");
+		assert!(options.emit.is_empty());
+		assert_eq!(options.dump, [Dump::Ast]);
+
+		let options = parse("build --emit js,declarations --dump js-ir main.rsst");
+		assert_eq!(options.emit, [Emit::Js, Emit::Declarations]);
+		assert_eq!(options.dump, [Dump::JsIr]);
+		assert!(options.warnings.is_empty());
+	}
 
-	let input = read_to_string(input).unwrap();
+	#[test]
+	fn changes_settle_once_files_stop_changing() {
+		let time = |seconds| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+		let mut changes = Changes::new(vec![time(1), None]);
+		assert!(!changes.settled(vec![time(1), None]));
+		assert!(!changes.settled(vec![time(2), None]));
+		assert!(!changes.settled(vec![time(2), time(3)]));
+		assert!(changes.settled(vec![time(2), time(3)]));
 
-	let block = Parser::new(Tokenizer::new(input.chars())).parse_block();
-	println!("FRONTEND IR: {:#?}", block);
+		let mut changes = Changes::new(vec![time(1)]);
+		assert!(!changes.settled(vec![None]));
+		assert!(changes.settled(vec![None]));
+	}
 
-	let scope = Scope::new();
-	let code = construct_main_representation(&block, ScopeRef::new(&scope));
-	println!("MAIN IR: {:#?}", code);
+	#[test]
+	fn files_being_created_and_deleted_are_changes() {
+		let file = temp_dir().join(format!("rustscript-watch-{}.rsst", process::id()));
+		let files = BTreeSet::from([file.clone()]);
+		assert_eq!(modification_times(&files), [None]);
 
-	let block = from_main_representation(&code);
-	println!("JAVASCRIPT BACKEND IR: {:#?}", block);
+		write(&file, "fn main() {}").unwrap();
+		let mut changes = Changes::new(vec![None]);
+		let created = modification_times(&files);
+		assert!(created[0].is_some());
+		assert!(!changes.settled(created.clone()));
+		assert!(changes.settled(created));
 
-	write(output, format!("{}", block)).unwrap();
+		remove_file(&file).unwrap();
+		assert!(!changes.settled(modification_times(&files)));
+	}
 }
@@ -0,0 +1,308 @@
+//! An interactive session, in which items are declared and expressions are
+//! evaluated one input at a time. Every input is checked along with all of
+//! the items entered before it, as if they were one crate root. Variables are
+//! only checked once: later inputs see them as their types and values.
+
+use crate::{backend::{interpreter::{Interpreter, Value}, javascript::{self, integer::Overflow,
+	ModuleSystem}},
+	frontend::{self, construct_expression, construct_main_representation,
+		error::{error, Error, ErrorCode::*}, module::{Declarations, ModuleRef},
+		parser::{self, Block, ForeignItem, Parser, Statement},
+		tokenizer::{Located, Token, Tokenizer}, Code, Expression, Scope, ScopeRef, TypeItem,
+		TypeName}};
+use std::{collections::HashMap, io::{self, BufRead, Write}};
+
+const HELP: &str = "\
+Enter items (`data`, `fn`, `let`, `use`, `extern` and `mod` with a body) and
+expressions. Items entered again replace the ones entered before.
+
+Commands:
+  :type <EXPRESSION>  Print the type of an expression without evaluating it
+  :ast <INPUT>        Print the syntax tree of an input
+  :js <EXPRESSION>    Print the JavaScript an expression compiles to
+  :help               Print this help
+  :quit               Leave the session";
+
+/// A crate root built up one input at a time.
+pub struct Session {
+	/// Every item entered so far, in the order it was entered.
+	items: Vec<Statement<'static>>,
+	/// The type and value of every variable entered so far.
+	variables: HashMap<Box<str>, (TypeName<'static>, Value)>,
+	target: ModuleSystem
+}
+
+impl Session {
+	pub fn new(target: ModuleSystem) -> Self {
+		Self {items: Vec::new(), variables: HashMap::new(), target}
+	}
+
+	/// Whether more lines are needed to complete `input`, because it has
+	/// unclosed braces, parentheses or strings.
+	pub fn is_incomplete(input: &str) -> bool {
+		let depth = Tokenizer::new(input).try_fold(0, |depth, token| Ok::<_, Error>(match token? {
+			Token::BraceLeft | Token::ParenLeft => depth + 1,
+			Token::BraceRight | Token::ParenRight => depth - 1,
+			_ => depth
+		}));
+
+		match depth {
+			Ok(depth) => depth > 0,
+			Err(error) => error.code == Unterminated
+		}
+	}
+
+	/// Runs an input, or a command if it starts with `:`, returning what to
+	/// print.
+	pub fn run(&mut self, input: &str) -> String {
+		let input = input.trim();
+		let (command, argument) = match input.strip_prefix(':') {
+			Some(command) => command.split_once(char::is_whitespace)
+				.map(|(command, argument)| (Some(command), argument.trim()))
+				.unwrap_or((Some(command), "")),
+			None => (None, input)
+		};
+
+		let result = match command {
+			None if input.is_empty() => Ok(String::new()),
+			None => Ok(self.enter(input)),
+			Some("type" | "t") => self.check(argument)
+				.map(|(_, expression)| expression.r#type().to_string()),
+			Some("ast") => parse(argument).map(|(block, expression)| {
+				let block = (!block.0.is_empty()).then(|| format!("{:#?}", block));
+				let expression = expression.map(|expression| format!("{:#?}", expression));
+				block.into_iter().chain(expression).collect::<Vec<_>>().join("\n")
+			}),
+			Some("js") => self.check(argument).map(|(code, expression)|
+				javascript::from_root_expression(&expression, &code, "main.js", self.target,
+					Overflow::default())
+					.to_string()),
+			Some("help" | "h") => Ok(HELP.into()),
+			Some(command) => Ok(format!("error: unknown command `:{}`; try `:help`", command))
+		};
+
+		result.unwrap_or_else(|error| error.to_string())
+	}
+
+	/// Declares the items of an input and runs its statements and evaluates its
+	/// expression, if it has one. Nothing is kept if any of it fails.
+	fn enter(&mut self, input: &str) -> String {
+		let (block, expression) = match parse(input) {
+			Ok(input) => input,
+			Err(error) => return error.to_string()
+		};
+		// Items replace those declaring the same names, which would otherwise be
+		// duplicates, and variables of the types they replace are dropped.
+		let replaced = block.0.iter().flat_map(declared_names).collect::<Vec<_>>();
+		let mut items = self.items.clone();
+		replaced.iter().for_each(|name| remove(&mut items, name));
+		items.extend(block.0.iter().cloned());
+		let mut variables = self.variables.clone();
+		variables.retain(|_, (r#type, _)| !matches!(&r#type.item,
+			TypeItem::Module(path) if replaced.contains(&&*path[0])));
+
+		let (code, expression) = match construct(&items, &variables, expression.as_ref()) {
+			Ok(checked) => checked,
+			Err(error) => return error.to_string()
+		};
+
+		let mut interpreter = Interpreter::new(&code);
+		variables.iter().for_each(|(name, (_, value))| interpreter.define(name, value.clone()));
+
+		let mut lines = Vec::new();
+		for statement in &code.statements {
+			let value = match interpreter.evaluate(statement.expression()) {
+				Ok(value) => value,
+				Err(error) => return error.to_string()
+			};
+			match statement {
+				frontend::Statement::Variable(variable) => {
+					lines.push(format!("{}: {} = {}",
+						variable.name, variable.r#type.name, value));
+					interpreter.define(&variable.name, value);
+				},
+				frontend::Statement::Expression {expression, ..} =>
+					lines.push(format!("{}: {}", value, expression.r#type()))
+			}
+		}
+		if let Some(expression) = &expression {
+			match interpreter.evaluate(expression) {
+				Ok(value) => lines.push(format!("{}: {}", value, expression.r#type())),
+				Err(error) => return error.to_string()
+			}
+		}
+
+		let declared = code.statements.iter().filter_map(frontend::Statement::variable_ref);
+		declared.for_each(|variable| {
+			let value = interpreter.value(&variable.name).unwrap().clone();
+			variables.insert(Box::from(&*variable.name), (variable.r#type.clone(), value));
+		});
+		// Statements have been run, and variables are kept as their values.
+		items.retain(|item|
+			!matches!(item, Statement::LetItem(..) | Statement::Expression(..)));
+		self.items = items;
+		self.variables = variables;
+		lines.join("\n")
+	}
+
+	/// Checks an expression against the items entered so far.
+	fn check(&self, input: &str) -> Result<(Code<'static>, Expression<'static>), Error> {
+		match parse(input)? {
+			(block, Some(expression)) if block.0.is_empty() =>
+				construct(&self.items, &self.variables, Some(&expression))
+					.map(|(code, expression)| (code, expression.unwrap())),
+			_ => Err(error(UnexpectedToken, "expected an expression"))
+		}
+	}
+}
+
+/// Constructs the main representation of a crate root made of `items`, and of
+/// an expression at the end of it. The variables entered before are declared
+/// around the crate root, as they are not checked again.
+fn construct(items: &[Statement<'static>],
+		variables: &HashMap<Box<str>, (TypeName<'static>, Value)>,
+		expression: Option<&parser::Expression<'static>>)
+		-> Result<(Code<'static>, Option<Expression<'static>>), Error> {
+	let block = Block(items.to_vec());
+	let declarations = Declarations::declare(&block)?;
+	let mut prelude = Scope::prelude();
+	prelude.values.extend(variables.iter().map(|(name, (r#type, _))| {
+		let value = frontend::Value {r#type: r#type.clone(), foreign: None};
+		(String::from(&**name).into(), value)
+	}));
+	let code = construct_main_representation(&block, ScopeRef::new(&prelude),
+		ModuleRef::new(&declarations))?;
+	let expression = expression
+		.map(|expression| construct_expression(expression,
+			ScopeRef::new(&prelude).r#in(&code.scope), ModuleRef::new(&declarations)))
+		.transpose()?;
+
+	Ok((code, expression))
+}
+
+/// Parses an input: items and statements, followed by an expression, which may
+/// end with a semicolon. Names are owned, as the items entered outlive the
+/// input they were entered in.
+fn parse(input: &str) -> Result<(Block<'static>, Option<parser::Expression<'static>>), Error> {
+	let mut parser = Parser::new(Located::new(input)
+		.map(|token| token.map(|(span, token)| (span, token.into_owned()))));
+	let (mut block, expression) = parser.parse_block_with_expression()?;
+	parser.parse_end()?;
+	let expression = match (expression, block.0.last()) {
+		(None, Some(Statement::Expression(..))) => match block.0.pop() {
+			Some(Statement::Expression(_, expression)) => Some(expression),
+			_ => unreachable!()
+		},
+		(expression, _) => expression
+	};
+
+	// Without a file to be in, a module cannot be loaded from one.
+	if let Some((location, module)) = block.0.iter()
+			.filter_map(|statement| Some((statement.location(), statement.mod_item_ref()?)))
+			.find(|(_, module)| module.body.is_none()) {
+		return Err(error(ModuleNotFound, format!(
+			"module {:?} cannot be loaded from a file in the REPL; give it a body instead",
+			module.name)).at(location..location))
+	}
+
+	Ok((block, expression))
+}
+
+/// The names an item declares, which no other item may share.
+fn declared_names<'a>(statement: &'a Statement) -> Vec<&'a str> {
+	match statement {
+		Statement::DataItem(_, data) => vec![&**data.name()],
+		Statement::FunctionItem(_, function) => vec![&*function.name],
+		Statement::ModItem(_, module) => vec![&*module.name],
+		Statement::UseItem(_, r#use) => vec![&**r#use.name()],
+		Statement::ExternItem(r#extern) =>
+			r#extern.items.iter().map(|(_, item)| &**item.name()).collect(),
+		_ => Vec::new()
+	}
+}
+
+/// Removes every item declaring `name`, including those within extern blocks.
+fn remove(items: &mut Vec<Statement>, name: &str) {
+	items.iter_mut().for_each(|item| if let Statement::ExternItem(r#extern) = item {
+		r#extern.items.retain(|(_, item): &(_, ForeignItem)| item.name() != name)
+	});
+	items.retain(|item| !declared_names(item).contains(&name)
+		|| matches!(item, Statement::ExternItem(_)));
+}
+
+/// Runs a session on standard input, printing a prompt before each input.
+pub fn run(target: ModuleSystem) -> io::Result<()> {
+	let mut session = Session::new(target);
+	let (stdin, mut stdout) = (io::stdin(), io::stdout());
+	let mut lines = stdin.lock().lines();
+
+	println!("rustscript {} REPL; enter :help for help", env!("CARGO_PKG_VERSION"));
+	loop {
+		print!(">> ");
+		stdout.flush()?;
+
+		let mut input = match lines.next() {
+			Some(line) => line?,
+			None => break
+		};
+		while Session::is_incomplete(&input) {
+			print!(".. ");
+			stdout.flush()?;
+			match lines.next() {
+				Some(line) => input = input + "\n" + &line?,
+				None => break
+			}
+		}
+
+		if matches!(input.trim(), ":quit" | ":q") {break}
+		let output = session.run(&input);
+		if !output.is_empty() {println!("{}", output)}
+	}
+
+	println!();
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Session;
+	use crate::backend::javascript::ModuleSystem;
+
+	fn session(inputs: &[&str]) -> Vec<String> {
+		let mut session = Session::new(ModuleSystem::default());
+		inputs.iter().map(|input| session.run(input)).collect()
+	}
+
+	#[test]
+	fn variables_are_kept_between_inputs() {
+		assert_eq!(session(&["let x: i32 = 1;", "x", "let x: bool = true;", "x", ":type x"]),
+			["x: i32 = 1", "1: i32", "x: bool = true", "true: bool", "bool"]);
+	}
+
+	#[test]
+	fn failed_inputs_leave_nothing_behind() {
+		let outputs = session(&["let x: i32 = 1;", "let x: bool = true; f()", "x",
+			"let y: i32 = z;", "y"]);
+		assert_eq!(outputs[1], "error[RS0011]: unknown function \"f\"");
+		assert_eq!(outputs[2], "1: i32");
+		assert!(outputs[4].starts_with("error[RS0010]: unknown value \"y\""));
+	}
+
+	#[test]
+	fn variables_are_not_checked_again() {
+		// Redeclaring a global would otherwise be a mismatched type in `y`.
+		assert_eq!(session(&["extern \"js\" {let g: i32;}", "let y: i32 = g;",
+			"extern \"js\" {let g: bool;}", "y", ":type g"]),
+			["", "y: i32 = <g>", "", "<g>: i32", "bool"]);
+	}
+
+	#[test]
+	fn variables_of_replaced_types_are_dropped() {
+		let outputs = session(&["extern \"js\" {data T; let t: T;}", "let v: T = t;",
+			"extern \"js\" {data T;}", "v", "t"]);
+		assert_eq!(outputs[1], "v: T = <t>");
+		assert!(outputs[3].starts_with("error[RS0010]: unknown value \"v\""));
+		// The global, an item, is checked again against the new type.
+		assert_eq!(outputs[4], "<t>: T");
+	}
+}